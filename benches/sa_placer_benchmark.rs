@@ -8,17 +8,17 @@ fn sa_placer_small_benchmark(c: &mut Criterion) {
     // let initial_solution = gen_random_placement(layout, netlist);
     let layout = black_box(build_simple_fpga_layout(64, 64));
     let netlist: NetlistGraph = black_box(build_simple_netlist(300, 30, 100));
-    let initial_solution = black_box(gen_random_placement(&layout, &netlist));
+    let initial_solution = black_box(gen_random_placement(&layout, &netlist).unwrap());
 
     c.bench_function("fast_sa_placer_small", |b| {
-        b.iter(|| fast_sa_placer(initial_solution.clone(), 500, 16, false, false))
+        b.iter(|| fast_sa_placer(initial_solution.clone(), 500, 16, false, false, None))
     });
 }
 
 fn sa_placer_large_benchmark(c: &mut Criterion) {
     let layout = build_simple_fpga_layout(200, 200);
     let netlist = build_simple_netlist(1000, 50, 200);
-    let initial_solution = black_box(gen_random_placement(&layout, &netlist));
+    let initial_solution = black_box(gen_random_placement(&layout, &netlist).unwrap());
 
     c.bench_function("fast_sa_placer_large", |b| {
         b.iter(|| {
@@ -28,14 +28,27 @@ fn sa_placer_large_benchmark(c: &mut Criterion) {
                 black_box(16),
                 false,
                 false,
+                None,
             )
         })
     });
 }
 
+fn cost_bb_large_benchmark(c: &mut Criterion) {
+    let layout = build_simple_fpga_layout(200, 200);
+    let netlist = build_simple_netlist(1000, 50, 200);
+    let solution = black_box(gen_random_placement(&layout, &netlist).unwrap());
+
+    assert_eq!(solution.cost_bb(), solution.cost_bb_simd());
+
+    c.bench_function("cost_bb_large", |b| b.iter(|| solution.cost_bb()));
+    c.bench_function("cost_bb_simd_large", |b| b.iter(|| solution.cost_bb_simd()));
+}
+
 criterion_group!(
     benches,
     sa_placer_small_benchmark,
-    sa_placer_large_benchmark
+    sa_placer_large_benchmark,
+    cost_bb_large_benchmark
 );
 criterion_main!(benches);
@@ -0,0 +1,261 @@
+// quadratic (analytical) placement used to seed the annealer
+//
+// every net is modeled as a clique of springs between its pins, each spring
+// weighing net weight / (pins - 1), and the placement minimizing the summed
+// squared spring lengths solves L x = b_x and L y = b_y, where L is the
+// Laplacian over the movable nodes and b holds the pulls of the IO anchors
+// the continuous solution is then rounded to sites and legalized
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rustc_hash::FxHashMap;
+
+use super::fpga_layout::*;
+use super::netlist::*;
+use super::placer::*;
+
+// conjugate gradient stops once the residual is below this fraction of the
+// right hand side, well under a site since the result is rounded anyway
+pub const CG_TOLERANCE: f64 = 1e-6;
+// upper bound on conjugate gradient iterations, CG converges in at most n
+// iterations in exact arithmetic and far fewer on these well conditioned systems
+pub const CG_MAX_ITERATIONS: usize = 1000;
+// weak spring from every movable node to the middle of the fabric, keeps L
+// positive definite when a group of nodes has no path to an IO anchor (or the
+// netlist has no IOs at all) so those nodes settle around the center instead
+// of the solve failing
+pub const CENTER_PULL: f64 = 1e-3;
+
+// symmetric positive definite matrix stored as its diagonal and the off
+// diagonal entries of every row
+struct SparseSpd {
+    diag: Vec<f64>,
+    off_diag: Vec<Vec<(usize, f64)>>,
+}
+
+impl SparseSpd {
+    fn mul(&self, v: &[f64]) -> Vec<f64> {
+        (0..self.diag.len())
+            .map(|i| {
+                self.diag[i] * v[i]
+                    + self.off_diag[i]
+                        .iter()
+                        .map(|(j, value)| value * v[*j])
+                        .sum::<f64>()
+            })
+            .collect()
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+// solve a x = b starting from x
+fn conjugate_gradient(a: &SparseSpd, b: &[f64], mut x: Vec<f64>) -> Vec<f64> {
+    let ax = a.mul(&x);
+    let mut r = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect::<Vec<_>>();
+    let mut p = r.clone();
+    let mut r_norm_sq = dot(&r, &r);
+    let threshold_sq = (CG_TOLERANCE * dot(b, b).sqrt()).powi(2);
+
+    for _ in 0..CG_MAX_ITERATIONS {
+        if r_norm_sq <= threshold_sq {
+            break;
+        }
+
+        let ap = a.mul(&p);
+        let alpha = r_norm_sq / dot(&p, &ap);
+        for i in 0..x.len() {
+            x[i] += alpha * p[i];
+            r[i] -= alpha * ap[i];
+        }
+
+        let new_r_norm_sq = dot(&r, &r);
+        let beta = new_r_norm_sq / r_norm_sq;
+        r_norm_sq = new_r_norm_sq;
+        for i in 0..p.len() {
+            p[i] = r[i] + beta * p[i];
+        }
+    }
+
+    x
+}
+
+pub fn gen_analytical_placement<'a>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    gen_analytical_placement_with_rng(layout, netlist, &mut rand::thread_rng())
+}
+
+// IO pads are the anchors of the solve, nothing pins them here so they are
+// placed on random legal IO sites first, every other node is placed by the
+// quadratic solve, rounded to the nearest coordinate and then moved onto a
+// legal site by legalize_min_displacement
+pub fn gen_analytical_placement_with_rng<'a, R: Rng + ?Sized>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+    rng: &mut R,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    gen_analytical_placement_from(PlacementSolution::new(layout, netlist), rng)
+}
+
+// gen_analytical_placement_with_rng starting from a solution whose pads may
+// already be placed, e.g. pinned by apply_io_constraints, placed IOs and
+// placed locked nodes stay where they are and anchor the solve, only the IOs
+// left unplaced go to random legal IO sites
+pub fn gen_analytical_placement_from<'a, R: Rng + ?Sized>(
+    mut solution: PlacementSolution<'a>,
+    rng: &mut R,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    let layout = solution.layout;
+    let netlist = solution.netlist;
+    check_site_counts(layout, netlist)?;
+
+    for node in netlist.all_nodes_sorted() {
+        if node.macro_type != MacroType::IO || solution.solution_map.contains_key(node) {
+            continue;
+        }
+        let location = *solution
+            .get_possible_sites_for(node)
+            .choose(rng)
+            .ok_or(InitialPlacementError::NoFreeSite(*node))?;
+        solution.place_node(*node, location);
+    }
+
+    let is_anchor = |node: &NetlistNode| {
+        node.macro_type == MacroType::IO
+            || (solution.locked.contains(node) && solution.solution_map.contains_key(node))
+    };
+    let movable = netlist
+        .all_nodes_sorted()
+        .into_iter()
+        .filter(|node| !is_anchor(node))
+        .copied()
+        .collect::<Vec<_>>();
+    let index = movable
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (*node, i))
+        .collect::<FxHashMap<_, _>>();
+
+    let center_x = layout.width.saturating_sub(1) as f64 / 2.0;
+    let center_y = layout.height.saturating_sub(1) as f64 / 2.0;
+    let mut laplacian = SparseSpd {
+        diag: vec![CENTER_PULL; movable.len()],
+        off_diag: vec![Vec::new(); movable.len()],
+    };
+    let mut b_x = vec![CENTER_PULL * center_x; movable.len()];
+    let mut b_y = vec![CENTER_PULL * center_y; movable.len()];
+    // (movable node, anchor pin, weight) springs
+    let mut anchors = Vec::new();
+
+    for (net, pins) in netlist.nets().iter().enumerate() {
+        if pins.len() < 2 {
+            continue;
+        }
+        let weight = solution.net_weight(net) as f64 / (pins.len() - 1) as f64;
+
+        for (a, pin_a) in pins.iter().enumerate() {
+            for pin_b in &pins[a + 1..] {
+                match (index.get(pin_a), index.get(pin_b)) {
+                    (Some(&i), Some(&j)) => {
+                        laplacian.diag[i] += weight;
+                        laplacian.diag[j] += weight;
+                        laplacian.off_diag[i].push((j, -weight));
+                        laplacian.off_diag[j].push((i, -weight));
+                    }
+                    (Some(&i), None) => anchors.push((i, *pin_b, weight)),
+                    (None, Some(&j)) => anchors.push((j, *pin_a, weight)),
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+
+    for (i, pad, weight) in anchors {
+        let location = solution.solution_map[&pad];
+        laplacian.diag[i] += weight;
+        b_x[i] += weight * location.x as f64;
+        b_y[i] += weight * location.y as f64;
+    }
+
+    let xs = conjugate_gradient(&laplacian, &b_x, vec![center_x; movable.len()]);
+    let ys = conjugate_gradient(&laplacian, &b_y, vec![center_y; movable.len()]);
+
+    for (i, node) in movable.iter().enumerate() {
+        let x = xs[i]
+            .round()
+            .clamp(0.0, layout.width.saturating_sub(1) as f64) as u32;
+        let y = ys[i]
+            .round()
+            .clamp(0.0, layout.height.saturating_sub(1) as f64) as u32;
+        solution.place_node(*node, FPGALayoutCoordinate::new(x, y));
+    }
+
+    // legalize only ever runs out of sites, AlreadyGrouped comes from add_relative_group
+    solution
+        .legalize_min_displacement()
+        .map_err(|err| match err {
+            LegalizeError::NoFreeSite(node) | LegalizeError::AlreadyGrouped(node) => {
+                InitialPlacementError::NoFreeSite(node)
+            }
+        })?;
+
+    assert!(solution.valid());
+
+    Ok(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn node(id: u32, macro_type: MacroType) -> NetlistNode {
+        NetlistNode { id, macro_type }
+    }
+
+    #[test]
+    fn pinned_pads_anchor_the_solve() {
+        let layout = build_simple_fpga_layout(12, 12);
+        // pad 0 - clb 1 - clb 2 - clb 3 - pad 4
+        let nodes = [
+            node(0, MacroType::IO),
+            node(1, MacroType::CLB),
+            node(2, MacroType::CLB),
+            node(3, MacroType::CLB),
+            node(4, MacroType::IO),
+        ];
+        let nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        let left = FPGALayoutCoordinate::new(0, 2);
+        let right = FPGALayoutCoordinate::new(11, 9);
+        solution
+            .apply_io_constraints(&[(0, left), (4, right)])
+            .unwrap();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let solution = gen_analytical_placement_from(solution, &mut rng).unwrap();
+
+        assert!(solution.valid());
+        assert_eq!(solution.solution_map[&nodes[0]], left);
+        assert_eq!(solution.solution_map[&nodes[4]], right);
+        assert!(solution.locked.contains(&nodes[0]));
+        // the chain is strung out between the two pads
+        let location = |id: usize| solution.solution_map[&nodes[id]];
+        assert!(location(1).x < location(2).x && location(2).x < location(3).x);
+        // springs of equal weight put the middle node halfway between the pads
+        assert!(location(2).manhattan_distance(&FPGALayoutCoordinate::new(5, 5)) <= 2);
+        assert!(location(1).manhattan_distance(&left) < location(1).manhattan_distance(&right));
+        assert!(location(3).manhattan_distance(&right) < location(3).manhattan_distance(&left));
+    }
+}
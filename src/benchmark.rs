@@ -0,0 +1,279 @@
+// loader for placement benchmarks stored as a directory:
+//
+//   <benchmark>/
+//     netlist.json   netlist in the NetlistGraph::from_json format
+//     fabric.json    { "width": 64, "height": 64, "fixed": [{ "id": 3, "x": 0, "y": 5 }] }
+//
+// the fabric is built with build_simple_fpga_layout(width, height) unless
+// fabric.json lists hard block columns, e.g.
+//   "columns": [{ "macro_type": "DSP", "first_col": 4, "stride": 8 }]
+// in which case it is the FabricSpec of that size with those columns
+// "fixed" lists nodes, usually IO pads, that have to sit on a given site
+// the directory name is used as the benchmark name
+// converted MCNC/ISPD circuits fit this format once their cells are mapped to
+// macro types and their multi-pin nets are broken into edges
+
+use std::path::Path;
+
+use rustc_hash::FxHashMap;
+use rustc_hash::FxHashSet;
+use serde::Deserialize;
+
+use super::fpga_layout::*;
+use super::netlist::*;
+use super::placer::*;
+
+#[derive(Debug)]
+pub enum BenchmarkError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    Netlist(NetlistJsonError),
+    UnknownMacroType(String),
+    Fabric(FabricSpecError),
+    // a fixed location refers to a node id that isn't in the netlist
+    UnknownNode(u32),
+    DuplicateFixedNode(u32),
+    // the fabric can't host the node at its fixed location or another node is fixed there
+    IllegalFixedLocation(u32, FPGALayoutCoordinate),
+}
+
+impl std::fmt::Display for BenchmarkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BenchmarkError::Io(err) => write!(f, "unable to read benchmark: {}", err),
+            BenchmarkError::Parse(err) => write!(f, "unable to parse fabric: {}", err),
+            BenchmarkError::Netlist(err) => write!(f, "{}", err),
+            BenchmarkError::UnknownMacroType(macro_type) => {
+                write!(f, "unknown macro type \"{}\"", macro_type)
+            }
+            BenchmarkError::Fabric(err) => write!(f, "{}", err),
+            BenchmarkError::UnknownNode(id) => {
+                write!(f, "fixed location refers to unknown node id {}", id)
+            }
+            BenchmarkError::DuplicateFixedNode(id) => {
+                write!(f, "node id {} is fixed twice", id)
+            }
+            BenchmarkError::IllegalFixedLocation(id, location) => write!(
+                f,
+                "node {} can't be fixed at ({}, {})",
+                id, location.x, location.y
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BenchmarkError {}
+
+impl From<std::io::Error> for BenchmarkError {
+    fn from(err: std::io::Error) -> Self {
+        BenchmarkError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for BenchmarkError {
+    fn from(err: serde_json::Error) -> Self {
+        BenchmarkError::Parse(err)
+    }
+}
+
+impl From<NetlistJsonError> for BenchmarkError {
+    fn from(err: NetlistJsonError) -> Self {
+        BenchmarkError::Netlist(err)
+    }
+}
+
+impl From<FabricSpecError> for BenchmarkError {
+    fn from(err: FabricSpecError) -> Self {
+        BenchmarkError::Fabric(err)
+    }
+}
+
+#[derive(Deserialize)]
+struct FabricJson {
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    columns: Option<Vec<ColumnJson>>,
+    #[serde(default)]
+    fixed: Vec<FixedLocationJson>,
+}
+
+#[derive(Deserialize)]
+struct ColumnJson {
+    macro_type: String,
+    first_col: u32,
+    stride: u32,
+}
+
+#[derive(Deserialize)]
+struct FixedLocationJson {
+    id: u32,
+    x: u32,
+    y: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    pub name: String,
+    pub layout: FPGALayout,
+    pub netlist: NetlistGraph,
+    pub fixed_locations: FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+}
+
+impl Benchmark {
+    pub fn load(dir: impl AsRef<Path>) -> Result<Benchmark, BenchmarkError> {
+        let dir = dir.as_ref();
+
+        let netlist = NetlistGraph::from_json(dir.join("netlist.json"))?;
+        let fabric_json: FabricJson =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("fabric.json"))?)?;
+        let layout = match fabric_json.columns {
+            None => build_simple_fpga_layout(fabric_json.width, fabric_json.height),
+            Some(columns) => {
+                let mut spec = FabricSpec::new(fabric_json.width, fabric_json.height);
+                for column in columns {
+                    let macro_type = column
+                        .macro_type
+                        .parse()
+                        .map_err(BenchmarkError::UnknownMacroType)?;
+                    spec = spec.with_column(macro_type, column.first_col, column.stride);
+                }
+                build_fpga_layout(&spec)?
+            }
+        };
+
+        let nodes_by_id = netlist
+            .graph
+            .node_weights()
+            .map(|node| (node.id, *node))
+            .collect::<FxHashMap<_, _>>();
+
+        let mut fixed_locations = FxHashMap::default();
+        let mut fixed_sites = FxHashSet::default();
+        for fixed in fabric_json.fixed {
+            let node = *nodes_by_id
+                .get(&fixed.id)
+                .ok_or(BenchmarkError::UnknownNode(fixed.id))?;
+            let location = FPGALayoutCoordinate::new(fixed.x, fixed.y);
+            if fixed_locations.contains_key(&node) {
+                return Err(BenchmarkError::DuplicateFixedNode(fixed.id));
+            }
+
+            if !layout
+                .get(&location)
+                .is_some_and(|layout_type| layout_type.accepts(node.macro_type))
+                || (node.macro_type == MacroType::IO
+                    && !netlist
+                        .io_direction(&node)
+                        .fits_site(layout.io_direction(&location)))
+                || !fixed_sites.insert(location)
+            {
+                return Err(BenchmarkError::IllegalFixedLocation(fixed.id, location));
+            }
+            fixed_locations.insert(node, location);
+        }
+
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(Benchmark {
+            name,
+            layout,
+            netlist,
+            fixed_locations,
+        })
+    }
+
+    // move the fixed nodes onto their sites, swapping out whatever the initial
+    // placement put there, and lock them so the placer leaves them there, see
+    // lock_nodes, on an error the solution is left as it was
+    pub fn apply_fixed_locations(
+        &self,
+        solution: &mut PlacementSolution,
+    ) -> Result<(), PlaceError> {
+        let mut fixed_locations = self
+            .fixed_locations
+            .iter()
+            .map(|(node, location)| (*node, *location))
+            .collect::<Vec<_>>();
+        fixed_locations.sort_by_key(|(node, _)| node.id);
+
+        solution
+            .lock_nodes(&fixed_locations)
+            .map_err(|(_, err)| err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETLIST: &str = r#"{
+        "nodes": [
+            { "id": 0, "macro_type": "IO" },
+            { "id": 1, "macro_type": "CLB" },
+            { "id": 2, "macro_type": "DSP" }
+        ],
+        "edges": [[0, 1], [1, 2]]
+    }"#;
+
+    fn write_benchmark(fabric: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("netlist.json"), NETLIST).unwrap();
+        std::fs::write(dir.path().join("fabric.json"), fabric).unwrap();
+        dir
+    }
+
+    #[test]
+    fn fabric_columns_and_locked_fixed_nodes() {
+        let dir = write_benchmark(
+            r#"{
+                "width": 8,
+                "height": 8,
+                "columns": [{ "macro_type": "DSP", "first_col": 3, "stride": 4 }],
+                "fixed": [{ "id": 0, "x": 0, "y": 3 }]
+            }"#,
+        );
+        let benchmark = Benchmark::load(dir.path()).unwrap();
+        assert_eq!(
+            benchmark.layout.get(&FPGALayoutCoordinate::new(3, 2)),
+            Some(FPGALayoutType::MacroType(MacroType::DSP))
+        );
+
+        let mut solution = gen_random_placement(&benchmark.layout, &benchmark.netlist).unwrap();
+        benchmark.apply_fixed_locations(&mut solution).unwrap();
+        let pad = NetlistNode {
+            id: 0,
+            macro_type: MacroType::IO,
+        };
+        assert!(solution.locked.contains(&pad));
+
+        let config = PlacerConfig {
+            n_steps: 50,
+            seed: Some(0),
+            ..Default::default()
+        };
+        let output = Placer::new(solution, config).run(None);
+        assert_eq!(
+            output.final_solution.solution_map[&pad],
+            FPGALayoutCoordinate::new(0, 3)
+        );
+    }
+
+    #[test]
+    fn a_node_fixed_twice_is_a_duplicate() {
+        let dir = write_benchmark(
+            r#"{
+                "width": 8,
+                "height": 8,
+                "fixed": [{ "id": 0, "x": 0, "y": 3 }, { "id": 0, "x": 0, "y": 3 }]
+            }"#,
+        );
+        assert!(matches!(
+            Benchmark::load(dir.path()),
+            Err(BenchmarkError::DuplicateFixedNode(0))
+        ));
+    }
+}
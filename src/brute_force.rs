@@ -0,0 +1,230 @@
+// exhaustive search for the minimum hpwl placement of a tiny netlist, a
+// reference to check how close the annealer gets to the true optimum
+//
+// nodes are assigned one at a time in id order to every site that accepts them
+// and still has room, a branch is cut as soon as the hpwl of the nets placed so
+// far reaches the best complete placement, since adding pins never shrinks a
+// bounding box that is a lower bound on the final cost
+// the search is still sites ^ nodes in the worst case so the layout should be
+// tiny too
+
+use rustc_hash::FxHashMap;
+
+use super::fpga_layout::*;
+use super::net_cache::*;
+use super::netlist::*;
+use super::placer::*;
+
+// largest netlist brute_force_optimal will search
+pub const BRUTE_FORCE_MAX_NODES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BruteForceError {
+    TooManyNodes { nodes: usize, limit: usize },
+    // no assignment puts every node on a site that accepts it
+    NoLegalPlacement,
+}
+
+impl std::fmt::Display for BruteForceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BruteForceError::TooManyNodes { nodes, limit } => write!(
+                f,
+                "netlist has {} nodes, brute force search is limited to {}",
+                nodes, limit
+            ),
+            BruteForceError::NoLegalPlacement => {
+                write!(f, "the netlist has no legal placement on the layout")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BruteForceError {}
+
+struct Search<'a> {
+    layout: &'a FPGALayout,
+    nodes: Vec<NetlistNode>,
+    // legal sites of each node, indexed like nodes
+    sites: Vec<Vec<FPGALayoutCoordinate>>,
+    nets: Vec<Vec<NetlistNode>>,
+    // nets of each node, indexed like nodes
+    node_nets: Vec<Vec<NetId>>,
+    assignment: FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+    occupancy: FxHashMap<FPGALayoutCoordinate, u32>,
+    net_hpwl: Vec<u32>,
+    cost: u32,
+    best: Option<FxHashMap<NetlistNode, FPGALayoutCoordinate>>,
+    best_cost: u32,
+}
+
+impl<'a> Search<'a> {
+    fn visit(&mut self, depth: usize) {
+        if depth == self.nodes.len() {
+            if self.cost < self.best_cost {
+                self.best_cost = self.cost;
+                self.best = Some(self.assignment.clone());
+            }
+            return;
+        }
+
+        let node = self.nodes[depth];
+        let node_nets = self.node_nets[depth].clone();
+        for site_index in 0..self.sites[depth].len() {
+            let site = self.sites[depth][site_index];
+            let occupancy = self.occupancy.get(&site).copied().unwrap_or(0);
+            if occupancy >= self.layout.capacity(&site) {
+                continue;
+            }
+
+            self.assignment.insert(node, site);
+            self.occupancy.insert(site, occupancy + 1);
+            let old_cost = self.cost;
+            let old_hpwls = node_nets
+                .iter()
+                .map(|net| self.net_hpwl[*net])
+                .collect::<Vec<_>>();
+            for net in &node_nets {
+                let hpwl = pins_hpwl(&self.nets[*net], &self.assignment, self.layout);
+                self.cost = self.cost - self.net_hpwl[*net] + hpwl;
+                self.net_hpwl[*net] = hpwl;
+            }
+
+            if self.cost < self.best_cost {
+                self.visit(depth + 1);
+            }
+
+            for (net, hpwl) in node_nets.iter().zip(old_hpwls) {
+                self.net_hpwl[*net] = hpwl;
+            }
+            self.cost = old_cost;
+            self.occupancy.insert(site, occupancy);
+            self.assignment.remove(&node);
+        }
+    }
+}
+
+// the placement with the lowest hpwl of all legal placements, ties going to
+// the first one found, only for netlists of at most BRUTE_FORCE_MAX_NODES nodes
+pub fn brute_force_optimal<'a>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+) -> Result<PlacementSolution<'a>, BruteForceError> {
+    let nodes = netlist
+        .all_nodes_sorted()
+        .into_iter()
+        .copied()
+        .collect::<Vec<_>>();
+    if nodes.len() > BRUTE_FORCE_MAX_NODES {
+        return Err(BruteForceError::TooManyNodes {
+            nodes: nodes.len(),
+            limit: BRUTE_FORCE_MAX_NODES,
+        });
+    }
+
+    let empty = PlacementSolution::new(layout, netlist);
+    let sites = nodes
+        .iter()
+        .map(|node| empty.get_possible_sites_for(node))
+        .collect::<Vec<_>>();
+
+    let nets = netlist.nets();
+    let node_index = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| (*node, index))
+        .collect::<FxHashMap<_, _>>();
+    let mut node_nets = vec![Vec::new(); nodes.len()];
+    for (net, pins) in nets.iter().enumerate() {
+        for pin in pins {
+            let pin_nets: &mut Vec<NetId> = &mut node_nets[node_index[pin]];
+            if pin_nets.last() != Some(&net) {
+                pin_nets.push(net);
+            }
+        }
+    }
+
+    let mut search = Search {
+        layout,
+        nodes,
+        sites,
+        net_hpwl: vec![0; nets.len()],
+        nets,
+        node_nets,
+        assignment: FxHashMap::default(),
+        occupancy: FxHashMap::default(),
+        cost: 0,
+        best: None,
+        best_cost: u32::MAX,
+    };
+    search.visit(0);
+
+    let best = search.best.ok_or(BruteForceError::NoLegalPlacement)?;
+    let mut solution = PlacementSolution::new(layout, netlist);
+    for (node, location) in best {
+        solution.place_node(node, location);
+    }
+    Ok(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn clb(id: u32) -> NetlistNode {
+        NetlistNode {
+            id,
+            macro_type: MacroType::CLB,
+        }
+    }
+
+    #[test]
+    fn annealer_reaches_the_optimum_of_a_5_node_design() {
+        // a 3 x 3 block of CLB sites inside the IO ring
+        let layout = build_simple_fpga_layout(5, 5);
+        let nodes = (0..5).map(clb).collect::<Vec<_>>();
+        let nets = vec![
+            vec![clb(0), clb(1), clb(2)],
+            vec![clb(2), clb(3)],
+            vec![clb(3), clb(4)],
+            vec![clb(4), clb(0)],
+        ];
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let optimal = brute_force_optimal(&layout, &netlist).unwrap();
+        assert!(optimal.valid());
+
+        let mut rng = StdRng::seed_from_u64(31);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 500,
+            initial_temperature: 2.0,
+            seed: Some(31),
+            ..Default::default()
+        };
+        let output = Placer::new(solution, config).run(None);
+        assert_eq!(output.best_solution.cost_hpwl(), optimal.cost_hpwl());
+    }
+
+    #[test]
+    fn netlists_over_the_limit_are_refused() {
+        let layout = build_simple_fpga_layout(5, 5);
+        let nodes = (0..BRUTE_FORCE_MAX_NODES as u32 + 1)
+            .map(clb)
+            .collect::<Vec<_>>();
+        let nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        assert_eq!(
+            brute_force_optimal(&layout, &netlist).err(),
+            Some(BruteForceError::TooManyNodes {
+                nodes: BRUTE_FORCE_MAX_NODES + 1,
+                limit: BRUTE_FORCE_MAX_NODES,
+            })
+        );
+    }
+}
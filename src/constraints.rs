@@ -0,0 +1,219 @@
+// reader for IO constraint files pinning IO pads to fixed sites, one pin per
+// line as "<node id> <x> <y>", blank lines and text after a # are ignored
+//
+//   # clock and reset pads
+//   12 0 5
+//   13 0 6
+
+use std::path::Path;
+
+use rustc_hash::FxHashMap;
+use rustc_hash::FxHashSet;
+
+use super::fpga_layout::*;
+use super::placer::*;
+
+#[derive(Debug)]
+pub enum IoConstraintError {
+    Io(std::io::Error),
+    // line number (starting at 1) and contents of a line that isn't "<id> <x> <y>"
+    Parse(usize, String),
+    // a constraint refers to a node id that isn't in the netlist
+    UnknownNode(u32),
+    // only IO nodes can be pinned to pads
+    NotIoNode(u32),
+    // the same node is pinned more than once
+    DuplicateNode(u32),
+    // the coordinate isn't an IO site, or its direction doesn't fit the pad's
+    NotIoSite(u32, FPGALayoutCoordinate),
+    // the pad can't be placed there, e.g. the site is taken by another pinned pad
+    Place(u32, PlaceError),
+}
+
+impl std::fmt::Display for IoConstraintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IoConstraintError::Io(err) => write!(f, "unable to read IO constraints: {}", err),
+            IoConstraintError::Parse(line, contents) => {
+                write!(f, "line {} is not \"<id> <x> <y>\": {}", line, contents)
+            }
+            IoConstraintError::UnknownNode(id) => {
+                write!(f, "IO constraint refers to unknown node id {}", id)
+            }
+            IoConstraintError::NotIoNode(id) => write!(f, "node {} is not an IO", id),
+            IoConstraintError::DuplicateNode(id) => {
+                write!(f, "node {} is pinned more than once", id)
+            }
+            IoConstraintError::NotIoSite(id, location) => write!(
+                f,
+                "node {} is pinned to ({}, {}) which is not an IO site of its direction",
+                id, location.x, location.y
+            ),
+            IoConstraintError::Place(id, err) => {
+                write!(f, "unable to pin node {}: {}", id, err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IoConstraintError {}
+
+impl From<std::io::Error> for IoConstraintError {
+    fn from(err: std::io::Error) -> Self {
+        IoConstraintError::Io(err)
+    }
+}
+
+pub fn read_io_constraints(
+    path: impl AsRef<Path>,
+) -> Result<Vec<(u32, FPGALayoutCoordinate)>, IoConstraintError> {
+    parse_io_constraints(&std::fs::read_to_string(path)?)
+}
+
+pub fn parse_io_constraints(
+    contents: &str,
+) -> Result<Vec<(u32, FPGALayoutCoordinate)>, IoConstraintError> {
+    let mut constraints = Vec::new();
+
+    for (line_idx, line) in contents.lines().enumerate() {
+        let fields = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        if fields.is_empty() {
+            continue;
+        }
+
+        let parse_error = || IoConstraintError::Parse(line_idx + 1, line.to_string());
+        let [id, x, y] = fields[..] else {
+            return Err(parse_error());
+        };
+        let id = id.parse().map_err(|_| parse_error())?;
+        let x = x.parse().map_err(|_| parse_error())?;
+        let y = y.parse().map_err(|_| parse_error())?;
+
+        constraints.push((id, FPGALayoutCoordinate::new(x, y)));
+    }
+
+    Ok(constraints)
+}
+
+impl<'a> PlacementSolution<'a> {
+    // move every pinned pad onto its site and lock it there so the placer
+    // never moves it, see lock_nodes
+    // a pad that can't be placed (e.g. its site isn't an IO site of its
+    // direction, holds a locked node or another pad pinned there) rolls back
+    // the pads already applied, so on an error the solution is left as it was
+    pub fn apply_io_constraints(
+        &mut self,
+        constraints: &[(u32, FPGALayoutCoordinate)],
+    ) -> Result<(), IoConstraintError> {
+        let nodes_by_id = self
+            .netlist
+            .graph
+            .node_weights()
+            .map(|node| (node.id, *node))
+            .collect::<FxHashMap<_, _>>();
+
+        let mut pinned = FxHashSet::default();
+        let mut pins = Vec::new();
+        for (id, location) in constraints {
+            let node = *nodes_by_id
+                .get(id)
+                .ok_or(IoConstraintError::UnknownNode(*id))?;
+            if node.macro_type != MacroType::IO {
+                return Err(IoConstraintError::NotIoNode(*id));
+            }
+            if !pinned.insert(*id) {
+                return Err(IoConstraintError::DuplicateNode(*id));
+            }
+            pins.push((node, *location));
+        }
+
+        self.lock_nodes(&pins).map_err(|(node, err)| match err {
+            PlaceError::OutOfBounds(location) | PlaceError::WrongSiteType(location) => {
+                IoConstraintError::NotIoSite(node.id, location)
+            }
+            err => IoConstraintError::Place(node.id, err),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::netlist::*;
+
+    fn node(id: u32, macro_type: MacroType) -> NetlistNode {
+        NetlistNode { id, macro_type }
+    }
+
+    // two IO pads each driving a CLB
+    fn pad_netlist() -> NetlistGraph {
+        let nodes = [
+            node(0, MacroType::IO),
+            node(1, MacroType::IO),
+            node(2, MacroType::CLB),
+            node(3, MacroType::CLB),
+        ];
+        NetlistGraph::from_nets(
+            &nodes,
+            &[vec![nodes[0], nodes[2]], vec![nodes[1], nodes[3]]],
+        )
+    }
+
+    #[test]
+    fn pinned_pad_is_placed_and_locked() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = pad_netlist();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+
+        let site = FPGALayoutCoordinate::new(0, 3);
+        solution
+            .apply_io_constraints(&parse_io_constraints("0 0 3").unwrap())
+            .unwrap();
+
+        let pad = node(0, MacroType::IO);
+        assert_eq!(solution.solution_map[&pad], site);
+        assert!(solution.locked.contains(&pad));
+        assert!(solution.valid());
+    }
+
+    #[test]
+    fn failed_constraints_leave_the_solution_unchanged() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = pad_netlist();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let before = solution.solution_map.clone();
+
+        // both pads on one site, the second can't swap out the locked first
+        let err = solution
+            .apply_io_constraints(&parse_io_constraints("0 0 3\n1 0 3").unwrap())
+            .unwrap_err();
+        assert!(matches!(err, IoConstraintError::Place(1, _)));
+        assert_eq!(solution.solution_map, before);
+        assert!(solution.locked.is_empty());
+
+        let err = solution
+            .apply_io_constraints(&parse_io_constraints("0 0 3\n0 0 4").unwrap())
+            .unwrap_err();
+        assert!(matches!(err, IoConstraintError::DuplicateNode(0)));
+
+        let mut netlist = pad_netlist();
+        netlist.set_io_direction(node(0, MacroType::IO), IoDirection::Input);
+        let mut layout = build_simple_fpga_layout(8, 8);
+        layout.config_io_direction(FPGALayoutCoordinate::new(0, 3), IoDirection::Output);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let err = solution
+            .apply_io_constraints(&parse_io_constraints("0 0 3").unwrap())
+            .unwrap_err();
+        assert!(matches!(err, IoConstraintError::NotIoSite(0, _)));
+    }
+}
@@ -0,0 +1,192 @@
+// objectives the placer can minimize in place of its built in cost, see
+// Placer::with_cost_function
+
+use super::netlist::*;
+use super::placer::*;
+use super::timing::*;
+
+// Sync so placers holding one can still be run on rayon's threads
+pub trait CostFunction: Sync {
+    fn cost(&self, solution: &PlacementSolution) -> f32;
+
+    // change in cost from solution to moved, the neighbor a move produced
+    // from it, current_cost is cost(solution) which the placer already has,
+    // override this when the change can be found without costing moved in full
+    fn delta(
+        &self,
+        _solution: &PlacementSolution,
+        current_cost: f32,
+        moved: &PlacementSolution,
+    ) -> f32 {
+        self.cost(moved) - current_cost
+    }
+}
+
+// the weighted hpwl with each net's x and y span scaled, what the placer
+// minimizes with every other weight off, see PlacerConfig::x_weight
+#[derive(Debug, Clone, Copy)]
+pub struct HpwlCost {
+    pub x_weight: f32,
+    pub y_weight: f32,
+}
+
+impl Default for HpwlCost {
+    fn default() -> Self {
+        Self {
+            x_weight: 1.0,
+            y_weight: 1.0,
+        }
+    }
+}
+
+impl CostFunction for HpwlCost {
+    fn cost(&self, solution: &PlacementSolution) -> f32 {
+        if self.x_weight == 1.0 && self.y_weight == 1.0 {
+            solution.weighted_hpwl()
+        } else {
+            solution.weighted_hpwl_xy(self.x_weight, self.y_weight)
+        }
+    }
+}
+
+// delay of the critical path
+#[derive(Debug, Clone)]
+pub struct TimingCost {
+    pub timing_graph: TimingGraph,
+}
+
+impl TimingCost {
+    pub fn new(netlist: &NetlistGraph) -> TimingCost {
+        TimingCost {
+            timing_graph: TimingGraph::new(netlist),
+        }
+    }
+}
+
+impl CostFunction for TimingCost {
+    fn cost(&self, solution: &PlacementSolution) -> f32 {
+        self.timing_graph.critical_path_delay(solution)
+    }
+}
+
+// routing demand over supply, every bin of PlacementSolution::demand_grid can
+// take capacity units of demand per site it covers and the cost is the demand
+// above that summed over the bins, 0 while nothing is congested, a bin_size of
+// 0 is taken as 1
+#[derive(Debug, Clone, Copy)]
+pub struct CongestionCost {
+    pub bin_size: u32,
+    pub capacity: u32,
+}
+
+impl CostFunction for CongestionCost {
+    fn cost(&self, solution: &PlacementSolution) -> f32 {
+        let width = solution.layout.width;
+        let height = solution.layout.height;
+        let bin_size = self.bin_size.max(1);
+        // bins along the right and top edges can be cut short
+        let bin_sites = |bin: usize, length: u32| (length - bin as u32 * bin_size).min(bin_size);
+
+        let mut overflow = 0;
+        for (bin_y, row) in solution.demand_grid(bin_size).iter().enumerate() {
+            for (bin_x, demand) in row.iter().enumerate() {
+                let supply = self.capacity * bin_sites(bin_x, width) * bin_sites(bin_y, height);
+                overflow += demand.saturating_sub(supply);
+            }
+        }
+        overflow as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::fpga_layout::*;
+
+    // distance of node 0 from the left edge
+    struct LeftEdgeCost;
+
+    impl CostFunction for LeftEdgeCost {
+        fn cost(&self, solution: &PlacementSolution) -> f32 {
+            solution
+                .solution_map
+                .iter()
+                .find(|(node, _)| node.id == 0)
+                .map_or(0.0, |(_, location)| location.x as f32)
+        }
+    }
+
+    fn chain_netlist(n: u32) -> NetlistGraph {
+        let nodes = (0..n)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: MacroType::CLB,
+            })
+            .collect::<Vec<_>>();
+        let nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        NetlistGraph::from_nets(&nodes, &nets)
+    }
+
+    #[test]
+    fn placer_minimizes_a_custom_cost() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(6);
+        let mut rng = StdRng::seed_from_u64(0);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 200,
+            seed: Some(1),
+            ..Default::default()
+        };
+        let cost_function = LeftEdgeCost;
+        let placer = Placer::new(solution, config).with_cost_function(&cost_function);
+
+        let mut terms = Vec::new();
+        placer.cost_terms(&placer.current_solution, |name, _| terms.push(name));
+        assert_eq!(terms, vec!["custom"]);
+
+        let output = placer.run(None);
+        // column 1 is the leftmost CLB column
+        assert_eq!(cost_function.cost(&output.final_solution), 1.0);
+    }
+
+    #[test]
+    fn hpwl_cost_matches_the_placer_objective() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(6);
+        let mut rng = StdRng::seed_from_u64(0);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            y_weight: 3.0,
+            ..Default::default()
+        };
+        let placer = Placer::new(solution, config);
+        let hpwl_cost = HpwlCost {
+            x_weight: 1.0,
+            y_weight: 3.0,
+        };
+        assert_eq!(
+            placer.cost(&placer.current_solution),
+            hpwl_cost.cost(&placer.current_solution)
+        );
+
+        let congestion = CongestionCost {
+            bin_size: 0,
+            capacity: 1,
+        };
+        assert_eq!(
+            congestion.cost(&placer.current_solution),
+            CongestionCost {
+                bin_size: 1,
+                capacity: 1
+            }
+            .cost(&placer.current_solution)
+        );
+    }
+}
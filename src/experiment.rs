@@ -0,0 +1,474 @@
+// everything needed to reproduce and analyze a run in one JSON document: the
+// seed, the config, the layout, the netlist, the initial and final placements
+// and the cost and temperature history
+//
+// the layout is stored as its grid codes (see FPGALayoutType::grid_code) plus
+// the IO directions, capacities, multi type sites and topology, the netlist as
+// its nodes, IO directions, nets and net weights, rebuilt with
+// NetlistGraph::from_nets, and the solutions as their placed nodes, so
+// footprints, regions, relative groups and locks don't survive a replay
+
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use super::fpga_layout::*;
+use super::netlist::*;
+use super::placer::*;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    BadGridCode(u8),
+    BadMacroType(String),
+    Layout(LayoutSizeError),
+    // a placement or net refers to a node id that isn't in the netlist
+    UnknownNode(u32),
+    // the replayed run took a different number of steps
+    StepCountMismatch {
+        recorded: usize,
+        replayed: usize,
+    },
+    CostMismatch {
+        step: u32,
+        recorded: f32,
+        replayed: f32,
+    },
+    // the node ended up somewhere else, None when it is unplaced
+    PlacementMismatch {
+        id: u32,
+        recorded: Option<FPGALayoutCoordinate>,
+        replayed: Option<FPGALayoutCoordinate>,
+    },
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::BadGridCode(code) => write!(f, "unknown layout grid code {}", code),
+            ReplayError::BadMacroType(name) => write!(f, "unknown macro type {}", name),
+            ReplayError::Layout(err) => write!(f, "{}", err),
+            ReplayError::UnknownNode(id) => write!(f, "unknown node id {}", id),
+            ReplayError::StepCountMismatch { recorded, replayed } => write!(
+                f,
+                "recorded run took {} steps but the replay took {}",
+                recorded, replayed
+            ),
+            ReplayError::CostMismatch {
+                step,
+                recorded,
+                replayed,
+            } => write!(
+                f,
+                "cost at step {} was {} but the replay got {}",
+                step, recorded, replayed
+            ),
+            ReplayError::PlacementMismatch {
+                id,
+                recorded,
+                replayed,
+            } => write!(
+                f,
+                "node {} ended at {:?} but the replay put it at {:?}",
+                id, recorded, replayed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<LayoutSizeError> for ReplayError {
+    fn from(err: LayoutSizeError) -> Self {
+        ReplayError::Layout(err)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutRecord {
+    pub width: u32,
+    pub height: u32,
+    // grid[y][x], see FPGALayout::to_grid
+    pub grid: Vec<Vec<u8>>,
+    // (x, y, direction) of every directional IO site
+    pub io_directions: Vec<(u32, u32, IoDirection)>,
+    // (x, y, capacity) of every site holding more than one node
+    pub capacities: Vec<(u32, u32, u32)>,
+    pub topology: Topology,
+    // (x, y, types) of every multi type site, grid code 5 in the grid
+    #[serde(default)]
+    pub multi_type_sites: Vec<(u32, u32, Vec<String>)>,
+}
+
+impl LayoutRecord {
+    pub fn new(layout: &FPGALayout) -> LayoutRecord {
+        let mut io_directions = layout
+            .io_directions
+            .iter()
+            .map(|(location, direction)| (location.x, location.y, *direction))
+            .collect::<Vec<_>>();
+        io_directions.sort_by_key(|(x, y, _)| (*x, *y));
+        let mut capacities = layout
+            .capacities
+            .iter()
+            .map(|(location, capacity)| (location.x, location.y, *capacity))
+            .collect::<Vec<_>>();
+        capacities.sort();
+        let mut multi_type_sites = layout
+            .map
+            .iter()
+            .filter_map(|(location, layout_type)| match layout_type {
+                FPGALayoutType::MultiType(macro_types) => {
+                    let names = macro_types
+                        .iter()
+                        .map(|macro_type| macro_type.as_str().to_string())
+                        .collect();
+                    Some((location.x, location.y, names))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        multi_type_sites.sort_by_key(|(x, y, _)| (*x, *y));
+
+        LayoutRecord {
+            width: layout.width,
+            height: layout.height,
+            grid: layout.to_grid(),
+            io_directions,
+            capacities,
+            topology: layout.topology,
+            multi_type_sites,
+        }
+    }
+
+    pub fn to_layout(&self) -> Result<FPGALayout, ReplayError> {
+        let mut layout = FPGALayout::try_new(self.width, self.height)?;
+        for (y, row) in self.grid.iter().enumerate() {
+            for (x, code) in row.iter().enumerate() {
+                // filled in from multi_type_sites below
+                if *code == FPGALayoutType::MultiType(Vec::new()).grid_code() {
+                    continue;
+                }
+                let layout_type =
+                    FPGALayoutType::from_grid_code(*code).ok_or(ReplayError::BadGridCode(*code))?;
+                layout
+                    .map
+                    .insert(FPGALayoutCoordinate::new(x as u32, y as u32), layout_type);
+            }
+        }
+        for (x, y, names) in &self.multi_type_sites {
+            let macro_types = names
+                .iter()
+                .map(|name| name.parse::<MacroType>().map_err(ReplayError::BadMacroType))
+                .collect::<Result<Vec<_>, _>>()?;
+            layout.config_multi_type(FPGALayoutCoordinate::new(*x, *y), &macro_types);
+        }
+        for (x, y, direction) in &self.io_directions {
+            layout.config_io_direction(FPGALayoutCoordinate::new(*x, *y), *direction);
+        }
+        for (x, y, capacity) in &self.capacities {
+            layout.config_capacity(FPGALayoutCoordinate::new(*x, *y), *capacity);
+        }
+        layout.config_topology(self.topology);
+        Ok(layout)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetlistRecord {
+    // (id, macro type) of every node in graph order
+    pub nodes: Vec<(u32, String)>,
+    // (id, direction) of every directional IO node
+    pub io_directions: Vec<(u32, IoDirection)>,
+    // pin ids of every net by NetId, the driver first, see NetlistGraph::nets
+    pub nets: Vec<Vec<u32>>,
+    pub net_weights: Vec<f32>,
+}
+
+impl NetlistRecord {
+    pub fn new(netlist: &NetlistGraph) -> NetlistRecord {
+        let mut io_directions = netlist
+            .io_directions
+            .iter()
+            .map(|(node, direction)| (node.id, *direction))
+            .collect::<Vec<_>>();
+        io_directions.sort_by_key(|(id, _)| *id);
+
+        NetlistRecord {
+            nodes: netlist
+                .graph
+                .node_weights()
+                .map(|node| (node.id, node.macro_type.as_str().to_string()))
+                .collect(),
+            io_directions,
+            nets: netlist
+                .nets()
+                .iter()
+                .map(|pins| pins.iter().map(|pin| pin.id).collect())
+                .collect(),
+            net_weights: (0..netlist.net_count())
+                .map(|net| netlist.net_weight(net))
+                .collect(),
+        }
+    }
+
+    pub fn to_netlist(&self) -> Result<NetlistGraph, ReplayError> {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(id, name)| {
+                let macro_type = name
+                    .parse::<MacroType>()
+                    .map_err(ReplayError::BadMacroType)?;
+                Ok(NetlistNode {
+                    id: *id,
+                    macro_type,
+                })
+            })
+            .collect::<Result<Vec<_>, ReplayError>>()?;
+        let nodes_by_id = nodes
+            .iter()
+            .map(|node| (node.id, *node))
+            .collect::<FxHashMap<_, _>>();
+        let node = |id: &u32| {
+            nodes_by_id
+                .get(id)
+                .copied()
+                .ok_or(ReplayError::UnknownNode(*id))
+        };
+
+        let nets = self
+            .nets
+            .iter()
+            .map(|pins| pins.iter().map(node).collect::<Result<Vec<_>, _>>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut netlist = NetlistGraph::from_nets(&nodes, &nets);
+        for (id, direction) in &self.io_directions {
+            netlist.set_io_direction(node(id)?, *direction);
+        }
+        for (net, weight) in self.net_weights.iter().enumerate() {
+            if *weight != 1.0 {
+                netlist.set_net_weight(net, *weight);
+            }
+        }
+        Ok(netlist)
+    }
+}
+
+// (id, x, y) of every placed node, sorted by id
+fn placement_record(solution: &PlacementSolution) -> Vec<(u32, u32, u32)> {
+    let mut placement = solution
+        .solution_map
+        .iter()
+        .map(|(node, location)| (node.id, location.x, location.y))
+        .collect::<Vec<_>>();
+    placement.sort();
+    placement
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentRecord {
+    pub seed: u64,
+    // config.seed is always Some(seed)
+    pub config: PlacerConfig,
+    pub layout: LayoutRecord,
+    pub netlist: NetlistRecord,
+    pub initial_placement: Vec<(u32, u32, u32)>,
+    pub final_placement: Vec<(u32, u32, u32)>,
+    pub x_steps: Vec<u32>,
+    pub y_cost: Vec<f32>,
+    pub temperature_per_step: Vec<f32>,
+}
+
+impl ExperimentRecord {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json_str(contents: &str) -> Result<ExperimentRecord, serde_json::Error> {
+        serde_json::from_str(contents)
+    }
+}
+
+impl<'a> PlacerOutput<'a> {
+    // config and seed are what the run was started with, the seed has to be
+    // the one the placer's rng was seeded from for the record to replay
+    pub fn to_experiment_record(&self, config: &PlacerConfig, seed: u64) -> ExperimentRecord {
+        ExperimentRecord {
+            seed,
+            config: PlacerConfig {
+                seed: Some(seed),
+                ..config.clone()
+            },
+            layout: LayoutRecord::new(self.initial_solution.layout),
+            netlist: NetlistRecord::new(self.initial_solution.netlist),
+            initial_placement: placement_record(&self.initial_solution),
+            final_placement: placement_record(&self.final_solution),
+            x_steps: self.x_steps.clone(),
+            y_cost: self.y_cost.clone(),
+            temperature_per_step: self.temperature_per_step.clone(),
+        }
+    }
+}
+
+fn solution_from_record<'a>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+    placement: &[(u32, u32, u32)],
+) -> Result<PlacementSolution<'a>, ReplayError> {
+    let nodes_by_id = netlist
+        .graph
+        .node_weights()
+        .map(|node| (node.id, *node))
+        .collect::<FxHashMap<_, _>>();
+    let mut solution = PlacementSolution::new(layout, netlist);
+    for (id, x, y) in placement {
+        let node = *nodes_by_id.get(id).ok_or(ReplayError::UnknownNode(*id))?;
+        solution.place_node(node, FPGALayoutCoordinate::new(*x, *y));
+    }
+    Ok(solution)
+}
+
+// rerun the recorded experiment from its initial placement and check that
+// every step's cost and the final placement come out exactly the same
+pub fn replay(record: &ExperimentRecord) -> Result<(), ReplayError> {
+    let layout = record.layout.to_layout()?;
+    let netlist = record.netlist.to_netlist()?;
+    let initial_solution = solution_from_record(&layout, &netlist, &record.initial_placement)?;
+
+    let config = PlacerConfig {
+        seed: Some(record.seed),
+        verbose: false,
+        render: false,
+        ..record.config.clone()
+    };
+    let output = Placer::new(initial_solution, config).run(None);
+
+    if output.y_cost.len() != record.y_cost.len() {
+        return Err(ReplayError::StepCountMismatch {
+            recorded: record.y_cost.len(),
+            replayed: output.y_cost.len(),
+        });
+    }
+    for ((step, recorded), replayed) in record
+        .x_steps
+        .iter()
+        .zip(&record.y_cost)
+        .zip(&output.y_cost)
+    {
+        if recorded != replayed {
+            return Err(ReplayError::CostMismatch {
+                step: *step,
+                recorded: *recorded,
+                replayed: *replayed,
+            });
+        }
+    }
+
+    let final_placement = placement_record(&output.final_solution);
+    if final_placement != record.final_placement {
+        let recorded = record
+            .final_placement
+            .iter()
+            .map(|(id, x, y)| (*id, FPGALayoutCoordinate::new(*x, *y)))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let replayed = final_placement
+            .iter()
+            .map(|(id, x, y)| (*id, FPGALayoutCoordinate::new(*x, *y)))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        let id = recorded
+            .keys()
+            .chain(replayed.keys())
+            .find(|id| recorded.get(id) != replayed.get(id))
+            .copied()
+            .unwrap();
+        return Err(ReplayError::PlacementMismatch {
+            id,
+            recorded: recorded.get(&id).copied(),
+            replayed: replayed.get(&id).copied(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn a_recorded_hyperedge_run_replays() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..8)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: if id < 7 {
+                    MacroType::CLB
+                } else {
+                    MacroType::IO
+                },
+            })
+            .collect::<Vec<_>>();
+        let nets = vec![
+            vec![nodes[0], nodes[1], nodes[2], nodes[3]],
+            vec![nodes[3], nodes[4]],
+            vec![nodes[4], nodes[5], nodes[6]],
+            vec![nodes[7], nodes[0], nodes[6]],
+        ];
+        let mut netlist = NetlistGraph::from_nets(&nodes, &nets);
+        netlist.set_net_weight(0, 2.5);
+        netlist.set_net_weight(2, 0.5);
+        netlist.set_io_direction(nodes[7], IoDirection::Input);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 100,
+            initial_temperature: 2.0,
+            seed: Some(8),
+            ..Default::default()
+        };
+        let output = Placer::new(solution, config.clone()).run(None);
+        let record = output.to_experiment_record(&config, 8);
+        let record = ExperimentRecord::from_json_str(&record.to_json()).unwrap();
+
+        let rebuilt = record.netlist.to_netlist().unwrap();
+        assert_eq!(rebuilt.nets(), netlist.nets());
+        assert_eq!(rebuilt.net_weight(0), 2.5);
+        assert_eq!(rebuilt.io_direction(&nodes[7]), IoDirection::Input);
+        replay(&record).unwrap();
+    }
+
+    #[test]
+    fn configs_recorded_before_newer_fields_still_load() {
+        let config = PlacerConfig {
+            n_steps: 10,
+            seed: Some(3),
+            ..Default::default()
+        };
+        let mut config_json = serde_json::to_value(&config).unwrap();
+        let fields = config_json.as_object_mut().unwrap();
+        for field in [
+            "restart",
+            "x_weight",
+            "y_weight",
+            "hull_area_weight",
+            "biased_move_k",
+            "equal_cost_acceptance",
+            "record_move_distances",
+        ] {
+            assert!(
+                fields.remove(field).is_some(),
+                "{} is not serialized",
+                field
+            );
+        }
+
+        let loaded: PlacerConfig = serde_json::from_value(config_json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&config).unwrap()
+        );
+    }
+}
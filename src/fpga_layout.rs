@@ -1,4 +1,5 @@
 use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Hash, PartialEq, Eq, Debug, Copy)]
 pub enum MacroType {
@@ -8,12 +9,183 @@ pub enum MacroType {
     IO,
 }
 
+impl MacroType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MacroType::CLB => "CLB",
+            MacroType::DSP => "DSP",
+            MacroType::BRAM => "BRAM",
+            MacroType::IO => "IO",
+        }
+    }
+}
+
+impl std::fmt::Display for MacroType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for MacroType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "CLB" => Ok(MacroType::CLB),
+            "DSP" => Ok(MacroType::DSP),
+            "BRAM" => Ok(MacroType::BRAM),
+            "IO" => Ok(MacroType::IO),
+            _ => Err(s.to_string()),
+        }
+    }
+}
+
+// which way signals flow through an IO pad
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum IoDirection {
+    Input,
+    Output,
+    #[default]
+    Bidirectional,
+}
+
+impl IoDirection {
+    // an input or output pad fits on a site of the same direction or a
+    // bidirectional site, a bidirectional pad needs a bidirectional site
+    pub fn fits_site(&self, site_direction: IoDirection) -> bool {
+        site_direction == IoDirection::Bidirectional || site_direction == *self
+    }
+}
+
+// how distances are measured across the fabric, on a torus the left edge
+// wraps around to the right edge and the top edge to the bottom edge
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Topology {
+    #[default]
+    Planar,
+    Torus,
+}
+
+impl Topology {
+    // distance between two positions along an axis of the given length
+    pub fn axis_distance(&self, a: u32, b: u32, length: u32) -> u32 {
+        let distance = a.abs_diff(b);
+        match self {
+            Topology::Planar => distance,
+            Topology::Torus => distance.min(length - distance),
+        }
+    }
+
+    // length of the shortest stretch of an axis of the given length that
+    // covers every position, 0 for no positions
+    // on a torus that is the axis minus the widest gap between neighboring
+    // positions, counting the gap that wraps around
+    pub fn axis_span(&self, positions: &mut [u32], length: u32) -> u32 {
+        positions.sort_unstable();
+        let (first, last) = match (positions.first(), positions.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return 0,
+        };
+        match self {
+            Topology::Planar => last - first,
+            Topology::Torus => {
+                let widest_gap = positions
+                    .windows(2)
+                    .map(|pair| pair[1] - pair[0])
+                    .fold(first + length - last, u32::max);
+                length - widest_gap
+            }
+        }
+    }
+}
+
 #[derive(Clone, Hash, PartialEq, Eq, Debug)]
 pub enum FPGALayoutType {
     MacroType(MacroType),
+    // a site that can host any of several macro types, build it with
+    // FPGALayoutType::multi_type so equal sets compare equal
+    MultiType(Vec<MacroType>),
     EMPTY,
 }
 
+impl FPGALayoutType {
+    // a site accepting all of the given types, kept in CLB, DSP, BRAM, IO
+    // order without duplicates, a single type gives a plain MacroType site and
+    // no types an EMPTY one
+    pub fn multi_type(macro_types: &[MacroType]) -> FPGALayoutType {
+        let mut macro_types = [
+            MacroType::CLB,
+            MacroType::DSP,
+            MacroType::BRAM,
+            MacroType::IO,
+        ]
+        .into_iter()
+        .filter(|macro_type| macro_types.contains(macro_type))
+        .collect::<Vec<_>>();
+
+        match macro_types.len() {
+            0 => FPGALayoutType::EMPTY,
+            1 => FPGALayoutType::MacroType(macro_types.remove(0)),
+            _ => FPGALayoutType::MultiType(macro_types),
+        }
+    }
+
+    // whether a node of the given type may be placed on the site
+    pub fn accepts(&self, macro_type: MacroType) -> bool {
+        match self {
+            FPGALayoutType::MacroType(site_type) => *site_type == macro_type,
+            FPGALayoutType::MultiType(site_types) => site_types.contains(&macro_type),
+            FPGALayoutType::EMPTY => false,
+        }
+    }
+
+    // every macro type the site accepts, empty for EMPTY
+    pub fn macro_types(&self) -> Vec<MacroType> {
+        match self {
+            FPGALayoutType::MacroType(macro_type) => vec![*macro_type],
+            FPGALayoutType::MultiType(macro_types) => macro_types.clone(),
+            FPGALayoutType::EMPTY => Vec::new(),
+        }
+    }
+
+    // cell value used by FPGALayout::to_grid and PlacementSolution::to_occupancy_grid
+    // EMPTY = 0, CLB = 1, DSP = 2, BRAM = 3, IO = 4, any multi type site = 5
+    pub fn grid_code(&self) -> u8 {
+        match self {
+            FPGALayoutType::EMPTY => 0,
+            FPGALayoutType::MacroType(MacroType::CLB) => 1,
+            FPGALayoutType::MacroType(MacroType::DSP) => 2,
+            FPGALayoutType::MacroType(MacroType::BRAM) => 3,
+            FPGALayoutType::MacroType(MacroType::IO) => 4,
+            FPGALayoutType::MultiType(_) => 5,
+        }
+    }
+
+    // inverse of grid_code, 5 has no single site type so it gives None
+    pub fn from_grid_code(code: u8) -> Option<FPGALayoutType> {
+        match code {
+            0 => Some(FPGALayoutType::EMPTY),
+            1 => Some(FPGALayoutType::MacroType(MacroType::CLB)),
+            2 => Some(FPGALayoutType::MacroType(MacroType::DSP)),
+            3 => Some(FPGALayoutType::MacroType(MacroType::BRAM)),
+            4 => Some(FPGALayoutType::MacroType(MacroType::IO)),
+            _ => None,
+        }
+    }
+
+    // letter drawn for the site in the ascii renders
+    pub fn ascii_char(&self) -> char {
+        match self {
+            FPGALayoutType::EMPTY => ' ',
+            FPGALayoutType::MacroType(MacroType::CLB) => 'C',
+            FPGALayoutType::MacroType(MacroType::DSP) => 'D',
+            FPGALayoutType::MacroType(MacroType::BRAM) => 'B',
+            FPGALayoutType::MacroType(MacroType::IO) => 'I',
+            FPGALayoutType::MultiType(_) => 'M',
+        }
+    }
+}
+
 #[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
 pub struct FPGALayoutCoordinate {
     pub x: u32,
@@ -24,22 +196,197 @@ impl FPGALayoutCoordinate {
     pub fn new(x: u32, y: u32) -> FPGALayoutCoordinate {
         FPGALayoutCoordinate { x, y }
     }
+
+    // planar manhattan distance, widened so it can't overflow however far
+    // apart the two are
+    pub fn manhattan_distance(&self, other: &FPGALayoutCoordinate) -> u64 {
+        self.x.abs_diff(other.x) as u64 + self.y.abs_diff(other.y) as u64
+    }
+}
+
+// rectangle of sites with inclusive bounds
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
+pub struct Region {
+    pub x_min: u32,
+    pub y_min: u32,
+    pub x_max: u32,
+    pub y_max: u32,
+}
+
+impl Region {
+    pub fn new(x_min: u32, y_min: u32, x_max: u32, y_max: u32) -> Region {
+        assert!(
+            x_min <= x_max && y_min <= y_max,
+            "region bounds are inverted"
+        );
+        Region {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+        }
+    }
+
+    pub fn contains(&self, coordinate: &FPGALayoutCoordinate) -> bool {
+        coordinate.x >= self.x_min
+            && coordinate.x <= self.x_max
+            && coordinate.y >= self.y_min
+            && coordinate.y <= self.y_max
+    }
+
+    pub fn overlaps(&self, other: &Region) -> bool {
+        self.x_min <= other.x_max
+            && other.x_min <= self.x_max
+            && self.y_min <= other.y_max
+            && other.y_min <= self.y_max
+    }
 }
 
+// where (0, 0) is drawn, the layout itself is always stored with y = 0 as the first row
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CoordSystem {
+    // y grows downward, matching the ascii and svg renders
+    #[default]
+    TopLeft,
+    // y grows upward, matching most vendor floorplan views
+    BottomLeft,
+}
+
+impl CoordSystem {
+    // row a layout y coordinate is drawn at
+    pub fn display_y(&self, y: u32, height: u32) -> u32 {
+        match self {
+            CoordSystem::TopLeft => y,
+            CoordSystem::BottomLeft => height - 1 - y,
+        }
+    }
+}
+
+// number of sites a placed macro covers, extending right and down from the
+// coordinate it is placed at
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
+pub struct Footprint {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Footprint {
+    pub fn new(width: u32, height: u32) -> Footprint {
+        Footprint { width, height }
+    }
+}
+
+impl Default for Footprint {
+    fn default() -> Self {
+        Footprint::new(1, 1)
+    }
+}
+
+// a layout needs at least one row and one column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutSizeError {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::fmt::Display for LayoutSizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "a {}x{} layout has no sites", self.width, self.height)
+    }
+}
+
+impl std::error::Error for LayoutSizeError {}
+
 #[derive(Debug, Clone)]
 pub struct FPGALayout {
     pub map: FxHashMap<FPGALayoutCoordinate, FPGALayoutType>,
     pub width: u32,
     pub height: u32,
+    // IO sites missing from this map are bidirectional
+    pub io_directions: FxHashMap<FPGALayoutCoordinate, IoDirection>,
+    // how many nodes a site holds, e.g. the pads of an IO tile, sites missing
+    // from this map hold one
+    pub capacities: FxHashMap<FPGALayoutCoordinate, u32>,
+    // how the cost functions measure distance, see config_topology
+    pub topology: Topology,
 }
 
 impl FPGALayout {
+    // panics on a zero width or height, see try_new
     pub fn new(width: u32, height: u32) -> FPGALayout {
-        FPGALayout {
+        FPGALayout::try_new(width, height).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // new for sizes read from a file, everything else about a layout assumes
+    // there is at least one site, e.g. the last row is at height - 1
+    pub fn try_new(width: u32, height: u32) -> Result<FPGALayout, LayoutSizeError> {
+        if width == 0 || height == 0 {
+            return Err(LayoutSizeError { width, height });
+        }
+
+        Ok(FPGALayout {
             map: FxHashMap::default(),
             width,
             height,
-        }
+            io_directions: FxHashMap::default(),
+            capacities: FxHashMap::default(),
+            topology: Topology::default(),
+        })
+    }
+
+    // the wirelength and timing costs measure distance with the topology, site
+    // types and everything else about the layout are unaffected
+    pub fn config_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    // manhattan distance between two sites under the layout's topology,
+    // saturating rather than wrapping around to a short distance on a fabric
+    // whose sides add up to more than u32::MAX
+    pub fn distance(&self, a: &FPGALayoutCoordinate, b: &FPGALayoutCoordinate) -> u32 {
+        self.topology
+            .axis_distance(a.x, b.x, self.width)
+            .saturating_add(self.topology.axis_distance(a.y, b.y, self.height))
+    }
+
+    pub fn config_capacity(&mut self, coordinate: FPGALayoutCoordinate, capacity: u32) {
+        assert!(capacity > 0, "a site holds at least one node");
+        self.capacities.insert(coordinate, capacity);
+    }
+
+    pub fn capacity(&self, coordinate: &FPGALayoutCoordinate) -> u32 {
+        self.capacities.get(coordinate).copied().unwrap_or(1)
+    }
+
+    // the most nodes any one site holds
+    pub fn max_capacity(&self) -> u32 {
+        self.capacities.values().copied().max().unwrap_or(1).max(1)
+    }
+
+    pub fn config_io_direction(
+        &mut self,
+        coordinate: FPGALayoutCoordinate,
+        direction: IoDirection,
+    ) {
+        self.io_directions.insert(coordinate, direction);
+    }
+
+    pub fn io_direction(&self, coordinate: &FPGALayoutCoordinate) -> IoDirection {
+        self.io_directions
+            .get(coordinate)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // let the site host any of the given macro types
+    pub fn config_multi_type(
+        &mut self,
+        coordinate: FPGALayoutCoordinate,
+        macro_types: &[MacroType],
+    ) {
+        assert!(coordinate.x < self.width && coordinate.y < self.height);
+        self.map
+            .insert(coordinate, FPGALayoutType::multi_type(macro_types));
     }
 
     pub fn config_corners(&mut self, layout_type: FPGALayoutType) {
@@ -79,6 +426,28 @@ impl FPGALayout {
         }
     }
 
+    // config_border for the outer thickness rings, e.g. a 2 cell deep IO
+    // periphery, the rings may at most meet in the middle
+    pub fn config_border_thick(&mut self, layout_type: FPGALayoutType, thickness: u32) {
+        assert!(
+            thickness <= self.width.min(self.height) / 2,
+            "border thickness {} is more than half of the {}x{} layout",
+            thickness,
+            self.width,
+            self.height
+        );
+
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let ring = x.min(y).min(self.width - 1 - x).min(self.height - 1 - y);
+                if ring < thickness {
+                    self.map
+                        .insert(FPGALayoutCoordinate::new(x, y), layout_type.clone());
+                }
+            }
+        }
+    }
+
     pub fn config_repeat(
         &mut self,
         x: u32,
@@ -89,8 +458,8 @@ impl FPGALayout {
         step_y: u32,
         layout_type: FPGALayoutType,
     ) {
-        for x in (x..(x + width)).step_by(step_x as usize) {
-            for y in (y..(y + height)).step_by(step_y as usize) {
+        for x in (x..x.saturating_add(width)).step_by(step_x as usize) {
+            for y in (y..y.saturating_add(height)).step_by(step_y as usize) {
                 if x >= self.width || y >= self.height {
                     continue;
                 }
@@ -138,6 +507,10 @@ impl FPGALayout {
                     FPGALayoutType::MacroType(MacroType::DSP) => dsp_count += 1,
                     FPGALayoutType::MacroType(MacroType::BRAM) => bram_count += 1,
                     FPGALayoutType::MacroType(MacroType::IO) => io_count += 1,
+                    // each distinct set of types gets its own entry
+                    FPGALayoutType::MultiType(_) => {
+                        *count_summary.entry(layout_type).or_insert(0) += 1
+                    }
                     FPGALayoutType::EMPTY => empty_count += 1,
                 }
             }
@@ -166,6 +539,7 @@ impl FPGALayout {
         let mut dsp_count = 0;
         let mut bram_count = 0;
         let mut io_count = 0;
+        let mut multi_count = 0;
         let mut empty_count = 0;
 
         for x in 0..self.width {
@@ -180,6 +554,7 @@ impl FPGALayout {
                     FPGALayoutType::MacroType(MacroType::DSP) => dsp_count += 1,
                     FPGALayoutType::MacroType(MacroType::BRAM) => bram_count += 1,
                     FPGALayoutType::MacroType(MacroType::IO) => io_count += 1,
+                    FPGALayoutType::MultiType(_) => multi_count += 1,
                     FPGALayoutType::EMPTY => empty_count += 1,
                 }
             }
@@ -189,12 +564,157 @@ impl FPGALayout {
         output.push_str(&format!("DSP Count: {}\n", dsp_count));
         output.push_str(&format!("BRAM Count: {}\n", bram_count));
         output.push_str(&format!("IO Count: {}\n", io_count));
+        if multi_count > 0 {
+            output.push_str(&format!("Multi Type Count: {}\n", multi_count));
+        }
         output.push_str(&format!("Empty Count: {}\n", empty_count));
 
         output
     }
 
+    // site types as a height x width matrix of FPGALayoutType::grid_code values,
+    // indexed grid[y][x] with y = 0 as the first row so it can go straight into
+    // numpy / imshow
+    pub fn to_grid(&self) -> Vec<Vec<u8>> {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| {
+                        self.get(&FPGALayoutCoordinate::new(x, y))
+                            .unwrap_or(FPGALayoutType::EMPTY)
+                            .grid_code()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // every site once, in the order a space filling curve visits them, so
+    // sites close together on the curve are close together on the fabric
+    // a plain hilbert curve only covers 2^n x 2^n grids, this is the
+    // generalized ("gilbert") curve, which splits the longer side of the
+    // rectangle in two (or in three, walking the middle part the other way,
+    // when the rectangle is close to square) and recurses until a part is a
+    // single row or column, one step on the curve is one step on the fabric,
+    // apart from a single diagonal step on grids with an odd longer side and
+    // an even shorter side of at least 4
+    pub fn hilbert_order(&self) -> Vec<FPGALayoutCoordinate> {
+        let mut order = Vec::with_capacity(self.width as usize * self.height as usize);
+        let (width, height) = (self.width as i64, self.height as i64);
+        if width >= height {
+            hilbert_walk(&mut order, (0, 0), (width, 0), (0, height));
+        } else {
+            hilbert_walk(&mut order, (0, 0), (0, height), (width, 0));
+        }
+        order
+    }
+
+    // columns holding at least one BRAM or DSP site, sorted, crossing one of
+    // these costs extra routing on architectures with hard block columns
+    pub fn special_columns(&self) -> Vec<u32> {
+        (0..self.width)
+            .filter(|x| {
+                (0..self.height).any(|y| {
+                    self.get(&FPGALayoutCoordinate::new(*x, y))
+                        .is_some_and(|layout_type| {
+                            layout_type.accepts(MacroType::BRAM)
+                                || layout_type.accepts(MacroType::DSP)
+                        })
+                })
+            })
+            .collect()
+    }
+
+    // the non empty sites as a grid graph, e.g. to catch a BRAM column walled
+    // off from the IOs by empty cells, neighbors wrap around on a torus
+    pub fn connectivity_report(&self) -> ConnectivityReport {
+        let site_type = |x: u32, y: u32| match self.get(&FPGALayoutCoordinate::new(x, y)) {
+            Some(FPGALayoutType::EMPTY) | None => None,
+            Some(layout_type) => Some(layout_type.macro_types()),
+        };
+
+        let mut visited = vec![vec![false; self.width as usize]; self.height as usize];
+        let mut components = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if visited[y as usize][x as usize] || site_type(x, y).is_none() {
+                    continue;
+                }
+
+                visited[y as usize][x as usize] = true;
+                let mut component = Vec::new();
+                let mut stack = vec![(x, y)];
+                while let Some((x, y)) = stack.pop() {
+                    component.push(FPGALayoutCoordinate::new(x, y));
+                    let mut neighbors = Vec::with_capacity(4);
+                    if x > 0 {
+                        neighbors.push((x - 1, y));
+                    } else if self.topology == Topology::Torus {
+                        neighbors.push((self.width - 1, y));
+                    }
+                    if x + 1 < self.width {
+                        neighbors.push((x + 1, y));
+                    } else if self.topology == Topology::Torus {
+                        neighbors.push((0, y));
+                    }
+                    if y > 0 {
+                        neighbors.push((x, y - 1));
+                    } else if self.topology == Topology::Torus {
+                        neighbors.push((x, self.height - 1));
+                    }
+                    if y + 1 < self.height {
+                        neighbors.push((x, y + 1));
+                    } else if self.topology == Topology::Torus {
+                        neighbors.push((x, 0));
+                    }
+
+                    for (nx, ny) in neighbors {
+                        if !visited[ny as usize][nx as usize] && site_type(nx, ny).is_some() {
+                            visited[ny as usize][nx as usize] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+                component.sort_by_key(|site| (site.x, site.y));
+                components.push(component);
+            }
+        }
+        // stable, so equal sizes keep the order they were found in
+        components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+
+        let mut in_main = FxHashMap::default();
+        let mut sites_outside_main = FxHashMap::default();
+        for (index, component) in components.iter().enumerate() {
+            for site in component {
+                // a multi type site counts for each type it accepts
+                for macro_type in site_type(site.x, site.y).unwrap() {
+                    let seen_in_main = in_main.entry(macro_type).or_insert(false);
+                    if index == 0 {
+                        *seen_in_main = true;
+                    } else {
+                        *sites_outside_main.entry(macro_type).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+
+        ConnectivityReport {
+            components,
+            in_main,
+            sites_outside_main,
+        }
+    }
+
     pub fn render_ascii(&self) -> String {
+        self.render_ascii_with(|location| {
+            let layout_type = self.get(location).unwrap_or(FPGALayoutType::EMPTY);
+            format!(" {} ", layout_type.ascii_char())
+        })
+    }
+
+    // grid drawing shared by the ascii renders, cell returns the 3 characters
+    // drawn inside each cell
+    pub fn render_ascii_with<F: Fn(&FPGALayoutCoordinate) -> String>(&self, cell: F) -> String {
         let mut output = String::new();
 
         // Draw the top line
@@ -210,18 +730,8 @@ impl FPGALayout {
         for y in 0..self.height {
             // Draw the cells
             for x in 0..self.width {
-                let layout_type = self
-                    .map
-                    .get(&FPGALayoutCoordinate::new(x, y))
-                    .unwrap_or(&FPGALayoutType::EMPTY);
-
-                match layout_type {
-                    FPGALayoutType::MacroType(MacroType::CLB) => output.push_str("│ C "),
-                    FPGALayoutType::MacroType(MacroType::DSP) => output.push_str("│ D "),
-                    FPGALayoutType::MacroType(MacroType::BRAM) => output.push_str("│ B "),
-                    FPGALayoutType::MacroType(MacroType::IO) => output.push_str("│ I "),
-                    FPGALayoutType::EMPTY => output.push_str("│   "),
-                }
+                output.push('│');
+                output.push_str(&cell(&FPGALayoutCoordinate::new(x, y)));
             }
             output.push_str("│\n");
 
@@ -252,6 +762,94 @@ impl FPGALayout {
     }
 }
 
+// how the non empty sites of a layout hang together, see FPGALayout::connectivity_report
+#[derive(Debug, Clone)]
+pub struct ConnectivityReport {
+    // sites joined through their 4 neighbors, the largest component first
+    // (ties go to the one with the lowest site), sites sorted by (x, y)
+    pub components: Vec<Vec<FPGALayoutCoordinate>>,
+    // every macro type on the layout and whether any of its sites is in the
+    // main (first) component
+    pub in_main: FxHashMap<MacroType, bool>,
+    // sites of each macro type walled off from the main component, types with
+    // every site in it are left out
+    pub sites_outside_main: FxHashMap<MacroType, u32>,
+}
+
+impl ConnectivityReport {
+    // every non empty site can reach every other one
+    pub fn is_connected(&self) -> bool {
+        self.components.len() <= 1
+    }
+}
+
+// walk the rectangle with corner start spanned by the vectors a (the side the
+// walk runs along) and b, see FPGALayout::hilbert_order
+fn hilbert_walk(
+    order: &mut Vec<FPGALayoutCoordinate>,
+    start: (i64, i64),
+    a: (i64, i64),
+    b: (i64, i64),
+) {
+    let (x, y) = start;
+    let (ax, ay) = a;
+    let (bx, by) = b;
+    let w = (ax + ay).abs();
+    let h = (bx + by).abs();
+    let (dax, day) = (ax.signum(), ay.signum());
+    let (dbx, dby) = (bx.signum(), by.signum());
+
+    if w == 0 || h == 0 {
+        return;
+    }
+    if h == 1 {
+        for i in 0..w {
+            order.push(FPGALayoutCoordinate::new(
+                (x + i * dax) as u32,
+                (y + i * day) as u32,
+            ));
+        }
+        return;
+    }
+    if w == 1 {
+        for i in 0..h {
+            order.push(FPGALayoutCoordinate::new(
+                (x + i * dbx) as u32,
+                (y + i * dby) as u32,
+            ));
+        }
+        return;
+    }
+
+    // floor division so the halves of a negative vector round the same way
+    let (mut ax2, mut ay2) = (ax.div_euclid(2), ay.div_euclid(2));
+    let (mut bx2, mut by2) = (bx.div_euclid(2), by.div_euclid(2));
+    let w2 = (ax2 + ay2).abs();
+    let h2 = (bx2 + by2).abs();
+
+    if 2 * w > 3 * h {
+        // long rectangle, split it in two along a, with even halves where possible
+        if w2 % 2 == 1 && w > 2 {
+            (ax2, ay2) = (ax2 + dax, ay2 + day);
+        }
+        hilbert_walk(order, (x, y), (ax2, ay2), (bx, by));
+        hilbert_walk(order, (x + ax2, y + ay2), (ax - ax2, ay - ay2), (bx, by));
+    } else {
+        // up along half of b, across the full a, and back down
+        if h2 % 2 == 1 && h > 2 {
+            (bx2, by2) = (bx2 + dbx, by2 + dby);
+        }
+        hilbert_walk(order, (x, y), (bx2, by2), (ax2, ay2));
+        hilbert_walk(order, (x + bx2, y + by2), (ax, ay), (bx - bx2, by - by2));
+        hilbert_walk(
+            order,
+            (x + (ax - dax) + (bx2 - dbx), y + (ay - day) + (by2 - dby)),
+            (-bx2, -by2),
+            (-(ax - ax2), -(ay - ay2)),
+        );
+    }
+}
+
 pub fn build_simple_fpga_layout(width: u32, height: u32) -> FPGALayout {
     let mut layout = FPGALayout::new(width, height);
 
@@ -260,8 +858,8 @@ pub fn build_simple_fpga_layout(width: u32, height: u32) -> FPGALayout {
     layout.config_repeat(
         1,
         1,
-        width - 2,
-        height - 2,
+        width.saturating_sub(2),
+        height.saturating_sub(2),
         1,
         1,
         FPGALayoutType::MacroType(MacroType::CLB),
@@ -271,8 +869,8 @@ pub fn build_simple_fpga_layout(width: u32, height: u32) -> FPGALayout {
     layout.config_repeat(
         10,
         1,
-        width - 2,
-        height - 2,
+        width.saturating_sub(2),
+        height.saturating_sub(2),
         10,
         1,
         FPGALayoutType::MacroType(MacroType::BRAM),
@@ -282,3 +880,344 @@ pub fn build_simple_fpga_layout(width: u32, height: u32) -> FPGALayout {
 
     layout
 }
+
+// columns of one macro type replacing the fill at first_col, first_col +
+// stride, ... across the interior rows
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ColumnRule {
+    pub macro_type: MacroType,
+    pub first_col: u32,
+    pub stride: u32,
+}
+
+// describes a family of fabrics like build_simple_fpga_layout's, a border
+// ring with its own corners around a fill type broken up by hard block columns
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FabricSpec {
+    pub width: u32,
+    pub height: u32,
+    pub border: FPGALayoutType,
+    pub corners: FPGALayoutType,
+    pub fill: FPGALayoutType,
+    pub columns: Vec<ColumnRule>,
+}
+
+impl FabricSpec {
+    // IO border, empty corners and a CLB fill without any columns
+    pub fn new(width: u32, height: u32) -> FabricSpec {
+        FabricSpec {
+            width,
+            height,
+            border: FPGALayoutType::MacroType(MacroType::IO),
+            corners: FPGALayoutType::EMPTY,
+            fill: FPGALayoutType::MacroType(MacroType::CLB),
+            columns: Vec::new(),
+        }
+    }
+
+    pub fn with_column(mut self, macro_type: MacroType, first_col: u32, stride: u32) -> FabricSpec {
+        self.columns.push(ColumnRule {
+            macro_type,
+            first_col,
+            stride,
+        });
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FabricSpecError {
+    // a fabric needs at least one interior site inside the border
+    TooSmall {
+        width: u32,
+        height: u32,
+    },
+    ZeroStride(ColumnRule),
+    // two rules claim the same interior column
+    ColumnConflict {
+        column: u32,
+        first: ColumnRule,
+        second: ColumnRule,
+    },
+}
+
+impl std::fmt::Display for FabricSpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FabricSpecError::TooSmall { width, height } => {
+                write!(f, "a {}x{} fabric has no interior sites", width, height)
+            }
+            FabricSpecError::ZeroStride(rule) => {
+                write!(f, "{} column rule has a stride of 0", rule.macro_type)
+            }
+            FabricSpecError::ColumnConflict {
+                column,
+                first,
+                second,
+            } => write!(
+                f,
+                "column {} is claimed by both a {} and a {} column rule",
+                column, first.macro_type, second.macro_type
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FabricSpecError {}
+
+// realize a FabricSpec, columns only cover the interior so the border and
+// corners stay intact
+pub fn build_fpga_layout(spec: &FabricSpec) -> Result<FPGALayout, FabricSpecError> {
+    if spec.width < 3 || spec.height < 3 {
+        return Err(FabricSpecError::TooSmall {
+            width: spec.width,
+            height: spec.height,
+        });
+    }
+
+    let mut column_owners: FxHashMap<u32, ColumnRule> = FxHashMap::default();
+    for rule in &spec.columns {
+        if rule.stride == 0 {
+            return Err(FabricSpecError::ZeroStride(*rule));
+        }
+        for column in (rule.first_col.max(1)..spec.width - 1)
+            .filter(|column| (column - rule.first_col).is_multiple_of(rule.stride))
+        {
+            if let Some(first) = column_owners.insert(column, *rule) {
+                return Err(FabricSpecError::ColumnConflict {
+                    column,
+                    first,
+                    second: *rule,
+                });
+            }
+        }
+    }
+
+    let mut layout = FPGALayout::new(spec.width, spec.height);
+    layout.config_border(spec.border.clone());
+    layout.config_corners(spec.corners.clone());
+    layout.config_repeat(
+        1,
+        1,
+        spec.width - 2,
+        spec.height - 2,
+        1,
+        1,
+        spec.fill.clone(),
+    );
+    for (column, rule) in column_owners {
+        layout.config_repeat(
+            column,
+            1,
+            1,
+            spec.height - 2,
+            1,
+            1,
+            FPGALayoutType::MacroType(rule.macro_type),
+        );
+    }
+
+    assert!(layout.valid());
+
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::netlist::*;
+    use crate::placer::*;
+
+    fn node(id: u32, macro_type: MacroType) -> NetlistNode {
+        NetlistNode { id, macro_type }
+    }
+
+    #[test]
+    fn dual_site_hosts_clb_and_dsp() {
+        let mut layout = build_simple_fpga_layout(5, 5);
+        let dual_site = FPGALayoutCoordinate::new(2, 2);
+        layout.config_multi_type(dual_site, &[MacroType::DSP, MacroType::CLB]);
+        assert_eq!(
+            layout.get(&dual_site),
+            Some(FPGALayoutType::MultiType(vec![
+                MacroType::CLB,
+                MacroType::DSP
+            ]))
+        );
+        assert!(layout.valid());
+
+        let (dsp, clb) = (node(0, MacroType::DSP), node(1, MacroType::CLB));
+        let netlist = NetlistGraph::from_nets(&[dsp, clb], &[vec![dsp, clb]]);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        assert_eq!(solution.get_possible_sites_for(&dsp), vec![dual_site]);
+        assert!(solution.get_possible_sites_for(&clb).contains(&dual_site));
+
+        // either type is legal on the site, but only one node fits at a time
+        solution.place_node(clb, dual_site);
+        assert!(solution.validate(true).is_empty());
+        assert!(solution.get_possible_sites_for(&dsp).is_empty());
+        solution.place_node(clb, FPGALayoutCoordinate::new(1, 1));
+        solution.place_node(dsp, dual_site);
+        assert!(solution.valid());
+        assert!(!layout.get(&dual_site).unwrap().accepts(MacroType::BRAM));
+    }
+
+    #[test]
+    fn grids_hold_site_and_node_codes() {
+        let mut layout = build_simple_fpga_layout(4, 3);
+        layout.config_multi_type(
+            FPGALayoutCoordinate::new(2, 1),
+            &[MacroType::CLB, MacroType::DSP],
+        );
+        assert_eq!(
+            layout.to_grid(),
+            vec![vec![0, 4, 4, 0], vec![4, 1, 5, 4], vec![0, 4, 4, 0]]
+        );
+
+        let (clb, dsp, io) = (
+            node(0, MacroType::CLB),
+            node(1, MacroType::DSP),
+            node(2, MacroType::IO),
+        );
+        let netlist = NetlistGraph::from_nets(&[clb, dsp, io], &[vec![clb, dsp, io]]);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(dsp, FPGALayoutCoordinate::new(2, 1));
+        solution.place_node(io, FPGALayoutCoordinate::new(1, 0));
+        // an unplaced node leaves its site at 0
+        assert_eq!(
+            solution.to_occupancy_grid(),
+            vec![vec![0, 4, 0, 0], vec![0, 0, 2, 0], vec![0, 0, 0, 0]]
+        );
+        solution.place_node(clb, FPGALayoutCoordinate::new(1, 1));
+        assert_eq!(
+            solution.to_occupancy_grid(),
+            vec![vec![0, 4, 0, 0], vec![0, 1, 2, 0], vec![0, 0, 0, 0]]
+        );
+    }
+
+    #[test]
+    fn one_by_one_layout() {
+        let mut layout = FPGALayout::new(1, 1);
+        layout.config_border(FPGALayoutType::MacroType(MacroType::IO));
+        layout.config_corners(FPGALayoutType::EMPTY);
+        let site = FPGALayoutCoordinate::new(0, 0);
+        assert_eq!(layout.get(&site), Some(FPGALayoutType::EMPTY));
+        assert_eq!(layout.distance(&site, &site), 0);
+
+        layout.config_topology(Topology::Torus);
+        assert_eq!(layout.distance(&site, &site), 0);
+    }
+
+    #[test]
+    fn zero_sized_layouts_are_refused() {
+        for (width, height) in [(0, 0), (0, 4), (4, 0)] {
+            assert_eq!(
+                FPGALayout::try_new(width, height).unwrap_err(),
+                LayoutSizeError { width, height }
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "a 0x0 layout has no sites")]
+    fn new_panics_on_a_zero_sized_layout() {
+        FPGALayout::new(0, 0);
+    }
+
+    #[test]
+    fn distance_saturates_on_huge_fabrics() {
+        let layout = FPGALayout::new(u32::MAX, u32::MAX);
+        let corner = FPGALayoutCoordinate::new(u32::MAX - 1, u32::MAX - 1);
+        let origin = FPGALayoutCoordinate::new(0, 0);
+        assert_eq!(layout.distance(&origin, &corner), u32::MAX);
+        assert_eq!(
+            origin.manhattan_distance(&corner),
+            2 * (u32::MAX as u64 - 1)
+        );
+    }
+
+    #[test]
+    fn border_thickness_2_marks_64_cells() {
+        let io = FPGALayoutType::MacroType(MacroType::IO);
+        let mut layout = FPGALayout::new(10, 10);
+        layout.config_border_thick(io.clone(), 2);
+        let border = (0..10)
+            .flat_map(|x| (0..10).map(move |y| FPGALayoutCoordinate::new(x, y)))
+            .filter(|site| layout.get(site) == Some(io.clone()))
+            .collect::<Vec<_>>();
+        // 100 cells less the 6 x 6 interior
+        assert_eq!(border.len(), 64);
+        assert!(border
+            .iter()
+            .all(|site| { site.x < 2 || site.y < 2 || site.x >= 8 || site.y >= 8 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "border thickness 6 is more than half")]
+    fn border_thicker_than_half_the_layout_panics() {
+        FPGALayout::new(10, 12).config_border_thick(FPGALayoutType::EMPTY, 6);
+    }
+
+    #[test]
+    fn a_moat_walls_off_one_cell() {
+        let mut layout = build_simple_fpga_layout(12, 12);
+        assert!(layout.connectivity_report().is_connected());
+
+        // a DSP at (5, 5) ringed by empty cells
+        layout.config_repeat(4, 4, 3, 3, 1, 1, FPGALayoutType::EMPTY);
+        layout.config_repeat(5, 5, 1, 1, 1, 1, FPGALayoutType::MacroType(MacroType::DSP));
+        let report = layout.connectivity_report();
+        assert!(!report.is_connected());
+        assert_eq!(report.components.len(), 2);
+        // 144 sites less the 4 empty corners, the moat and the walled off DSP
+        assert_eq!(report.components[0].len(), 144 - 4 - 9);
+        assert_eq!(report.components[1], vec![FPGALayoutCoordinate::new(5, 5)]);
+        assert!(!report.in_main[&MacroType::DSP]);
+        for macro_type in [MacroType::CLB, MacroType::BRAM, MacroType::IO] {
+            assert!(report.in_main[&macro_type]);
+        }
+        assert_eq!(
+            report.sites_outside_main.into_iter().collect::<Vec<_>>(),
+            vec![(MacroType::DSP, 1)]
+        );
+    }
+
+    #[test]
+    fn dsp_every_8_and_bram_every_12() {
+        let spec = FabricSpec::new(24, 10)
+            .with_column(MacroType::DSP, 8, 8)
+            .with_column(MacroType::BRAM, 12, 12);
+        let mut layout = build_fpga_layout(&spec).unwrap();
+        assert!(layout.valid());
+        assert_eq!(layout.special_columns(), vec![8, 12, 16]);
+        for (x, macro_type) in [
+            (8, MacroType::DSP),
+            (12, MacroType::BRAM),
+            (16, MacroType::DSP),
+            (20, MacroType::CLB),
+        ] {
+            for y in 1..9 {
+                assert_eq!(
+                    layout.get(&FPGALayoutCoordinate::new(x, y)),
+                    Some(FPGALayoutType::MacroType(macro_type))
+                );
+            }
+        }
+        // the border is left alone
+        assert_eq!(
+            layout.get(&FPGALayoutCoordinate::new(8, 0)),
+            Some(FPGALayoutType::MacroType(MacroType::IO))
+        );
+
+        // a wider fabric reaches column 24, which both rules claim
+        let wide = FabricSpec { width: 30, ..spec };
+        assert!(matches!(
+            build_fpga_layout(&wide),
+            Err(FabricSpecError::ColumnConflict { column: 24, .. })
+        ));
+        assert!(matches!(
+            build_fpga_layout(&FabricSpec::new(10, 10).with_column(MacroType::DSP, 2, 0)),
+            Err(FabricSpecError::ZeroStride(_))
+        ));
+    }
+}
@@ -1,10 +1,38 @@
 #![feature(portable_simd)]
 #![feature(iter_array_chunks)]
 
+pub mod analytical;
+pub mod benchmark;
+pub mod brute_force;
+pub mod constraints;
+pub mod cost_function;
+pub mod experiment;
 pub mod fpga_layout;
+pub mod multi_design;
+pub mod multilevel;
+pub mod net_cache;
 pub mod netlist;
 pub mod placer;
+pub mod relative_group;
+pub mod spatial_index;
+pub mod sweep;
+pub mod tempering;
+pub mod timing;
 
+pub use analytical::*;
+pub use benchmark::*;
+pub use brute_force::*;
+pub use constraints::*;
+pub use cost_function::*;
+pub use experiment::*;
 pub use fpga_layout::*;
+pub use multi_design::*;
+pub use multilevel::*;
+pub use net_cache::*;
 pub use netlist::*;
 pub use placer::*;
+pub use relative_group::*;
+pub use spatial_index::*;
+pub use sweep::*;
+pub use tempering::*;
+pub use timing::*;
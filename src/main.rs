@@ -1,8 +1,66 @@
 use std::{collections::HashMap, process::Command, sync::Mutex};
 
+use clap::Parser;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use rayon::prelude::*;
 use sa_placer_lib::*;
 
+/// sweep the SA placer over several n_neighbors values on a random netlist
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// fabric width in sites
+    #[arg(long, default_value_t = 64)]
+    width: u32,
+
+    /// fabric height in sites
+    #[arg(long, default_value_t = 64)]
+    height: u32,
+
+    /// number of nodes in the random netlist
+    #[arg(long, default_value_t = 300)]
+    nodes: u32,
+
+    /// number of steps to run for all placement runs
+    #[arg(long, default_value_t = 1000)]
+    steps: u32,
+
+    /// values of n_neighbors to explore, comma separated
+    #[arg(long, value_delimiter = ',', default_values_t = vec![1, 2, 4, 8, 16, 32, 64, 128, 256])]
+    neighbors: Vec<usize>,
+
+    /// narrow each run from its n_neighbors down to this many as it cools
+    #[arg(long)]
+    min_neighbors: Option<usize>,
+
+    /// starting annealing temperature, 0 only accepts improving moves
+    #[arg(long, default_value_t = 0.0)]
+    temperature: f32,
+
+    /// run inner_num * n_blocks^(4/3) steps at each temperature like VPR
+    /// instead of cooling after every step
+    #[arg(long)]
+    inner_num: Option<f32>,
+
+    /// seed for the netlist, initial placement and placer runs, random if not given
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// directory the csvs, plots and renders are written to, it is cleared first
+    #[arg(long, default_value = "./output_data")]
+    output_dir: String,
+
+    // defaults to 6 becasue my laptop has 12 logical cores
+    /// number of rayon worker threads
+    #[arg(long, default_value_t = 6)]
+    threads: usize,
+
+    /// skip the svg / png renders of the placements
+    #[arg(long)]
+    no_render: bool,
+}
+
 fn render_solution_to_png(
     solution: &PlacementSolution<'_>,
     output_name: &str,
@@ -30,48 +88,56 @@ fn render_solution_to_png(
 }
 
 fn main() {
-    // limit the number of threads to 6 becasue my laptop has 12 logical cores
+    let args = Args::parse();
+    let output_dir = args.output_dir.as_str();
+    let render = !args.no_render;
+
     rayon::ThreadPoolBuilder::new()
-        .num_threads(6)
+        .num_threads(args.threads)
         .build_global()
         .unwrap();
 
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
     // make a data directory
-    if std::path::Path::new("./output_data").exists() {
-        std::fs::remove_dir_all("./output_data").expect("Unable to remove directory");
+    if std::path::Path::new(output_dir).exists() {
+        std::fs::remove_dir_all(output_dir).expect("Unable to remove directory");
     }
-    std::fs::create_dir_all("./output_data").expect("Unable to create directory");
+    std::fs::create_dir_all(output_dir).expect("Unable to create directory");
 
     // build FPGA layout
-    let layout = build_simple_fpga_layout(64, 64);
+    let layout = build_simple_fpga_layout(args.width, args.height);
 
     // ascii render of the layout
     let vis = layout.render_ascii();
-    std::fs::write("./output_data/fpga_layout.txt", vis).expect("Unable to write file");
+    std::fs::write(format!("{}/fpga_layout.txt", output_dir), vis).expect("Unable to write file");
 
     // text summary of the fpga layout
     let summary = layout.render_summary();
-    std::fs::write("./output_data/fpga_layout_summary.txt", summary).expect("Unable to write file");
+    std::fs::write(format!("{}/fpga_layout_summary.txt", output_dir), summary)
+        .expect("Unable to write file");
 
     // build a random netlist
-    let netlist: NetlistGraph = build_simple_netlist(300, 30, 100);
+    let netlist: NetlistGraph = build_simple_netlist_with_rng(args.nodes, 30, 100, &mut rng);
 
     // build a random initial placement solution
     let inital_placement_method = InitialPlacerMethod::Random;
-    let initial_solution = gen_initial_placement(&layout, &netlist, inital_placement_method);
+    let initial_solution =
+        gen_initial_placement_with_rng(&layout, &netlist, inital_placement_method, &mut rng)
+            .unwrap_or_else(|err| panic!("unable to place the netlist: {}", err));
 
-    render_solution_to_png(
-        &initial_solution,
-        "initial_solution",
-        "./output_data",
-        false,
-    );
+    if render {
+        render_solution_to_png(&initial_solution, "initial_solution", output_dir, false);
+    }
 
     // values of n_neighbors to explore
-    let configs_n_neighbors = vec![1, 2, 4, 8, 16, 32, 64, 128, 256];
+    let configs_n_neighbors = args.neighbors.clone();
 
     // number of steps to run for all placement runs
-    let n_steps = 1000;
+    let n_steps = args.steps;
 
     let x_data_collection = Mutex::new(HashMap::new());
     let y_data_collection = Mutex::new(HashMap::new());
@@ -81,8 +147,24 @@ fn main() {
 
     config_data_collection.par_iter().for_each(|&n_neighbors| {
         println!("Running SA Placer with {} neighbors", n_neighbors);
-        let placer_output =
-            fast_sa_placer(initial_solution.clone(), n_steps, n_neighbors, false, true);
+        let config = PlacerConfig {
+            n_steps,
+            n_neighbors,
+            render,
+            neighbor_schedule: match args.min_neighbors {
+                Some(min) => NeighborSchedule::Cooling { min },
+                None => NeighborSchedule::Constant,
+            },
+            initial_temperature: args.temperature,
+            cooling_schedule: match args.inner_num {
+                Some(inner_num) => CoolingSchedule::Vpr { inner_num },
+                None => CoolingSchedule::EveryStep,
+            },
+            // give every run its own stream so runs don't just repeat each other
+            seed: args.seed.map(|seed| seed.wrapping_add(n_neighbors as u64)),
+            ..Default::default()
+        };
+        let placer_output = Placer::new(initial_solution.clone(), config).run(None);
 
         let final_solution: PlacementSolution<'_> = placer_output.final_solution;
 
@@ -97,7 +179,9 @@ fn main() {
 
         let mut render_collection = render_collection.lock().unwrap();
         let mut final_solution_collection = final_solution_collection.lock().unwrap();
-        render_collection.insert(n_neighbors, placer_output.renderer.unwrap());
+        if let Some(renderer) = placer_output.renderer {
+            render_collection.insert(n_neighbors, renderer);
+        }
         final_solution_collection.insert(n_neighbors, final_solution.clone());
     });
 
@@ -106,12 +190,13 @@ fn main() {
     let y_data_collection = y_data_collection.into_inner().unwrap();
 
     let final_solution_collection = final_solution_collection.into_inner().unwrap();
-    let render_collection = render_collection.into_inner().unwrap();
+    let _render_collection = render_collection.into_inner().unwrap();
 
     // csv for each n_neighbors
     for n in config_data_collection.clone().iter() {
         let mut wtr: csv::Writer<std::fs::File> =
-            csv::Writer::from_path(format!("output_data/fpga_placer_history_{}.csv", n)).unwrap();
+            csv::Writer::from_path(format!("{}/fpga_placer_history_{}.csv", output_dir, n))
+                .unwrap();
         wtr.write_record(["step", "obj_fn_value"]).unwrap();
         let x_series = x_data_collection.get(n).unwrap();
         let y_series = y_data_collection.get(n).unwrap();
@@ -123,7 +208,7 @@ fn main() {
 
     // one big csv with all the data
     let mut wtr: csv::Writer<std::fs::File> =
-        csv::Writer::from_path("output_data/fpga_placer_history.csv").unwrap();
+        csv::Writer::from_path(format!("{}/fpga_placer_history.csv", output_dir)).unwrap();
     wtr.write_record(["step", "obj_fn_value", "n_neighbors"])
         .unwrap();
     for n_neighbors in config_data_collection.clone() {
@@ -137,7 +222,10 @@ fn main() {
 
     let mut gnuplot_command = String::new();
     gnuplot_command.push_str("set terminal png size 1000,500; ");
-    gnuplot_command.push_str("set output 'output_data/fpga_placer_history.png'; ");
+    gnuplot_command.push_str(&format!(
+        "set output '{}/fpga_placer_history.png'; ",
+        output_dir
+    ));
     gnuplot_command.push_str("set datafile separator ','; ");
     gnuplot_command.push_str("set title 'FPGA Placement History'; ");
     gnuplot_command.push_str("set xlabel 'Step'; ");
@@ -147,8 +235,8 @@ fn main() {
     for n_neighbors in config_data_collection.clone() {
         gnuplot_command.push_str(
             format!(
-                "'output_data/fpga_placer_history_{}.csv' using 1:2 title '{} neighbors' with lines, ",
-                n_neighbors, n_neighbors
+                "'{}/fpga_placer_history_{}.csv' using 1:2 title '{} neighbors' with lines, ",
+                output_dir, n_neighbors, n_neighbors
             )
             .as_str(),
         );
@@ -164,6 +252,10 @@ fn main() {
         .output()
         .expect("failed to execute process");
 
+    if !render {
+        return;
+    }
+
     // render_solution_to_png(&selected_final_solution, "fpga_final_solution");
 
     // selected_renderer.render_to_video("./placer_animation", 30.0, 20);
@@ -172,14 +264,14 @@ fn main() {
         render_solution_to_png(
             solution,
             &format!("final_solution_{}", n_neighbors),
-            "./output_data",
+            output_dir,
             false,
         );
 
         // let renderer: Renderer = render_collection.get(&n_neighbors).unwrap().clone();
         // renderer.render_to_video(
         //     &format!("placer_animation_{}", n_neighbors),
-        //     "./output_data",
+        //     output_dir,
         //     30.0,
         //     20,
         //     true,
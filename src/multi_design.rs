@@ -0,0 +1,222 @@
+use rand::Rng;
+use rayon::prelude::*;
+
+use super::fpga_layout::*;
+use super::netlist::*;
+use super::placer::*;
+
+use rustc_hash::FxHashSet;
+
+// several independent designs placed on the same fabric, each one confined to
+// its own region so the designs never compete for sites
+#[derive(Debug, Clone)]
+pub struct MultiDesignSolution<'a> {
+    pub layout: &'a FPGALayout,
+    pub designs: Vec<PlacementSolution<'a>>,
+}
+
+impl<'a> MultiDesignSolution<'a> {
+    // randomly place each netlist inside its region
+    pub fn new(
+        layout: &'a FPGALayout,
+        designs: &[(&'a NetlistGraph, Region)],
+    ) -> Result<Self, InitialPlacementError> {
+        Self::new_with_rng(layout, designs, &mut rand::thread_rng())
+    }
+
+    pub fn new_with_rng<R: Rng + ?Sized>(
+        layout: &'a FPGALayout,
+        designs: &[(&'a NetlistGraph, Region)],
+        rng: &mut R,
+    ) -> Result<Self, InitialPlacementError> {
+        for (i, (_, region_a)) in designs.iter().enumerate() {
+            for (j, (_, region_b)) in designs.iter().enumerate().skip(i + 1) {
+                if region_a.overlaps(region_b) {
+                    return Err(InitialPlacementError::OverlappingRegions(i, j));
+                }
+            }
+        }
+
+        Ok(Self {
+            layout,
+            designs: designs
+                .iter()
+                .map(|(netlist, region)| {
+                    gen_random_placement_in_region_with_rng(layout, netlist, *region, rng)
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn cost_hpwl(&self) -> f32 {
+        self.designs.iter().map(|design| design.cost_hpwl()).sum()
+    }
+
+    pub fn valid(&self) -> bool {
+        // every design is legal on its own
+        if !self.designs.iter().all(|design| design.valid()) {
+            return false;
+        }
+
+        // and no site is used by two designs, nodes of one design may share a
+        // site with room for several, which design.valid() already checked
+        let sites = self
+            .designs
+            .iter()
+            .map(|design| {
+                design
+                    .solution_map
+                    .values()
+                    .copied()
+                    .collect::<FxHashSet<_>>()
+            })
+            .collect::<Vec<_>>();
+        sites.iter().enumerate().all(|(i, sites_a)| {
+            sites[i + 1..]
+                .iter()
+                .all(|sites_b| sites_a.is_disjoint(sites_b))
+        })
+    }
+
+    // the designs share no nets and no sites, so annealing each one on its own
+    // minimizes the combined cost and they can run in parallel
+    pub fn anneal(&self, config: &PlacerConfig) -> MultiDesignSolution<'a> {
+        let designs = self
+            .designs
+            .par_iter()
+            .map(|design| {
+                Placer::new(design.clone(), config.clone())
+                    .run(None)
+                    .final_solution
+            })
+            .collect();
+
+        MultiDesignSolution {
+            layout: self.layout,
+            designs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn chain_netlist(first_id: u32, n: u32) -> NetlistGraph {
+        let nodes = (first_id..first_id + n)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: MacroType::CLB,
+            })
+            .collect::<Vec<_>>();
+        let nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        NetlistGraph::from_nets(&nodes, &nets)
+    }
+
+    #[test]
+    fn designs_fill_their_regions_exactly() {
+        let layout = build_simple_fpga_layout(6, 6);
+        // two 2x4 blocks of CLB sites, each with exactly as many sites as nodes
+        let left = chain_netlist(0, 8);
+        let right = chain_netlist(8, 8);
+        let designs = [
+            (&left, Region::new(1, 1, 2, 4)),
+            (&right, Region::new(3, 1, 4, 4)),
+        ];
+        let mut rng = StdRng::seed_from_u64(0);
+        let solution = MultiDesignSolution::new_with_rng(&layout, &designs, &mut rng).unwrap();
+        assert!(solution.valid());
+
+        // one more node than the region has sites
+        let too_big = chain_netlist(8, 9);
+        let designs = [
+            (&left, Region::new(1, 1, 2, 4)),
+            (&too_big, Region::new(3, 1, 4, 4)),
+        ];
+        assert!(matches!(
+            MultiDesignSolution::new_with_rng(&layout, &designs, &mut rng),
+            Err(InitialPlacementError::NoFreeSite(_))
+        ));
+    }
+
+    #[test]
+    fn annealed_designs_stay_in_their_regions() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let left = chain_netlist(0, 10);
+        let right = chain_netlist(10, 10);
+        let regions = [Region::new(1, 1, 4, 8), Region::new(5, 1, 8, 8)];
+        let designs = [(&left, regions[0]), (&right, regions[1])];
+        let mut rng = StdRng::seed_from_u64(1);
+        let solution = MultiDesignSolution::new_with_rng(&layout, &designs, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 200,
+            initial_temperature: 3.0,
+            seed: Some(2),
+            ..Default::default()
+        };
+
+        let annealed = solution.anneal(&config);
+        assert!(annealed.valid());
+        assert!(annealed.cost_hpwl() <= solution.cost_hpwl());
+        for (design, region) in annealed.designs.iter().zip(regions) {
+            assert_eq!(design.solution_map.len(), 10);
+            assert!(design
+                .solution_map
+                .values()
+                .all(|location| region.contains(location)));
+        }
+    }
+
+    #[test]
+    fn nodes_of_one_design_may_share_a_site() {
+        let mut layout = build_simple_fpga_layout(6, 6);
+        let shared = FPGALayoutCoordinate::new(1, 1);
+        layout.config_capacity(shared, 2);
+        let left = chain_netlist(0, 2);
+        let right = chain_netlist(2, 2);
+        let mut left_solution = PlacementSolution::new(&layout, &left);
+        for node in left.graph.node_weights() {
+            left_solution.place_node(*node, shared);
+        }
+        let mut right_solution = PlacementSolution::new(&layout, &right);
+        for (node, x) in right.graph.node_weights().zip([3, 4]) {
+            right_solution.place_node(*node, FPGALayoutCoordinate::new(x, 1));
+        }
+        let mut solution = MultiDesignSolution {
+            layout: &layout,
+            designs: vec![left_solution, right_solution],
+        };
+        assert!(solution.valid());
+
+        // but not with a node of another design, even with room left on it
+        let (left_node, right_node) = (
+            *solution.designs[0].netlist.all_nodes_sorted()[1],
+            *solution.designs[1].netlist.all_nodes_sorted()[0],
+        );
+        solution.designs[0].place_node(left_node, FPGALayoutCoordinate::new(2, 1));
+        solution.designs[1].place_node(right_node, shared);
+        assert!(!solution.valid());
+    }
+
+    #[test]
+    fn overlapping_regions_are_an_error() {
+        let layout = build_simple_fpga_layout(6, 6);
+        let left = chain_netlist(0, 2);
+        let right = chain_netlist(2, 2);
+        let designs = [
+            (&left, Region::new(1, 1, 2, 4)),
+            (&right, Region::new(2, 1, 4, 4)),
+        ];
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            MultiDesignSolution::new_with_rng(&layout, &designs, &mut rng).err(),
+            Some(InitialPlacementError::OverlappingRegions(0, 1))
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use rustc_hash::FxHashMap;
+
+use super::fpga_layout::*;
+use super::netlist::*;
+use super::placer::*;
+
+// helpers for multilevel placement: a coarse layout merges each factor x factor
+// block of sites into one site, a placement is coarsened onto it, annealed
+// there and refined back onto the fine layout
+
+pub fn coarse_coordinate(location: &FPGALayoutCoordinate, factor: u32) -> FPGALayoutCoordinate {
+    FPGALayoutCoordinate::new(location.x / factor, location.y / factor)
+}
+
+// top left site of the fine block under a coarse site
+pub fn fine_coordinate(location: &FPGALayoutCoordinate, factor: u32) -> FPGALayoutCoordinate {
+    FPGALayoutCoordinate::new(location.x * factor, location.y * factor)
+}
+
+impl FPGALayout {
+    // each coarse site takes the most common non-EMPTY type of its block, ties
+    // going to the scarcer IO, then BRAM, DSP and CLB so border and column
+    // sites survive, and is EMPTY only if the whole block is, the last row /
+    // column of blocks can be partial
+    // coarse IO sites are bidirectional
+    pub fn coarsen(&self, factor: u32) -> FPGALayout {
+        assert!(factor > 0, "factor must be at least 1");
+
+        let width = self.width.div_ceil(factor);
+        let height = self.height.div_ceil(factor);
+        let mut coarse = FPGALayout::new(width, height);
+        coarse.config_topology(self.topology);
+
+        for coarse_x in 0..width {
+            for coarse_y in 0..height {
+                let mut counts: FxHashMap<MacroType, u32> = FxHashMap::default();
+                for x in coarse_x * factor..((coarse_x + 1) * factor).min(self.width) {
+                    for y in coarse_y * factor..((coarse_y + 1) * factor).min(self.height) {
+                        if let Some(layout_type) = self.get(&FPGALayoutCoordinate::new(x, y)) {
+                            for macro_type in layout_type.macro_types() {
+                                *counts.entry(macro_type).or_insert(0) += 1;
+                            }
+                        }
+                    }
+                }
+
+                let mut layout_type = FPGALayoutType::EMPTY;
+                let mut best_count = 0;
+                for macro_type in [
+                    MacroType::IO,
+                    MacroType::BRAM,
+                    MacroType::DSP,
+                    MacroType::CLB,
+                ] {
+                    let count = counts.get(&macro_type).copied().unwrap_or(0);
+                    if count > best_count {
+                        best_count = count;
+                        layout_type = FPGALayoutType::MacroType(macro_type);
+                    }
+                }
+
+                coarse
+                    .map
+                    .insert(FPGALayoutCoordinate::new(coarse_x, coarse_y), layout_type);
+            }
+        }
+
+        coarse
+    }
+}
+
+impl<'a> PlacementSolution<'a> {
+    // the placement moved onto coarse_layout, which should come from
+    // self.layout.coarsen(factor)
+    // every node goes to the coarse site over its fine site, when several
+    // nodes land on one coarse site (or on one of the wrong type) the lowest
+    // node id keeps it and the rest move to the nearest free legal coarse site,
+    // see legalize, which fails if the coarse fabric runs out of sites
+    pub fn coarsen<'b>(
+        &self,
+        coarse_layout: &'b FPGALayout,
+        factor: u32,
+    ) -> Result<PlacementSolution<'b>, LegalizeError>
+    where
+        'a: 'b,
+    {
+        let mut coarse = PlacementSolution::new(coarse_layout, self.netlist);
+        for (node, location) in &self.solution_map {
+            coarse.place_node(*node, coarse_coordinate(location, factor));
+        }
+        coarse.legalize()?;
+        Ok(coarse)
+    }
+
+    // the inverse of coarsen, every node goes to the top left site of its
+    // block on fine_layout and then collisions and type mismatches are
+    // resolved the same way, lowest node id first
+    pub fn refine<'b>(
+        &self,
+        fine_layout: &'b FPGALayout,
+        factor: u32,
+    ) -> Result<PlacementSolution<'b>, LegalizeError>
+    where
+        'a: 'b,
+    {
+        let mut fine = PlacementSolution::new(fine_layout, self.netlist);
+        for (node, location) in &self.solution_map {
+            fine.place_node(*node, fine_coordinate(location, factor));
+        }
+        fine.legalize()?;
+        Ok(fine)
+    }
+}
+
+// a netlist whose nodes are clusters of another netlist's nodes, see NetlistGraph::cluster
+pub type CoarseNetlist = NetlistGraph;
+
+// nets with more pins than this are left out of the connection weights, they
+// tie a node to everything and so to nothing in particular
+pub const CLUSTER_MAX_NET_PINS: usize = 32;
+
+// which coarse node every fine node was merged into and back
+#[derive(Debug, Clone)]
+pub struct ClusterMap {
+    pub cluster_of: FxHashMap<NetlistNode, NetlistNode>,
+    // fine nodes of every coarse node, sorted by id, the coarse node is the
+    // first of them
+    pub members: FxHashMap<NetlistNode, Vec<NetlistNode>>,
+}
+
+impl ClusterMap {
+    // expand a placement of the coarse netlist back onto the fine netlist on
+    // the same layout, every member starts on its cluster's site and legalize
+    // spreads them out, the lowest id keeping the site and the rest moving to
+    // the nearest free legal site
+    pub fn uncluster<'b>(
+        &self,
+        coarse_solution: &PlacementSolution<'b>,
+        netlist: &'b NetlistGraph,
+    ) -> Result<PlacementSolution<'b>, LegalizeError> {
+        let mut fine = PlacementSolution::new(coarse_solution.layout, netlist);
+        for (cluster, location) in &coarse_solution.solution_map {
+            for member in self.members.get(cluster).into_iter().flatten() {
+                fine.place_node(*member, *location);
+            }
+        }
+        fine.legalize()?;
+        Ok(fine)
+    }
+}
+
+impl NetlistGraph {
+    // one level of heavy edge matching: every pair of nodes sharing a net gets
+    // the connection weight sum of net weight / (pins - 1) over the nets they
+    // share, the clique model where a net's weight is spread over its pins,
+    // then pairs are merged from the heaviest down, each node into at most one
+    // pair, until the weight drops under threshold
+    // only nodes of the same type merge and IOs never do since each one needs
+    // its own pad, so a level at most halves the netlist, call it again on the
+    // coarse netlist to coarsen further
+    // a coarse node takes the id and type of its lowest id member, every fine
+    // net becomes the net over its pins' clusters with its weight kept, nets
+    // left with a single cluster disappear
+    pub fn cluster(&self, threshold: f32) -> (CoarseNetlist, ClusterMap) {
+        let nets = self.nets();
+        let mut connection: FxHashMap<(NetlistNode, NetlistNode), f32> = FxHashMap::default();
+        for (net, pins) in nets.iter().enumerate() {
+            if pins.len() < 2 || pins.len() > CLUSTER_MAX_NET_PINS {
+                continue;
+            }
+            let weight = self.net_weight(net) / (pins.len() - 1) as f32;
+            for (i, a) in pins.iter().enumerate() {
+                for b in &pins[i + 1..] {
+                    if a.macro_type != b.macro_type || a.macro_type == MacroType::IO {
+                        continue;
+                    }
+                    let pair = if a.id <= b.id { (*a, *b) } else { (*b, *a) };
+                    *connection.entry(pair).or_insert(0.0) += weight;
+                }
+            }
+        }
+
+        let mut pairs = connection
+            .into_iter()
+            .filter(|(_, weight)| *weight >= threshold)
+            .collect::<Vec<_>>();
+        pairs.sort_by(|((a0, b0), w0), ((a1, b1), w1)| {
+            w1.partial_cmp(w0)
+                .unwrap()
+                .then((a0.id, b0.id).cmp(&(a1.id, b1.id)))
+        });
+
+        let mut cluster_of = FxHashMap::default();
+        for ((a, b), _) in pairs {
+            if cluster_of.contains_key(&a) || cluster_of.contains_key(&b) {
+                continue;
+            }
+            cluster_of.insert(a, a);
+            cluster_of.insert(b, a);
+        }
+        for node in self.graph.node_weights() {
+            cluster_of.entry(*node).or_insert(*node);
+        }
+
+        let mut members: FxHashMap<NetlistNode, Vec<NetlistNode>> = FxHashMap::default();
+        for (node, cluster) in &cluster_of {
+            members.entry(*cluster).or_default().push(*node);
+        }
+        for cluster_members in members.values_mut() {
+            cluster_members.sort_by_key(|node| node.id);
+        }
+
+        let mut coarse_nodes = members.keys().copied().collect::<Vec<_>>();
+        coarse_nodes.sort_by_key(|node| node.id);
+        let mut coarse_nets = Vec::new();
+        let mut coarse_weights = Vec::new();
+        for (net, pins) in nets.iter().enumerate() {
+            let mut coarse_pins: Vec<NetlistNode> = Vec::new();
+            for pin in pins {
+                let cluster = cluster_of[pin];
+                if !coarse_pins.contains(&cluster) {
+                    coarse_pins.push(cluster);
+                }
+            }
+            if coarse_pins.len() > 1 {
+                coarse_nets.push(coarse_pins);
+                coarse_weights.push(self.net_weight(net));
+            }
+        }
+
+        let mut coarse = NetlistGraph::from_nets(&coarse_nodes, &coarse_nets);
+        for (net, weight) in coarse_weights.into_iter().enumerate() {
+            if weight != 1.0 {
+                coarse.set_net_weight(net, weight);
+            }
+        }
+        for (node, direction) in &self.io_directions {
+            coarse.set_io_direction(*node, *direction);
+        }
+
+        (
+            coarse,
+            ClusterMap {
+                cluster_of,
+                members,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn node(id: u32, macro_type: MacroType) -> NetlistNode {
+        NetlistNode { id, macro_type }
+    }
+
+    #[test]
+    fn coarsen_and_refine_round_trip_a_placement() {
+        let layout = build_simple_fpga_layout(20, 20);
+        let coarse_layout = layout.coarsen(2);
+        assert_eq!((coarse_layout.width, coarse_layout.height), (10, 10));
+        // a corner block holds two IO sites, one CLB and the empty corner
+        let site_type = |x, y| coarse_layout.get(&FPGALayoutCoordinate::new(x, y));
+        assert_eq!(
+            site_type(0, 0),
+            Some(FPGALayoutType::MacroType(MacroType::IO))
+        );
+        assert_eq!(
+            site_type(3, 3),
+            Some(FPGALayoutType::MacroType(MacroType::CLB))
+        );
+        // the BRAM column ties with the CLB column next to it and wins
+        assert_eq!(
+            site_type(5, 3),
+            Some(FPGALayoutType::MacroType(MacroType::BRAM))
+        );
+
+        let mut nodes = (0..20)
+            .map(|id| node(id, MacroType::CLB))
+            .collect::<Vec<_>>();
+        nodes.push(node(20, MacroType::BRAM));
+        nodes.push(node(21, MacroType::IO));
+        nodes.push(node(22, MacroType::IO));
+        let nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(1);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+
+        let coarse = solution.coarsen(&coarse_layout, 2).unwrap();
+        assert!(coarse.valid(), "{:?}", coarse.validate(false));
+        assert_eq!(coarse.solution_map.len(), nodes.len());
+
+        let fine = coarse.refine(&layout, 2).unwrap();
+        assert!(fine.valid(), "{:?}", fine.validate(false));
+        assert_eq!(fine.solution_map.len(), nodes.len());
+        // every coarse site holds one node, which keeps the top left site of
+        // its block whenever that site takes its type
+        for node in &nodes {
+            let top_left = fine_coordinate(&coarse.solution_map[node], 2);
+            if layout.get(&top_left) == Some(FPGALayoutType::MacroType(node.macro_type)) {
+                assert_eq!(fine.solution_map[node], top_left);
+            }
+        }
+    }
+
+    #[test]
+    fn cluster_and_uncluster_round_trip_membership() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let mut nodes = (0..8)
+            .map(|id| node(id, MacroType::CLB))
+            .collect::<Vec<_>>();
+        nodes.push(node(8, MacroType::BRAM));
+        nodes.push(node(9, MacroType::IO));
+        // a CLB chain with a BRAM on one end and an IO pad on the other
+        let mut nets = nodes[..8]
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        nets.push(vec![nodes[7], nodes[8]]);
+        nets.push(vec![nodes[9], nodes[0]]);
+        let mut netlist = NetlistGraph::from_nets(&nodes, &nets);
+        for net in [0, 2, 4, 6] {
+            netlist.set_net_weight(net, 4.0);
+        }
+
+        let (coarse, cluster_map) = netlist.cluster(2.0);
+        let mut clusters = cluster_map.members.values().cloned().collect::<Vec<_>>();
+        clusters.sort_by_key(|members| members[0].id);
+        let ids = clusters
+            .iter()
+            .map(|members| members.iter().map(|member| member.id).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                vec![0, 1],
+                vec![2, 3],
+                vec![4, 5],
+                vec![6, 7],
+                vec![8],
+                vec![9]
+            ]
+        );
+        assert_eq!(coarse.graph.node_count(), 6);
+        for (cluster, members) in &cluster_map.members {
+            assert_eq!(*cluster, members[0]);
+            for member in members {
+                assert_eq!(cluster_map.cluster_of[member], *cluster);
+            }
+        }
+        assert_eq!(cluster_map.cluster_of.len(), nodes.len());
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let coarse_solution = gen_random_placement_with_rng(&layout, &coarse, &mut rng).unwrap();
+        let fine = cluster_map.uncluster(&coarse_solution, &netlist).unwrap();
+        assert!(fine.valid());
+        assert_eq!(fine.solution_map.len(), nodes.len());
+        // the lowest id member keeps its cluster's site
+        for (cluster, location) in &coarse_solution.solution_map {
+            assert_eq!(fine.solution_map[cluster], *location);
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::sync::Arc;
+
+use super::fpga_layout::*;
+use super::netlist::*;
+
+use rustc_hash::FxHashMap;
+
+// (min_x, max_x, min_y, max_y) of a net's placed pins
+pub type NetBoundingBox = (u32, u32, u32, u32);
+
+pub fn pins_bounding_box(
+    pins: &[NetlistNode],
+    solution_map: &FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+) -> Option<NetBoundingBox> {
+    pins.iter()
+        .filter_map(|pin| solution_map.get(pin))
+        .fold(None, |bbox, location| match bbox {
+            None => Some((location.x, location.x, location.y, location.y)),
+            Some((min_x, max_x, min_y, max_y)) => Some((
+                min_x.min(location.x),
+                max_x.max(location.x),
+                min_y.min(location.y),
+                max_y.max(location.y),
+            )),
+        })
+}
+
+pub fn bounding_box_hpwl(bbox: Option<NetBoundingBox>) -> u32 {
+    match bbox {
+        Some((min_x, max_x, min_y, max_y)) => (max_x - min_x) + (max_y - min_y),
+        None => 0,
+    }
+}
+
+// hpwl of a net's placed pins under the layout's topology, on a planar fabric
+// this is bounding_box_hpwl of pins_bounding_box
+pub fn pins_hpwl(
+    pins: &[NetlistNode],
+    solution_map: &FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+    layout: &FPGALayout,
+) -> u32 {
+    let (x_span, y_span) = pins_spans(pins, solution_map, layout);
+    x_span + y_span
+}
+
+// (x span, y span) of a net's placed pins under the layout's topology, the
+// two halves of pins_hpwl
+pub fn pins_spans(
+    pins: &[NetlistNode],
+    solution_map: &FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+    layout: &FPGALayout,
+) -> (u32, u32) {
+    topology_spans(
+        pins,
+        solution_map,
+        layout.topology,
+        layout.width,
+        layout.height,
+    )
+}
+
+fn topology_spans(
+    pins: &[NetlistNode],
+    solution_map: &FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+    topology: Topology,
+    width: u32,
+    height: u32,
+) -> (u32, u32) {
+    if topology == Topology::Planar {
+        return match pins_bounding_box(pins, solution_map) {
+            Some((min_x, max_x, min_y, max_y)) => (max_x - min_x, max_y - min_y),
+            None => (0, 0),
+        };
+    }
+
+    let locations = pins
+        .iter()
+        .filter_map(|pin| solution_map.get(pin))
+        .collect::<Vec<_>>();
+    let mut xs = locations
+        .iter()
+        .map(|location| location.x)
+        .collect::<Vec<_>>();
+    let mut ys = locations
+        .iter()
+        .map(|location| location.y)
+        .collect::<Vec<_>>();
+    (
+        topology.axis_span(&mut xs, width),
+        topology.axis_span(&mut ys, height),
+    )
+}
+
+// bounding box of every net plus the running hpwl total, kept up to date as
+// nodes move so the cost of a neighbor only touches the nets of the moved nodes
+// the hpwl follows the layout's topology, the boxes are always planar
+// the net pin lists and node to net incidence never change during placement so
+// they are shared between clones of the solution
+#[derive(Debug, Clone)]
+pub struct NetBboxCache {
+    nets: Arc<Vec<Vec<NetlistNode>>>,
+    node_nets: Arc<FxHashMap<NetlistNode, Vec<NetId>>>,
+    weights: Arc<Vec<f32>>,
+    bboxes: Vec<Option<NetBoundingBox>>,
+    // (x span, y span) of each net, its hpwl is the sum
+    spans: Vec<(u32, u32)>,
+    topology: Topology,
+    width: u32,
+    height: u32,
+    // integer so the running sum never drifts from a full recompute
+    total_hpwl: u64,
+    // weighted x and y spans summed separately so the axes can be scaled
+    // differently, can drift by float rounding, exact while every weight is 1.0
+    total_weighted_x: f64,
+    total_weighted_y: f64,
+    // nets with an IO pin, and their hpwl total
+    io_nets: Arc<Vec<bool>>,
+    total_io_hpwl: u64,
+}
+
+impl NetBboxCache {
+    // weights is indexed by NetId like NetlistGraph::nets
+    pub fn new(
+        layout: &FPGALayout,
+        netlist: &NetlistGraph,
+        solution_map: &FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+        weights: Vec<f32>,
+    ) -> Self {
+        let nets = netlist.nets();
+        assert!(weights.len() == nets.len(), "need one weight per net");
+
+        let mut node_nets: FxHashMap<NetlistNode, Vec<NetId>> = FxHashMap::default();
+        for (net_id, pins) in nets.iter().enumerate() {
+            for pin in pins {
+                let pin_nets = node_nets.entry(*pin).or_default();
+                // a node that shows up twice on one net only needs one refresh
+                if pin_nets.last() != Some(&net_id) {
+                    pin_nets.push(net_id);
+                }
+            }
+        }
+
+        let bboxes = nets
+            .iter()
+            .map(|pins| pins_bounding_box(pins, solution_map))
+            .collect::<Vec<_>>();
+        let spans = nets
+            .iter()
+            .map(|pins| pins_spans(pins, solution_map, layout))
+            .collect::<Vec<_>>();
+        let hpwls = spans.iter().map(|(x_span, y_span)| x_span + y_span);
+        let total_hpwl = hpwls.clone().map(|hpwl| hpwl as u64).sum();
+        let io_nets = nets
+            .iter()
+            .map(|pins| pins.iter().any(|pin| pin.macro_type == MacroType::IO))
+            .collect::<Vec<_>>();
+        let total_io_hpwl = hpwls
+            .zip(&io_nets)
+            .filter(|(_, io_net)| **io_net)
+            .map(|(hpwl, _)| hpwl as u64)
+            .sum();
+        let total_weighted_x = spans
+            .iter()
+            .zip(&weights)
+            .map(|((x_span, _), weight)| *x_span as f64 * *weight as f64)
+            .sum();
+        let total_weighted_y = spans
+            .iter()
+            .zip(&weights)
+            .map(|((_, y_span), weight)| *y_span as f64 * *weight as f64)
+            .sum();
+
+        Self {
+            nets: Arc::new(nets),
+            node_nets: Arc::new(node_nets),
+            weights: Arc::new(weights),
+            bboxes,
+            spans,
+            topology: layout.topology,
+            width: layout.width,
+            height: layout.height,
+            total_hpwl,
+            total_weighted_x,
+            total_weighted_y,
+            io_nets: Arc::new(io_nets),
+            total_io_hpwl,
+        }
+    }
+
+    pub fn total_hpwl(&self) -> f32 {
+        self.total_hpwl as f32
+    }
+
+    pub fn total_weighted_hpwl(&self) -> f32 {
+        (self.total_weighted_x + self.total_weighted_y) as f32
+    }
+
+    // weighted hpwl with the x spans scaled by x_weight and the y spans by y_weight
+    pub fn total_weighted_hpwl_xy(&self, x_weight: f32, y_weight: f32) -> f32 {
+        (x_weight as f64 * self.total_weighted_x + y_weight as f64 * self.total_weighted_y) as f32
+    }
+
+    // hpwl summed over the nets with at least one IO pin
+    pub fn total_io_hpwl(&self) -> f32 {
+        self.total_io_hpwl as f32
+    }
+
+    pub fn net_weight(&self, net: NetId) -> f32 {
+        self.weights[net]
+    }
+
+    pub fn net_count(&self) -> usize {
+        self.bboxes.len()
+    }
+
+    pub fn net_pins(&self, net: NetId) -> &[NetlistNode] {
+        &self.nets[net]
+    }
+
+    pub fn net_bbox(&self, net: NetId) -> Option<NetBoundingBox> {
+        self.bboxes[net]
+    }
+
+    pub fn net_hpwl(&self, net: NetId) -> u32 {
+        let (x_span, y_span) = self.spans[net];
+        x_span + y_span
+    }
+
+    pub fn net_spans(&self, net: NetId) -> (u32, u32) {
+        self.spans[net]
+    }
+
+    pub fn node_nets(&self, node: &NetlistNode) -> &[NetId] {
+        self.node_nets
+            .get(node)
+            .map(|nets| nets.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // recompute one net from its pins and fix up the running total
+    pub fn refresh_net(
+        &mut self,
+        net: NetId,
+        solution_map: &FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+    ) -> Option<NetBoundingBox> {
+        let bbox = pins_bounding_box(&self.nets[net], solution_map);
+        let (old_x, old_y) = self.spans[net];
+        let (new_x, new_y) = topology_spans(
+            &self.nets[net],
+            solution_map,
+            self.topology,
+            self.width,
+            self.height,
+        );
+        let old_hpwl = old_x + old_y;
+        let new_hpwl = new_x + new_y;
+        self.total_hpwl -= old_hpwl as u64;
+        self.total_hpwl += new_hpwl as u64;
+        let weight = self.weights[net] as f64;
+        self.total_weighted_x += (new_x as f64 - old_x as f64) * weight;
+        self.total_weighted_y += (new_y as f64 - old_y as f64) * weight;
+        if self.io_nets[net] {
+            self.total_io_hpwl -= old_hpwl as u64;
+            self.total_io_hpwl += new_hpwl as u64;
+        }
+        self.bboxes[net] = bbox;
+        self.spans[net] = (new_x, new_y);
+        bbox
+    }
+
+    // refresh every net the node is on after it moved
+    pub fn update_node(
+        &mut self,
+        node: &NetlistNode,
+        solution_map: &FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+    ) {
+        let node_nets = Arc::clone(&self.node_nets);
+        if let Some(nets) = node_nets.get(node) {
+            for &net in nets {
+                self.refresh_net(net, solution_map);
+            }
+        }
+    }
+}
@@ -1,5 +1,9 @@
+use std::sync::OnceLock;
+
 use rustc_hash::FxHashMap;
+use rustc_hash::FxHashSet;
 
+use super::fpga_layout::IoDirection;
 use super::fpga_layout::MacroType;
 
 // mod super::fpga_layout;
@@ -8,6 +12,8 @@ use rand::seq::SliceRandom;
 use rand::Rng;
 use rustworkx_core::generators::gnp_random_graph;
 use rustworkx_core::petgraph;
+use rustworkx_core::petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Hash, PartialEq, Eq, Debug, Copy)]
 pub struct NetlistNode {
@@ -15,17 +21,666 @@ pub struct NetlistNode {
     pub macro_type: MacroType,
 }
 
+// index into NetlistGraph::nets()
+pub type NetId = usize;
+
+#[derive(Debug)]
+pub enum NetlistJsonError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    UnknownMacroType(String),
+    DuplicateNode(u32),
+    // an edge refers to a node id that isn't in the node list
+    UnknownNode(u32),
+    // only IO nodes can have an io_direction
+    DirectionOnNonIo(u32),
+}
+
+impl std::fmt::Display for NetlistJsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetlistJsonError::Io(err) => write!(f, "unable to read netlist: {}", err),
+            NetlistJsonError::Parse(err) => write!(f, "unable to parse netlist: {}", err),
+            NetlistJsonError::UnknownMacroType(macro_type) => {
+                write!(f, "unknown macro type \"{}\"", macro_type)
+            }
+            NetlistJsonError::DuplicateNode(id) => write!(f, "node id {} is used twice", id),
+            NetlistJsonError::UnknownNode(id) => {
+                write!(f, "edge refers to unknown node id {}", id)
+            }
+            NetlistJsonError::DirectionOnNonIo(id) => {
+                write!(f, "node {} has an io_direction but is not an IO", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetlistJsonError {}
+
+impl From<std::io::Error> for NetlistJsonError {
+    fn from(err: std::io::Error) -> Self {
+        NetlistJsonError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for NetlistJsonError {
+    fn from(err: serde_json::Error) -> Self {
+        NetlistJsonError::Parse(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum WeightFileError {
+    Io(std::io::Error),
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    // a weight refers to a node id that isn't in the netlist
+    UnknownNode(u32),
+    // both nodes exist but there is no edge from the first to the second
+    NoEdge(u32, u32),
+}
+
+impl std::fmt::Display for WeightFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeightFileError::Io(err) => write!(f, "unable to read weights: {}", err),
+            WeightFileError::Csv(err) => write!(f, "unable to parse weights: {}", err),
+            WeightFileError::Json(err) => write!(f, "unable to parse weights: {}", err),
+            WeightFileError::UnknownNode(id) => {
+                write!(f, "weight refers to unknown node id {}", id)
+            }
+            WeightFileError::NoEdge(source, target) => {
+                write!(f, "no edge from node {} to node {}", source, target)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WeightFileError {}
+
+impl From<std::io::Error> for WeightFileError {
+    fn from(err: std::io::Error) -> Self {
+        WeightFileError::Io(err)
+    }
+}
+
+impl From<csv::Error> for WeightFileError {
+    fn from(err: csv::Error) -> Self {
+        WeightFileError::Csv(err)
+    }
+}
+
+impl From<serde_json::Error> for WeightFileError {
+    fn from(err: serde_json::Error) -> Self {
+        WeightFileError::Json(err)
+    }
+}
+
+// one row of a weight csv, see NetlistGraph::load_weights
+#[derive(Deserialize)]
+struct EdgeWeightRecord {
+    src_id: u32,
+    dst_id: u32,
+    weight: f32,
+}
+
+// on disk format: { "nodes": [{ "id": 0, "macro_type": "CLB" }], "edges": [[0, 1]] }
+// edges are [source id, target id] pairs
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NetlistJson {
+    nodes: Vec<NetlistNodeJson>,
+    edges: Vec<(u32, u32)>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NetlistNodeJson {
+    id: u32,
+    macro_type: String,
+    // "Input" or "Output" for directional IO nodes, left out for everything else
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    io_direction: Option<IoDirection>,
+}
+
+// where the placer gets its net weights from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WeightPolicy {
+    // NetlistGraph::net_weight, 1.0 unless set by hand
+    #[default]
+    Manual,
+    // 1 / fanout of the net's driver (the edge source), so the k edges fanning
+    // out of one driver together weigh as much as a single connection, like
+    // the one k + 1 pin net they stand for, and low fanout paths pull harder
+    Fanout,
+}
+
+impl WeightPolicy {
+    // weight of every net indexed by NetId
+    pub fn net_weights(&self, netlist: &NetlistGraph) -> Vec<f32> {
+        match self {
+            WeightPolicy::Manual => (0..netlist.net_count())
+                .map(|net| netlist.net_weight(net))
+                .collect(),
+            WeightPolicy::Fanout => {
+                // the driver of a net is the source of its first edge, and its
+                // fanout is the number of distinct nets it drives
+                let edge_nets = netlist.edge_nets();
+                let mut drivers = vec![None; netlist.net_count()];
+                let mut driven_nets: FxHashMap<_, FxHashSet<NetId>> = FxHashMap::default();
+                for edge in netlist.graph.edge_references() {
+                    let net = edge_nets[edge.id().index()];
+                    drivers[net].get_or_insert(edge.source());
+                    driven_nets.entry(edge.source()).or_default().insert(net);
+                }
+
+                drivers
+                    .iter()
+                    .map(|driver| {
+                        let fanout = driver.map_or(0, |driver| driven_nets[&driver].len());
+                        1.0 / fanout.max(1) as f32
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// ids shared by more than one node, sorted, see NetlistGraph::try_new
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateIdError {
+    pub ids: Vec<u32>,
+}
+
+impl std::fmt::Display for DuplicateIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "node ids used more than once: {}",
+            self.ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl std::error::Error for DuplicateIdError {}
+
+// what NetlistGraph::sanitize removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SanitizeReport {
+    pub self_loops_removed: u32,
+    pub duplicate_edges_removed: u32,
+}
+
 #[derive(Debug, Clone)]
 
 pub struct NetlistGraph {
     pub graph: petgraph::graph::DiGraph<NetlistNode, ()>,
+    // IO nodes missing from this map are bidirectional
+    pub io_directions: FxHashMap<NetlistNode, IoDirection>,
+    // weight of each net in the placer's wirelength, nets missing from this map weigh 1.0
+    pub net_weights: FxHashMap<NetId, f32>,
+    // hyperedge label of each edge by edge index, edges with the same label
+    // are pins of one net and edges missing from this map are their own
+    // two-pin net
+    pub edge_labels: FxHashMap<usize, u32>,
+    // edge_nets and the net count, built on first use after the labels last
+    // changed, see cached_edge_nets
+    edge_net_cache: OnceLock<(Vec<NetId>, usize)>,
 }
 
 impl NetlistGraph {
+    // panics when two nodes share an id, see try_new
+    pub fn new(graph: petgraph::graph::DiGraph<NetlistNode, ()>) -> NetlistGraph {
+        NetlistGraph::try_new(graph).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    // for the generators, whose random ids can collide until they renumber
+    fn new_unchecked(graph: petgraph::graph::DiGraph<NetlistNode, ()>) -> NetlistGraph {
+        NetlistGraph {
+            graph,
+            io_directions: FxHashMap::default(),
+            net_weights: FxHashMap::default(),
+            edge_labels: FxHashMap::default(),
+            edge_net_cache: OnceLock::new(),
+        }
+    }
+
+    // new, but refuses a graph where two nodes share an id, the solution map
+    // and most lookups go by node so such nodes would trample each other
+    pub fn try_new(
+        graph: petgraph::graph::DiGraph<NetlistNode, ()>,
+    ) -> Result<NetlistGraph, DuplicateIdError> {
+        let netlist = NetlistGraph::new_unchecked(graph);
+        let ids = netlist.duplicate_ids();
+        if !ids.is_empty() {
+            return Err(DuplicateIdError { ids });
+        }
+        Ok(netlist)
+    }
+
+    // ids used by more than one node, sorted
+    pub fn duplicate_ids(&self) -> Vec<u32> {
+        let mut seen = FxHashSet::default();
+        let mut duplicates = FxHashSet::default();
+        for node in self.graph.node_weights() {
+            if !seen.insert(node.id) {
+                duplicates.insert(node.id);
+            }
+        }
+        let mut ids = duplicates.into_iter().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    // give every node the id of its node index, 0..node_count, which makes the
+    // ids unique, IO directions follow their nodes
+    // anything keyed by the old nodes outside the netlist (a placement, locks)
+    // no longer matches, so renumber before placing
+    pub fn renumber_nodes(&mut self) {
+        let io_directions = std::mem::take(&mut self.io_directions);
+        for node_idx in self.graph.node_indices() {
+            let node = &mut self.graph[node_idx];
+            let direction = io_directions.get(node).copied();
+            node.id = node_idx.index() as u32;
+            if let Some(direction) = direction {
+                self.io_directions.insert(*node, direction);
+            }
+        }
+    }
+
+    // netlist built from hyperedge nets, the first pin of each net drives an
+    // edge to every other pin and those edges share the net's label so the
+    // net is costed as one bounding box, nets keep their order as NetIds
+    // panics like new when two of the nodes share an id
+    pub fn from_nets(nodes: &[NetlistNode], nets: &[Vec<NetlistNode>]) -> NetlistGraph {
+        let mut graph = petgraph::graph::DiGraph::new();
+        let node_indices: FxHashMap<NetlistNode, _> = nodes
+            .iter()
+            .map(|node| (*node, graph.add_node(*node)))
+            .collect();
+
+        let mut netlist = NetlistGraph::new(graph);
+        for (label, pins) in nets.iter().enumerate() {
+            let Some((driver, sinks)) = pins.split_first() else {
+                continue;
+            };
+            for sink in sinks {
+                let edge_idx = netlist
+                    .graph
+                    .add_edge(node_indices[driver], node_indices[sink], ());
+                netlist.set_edge_label(edge_idx, label as u32);
+            }
+        }
+
+        netlist
+    }
+
+    pub fn set_edge_label(&mut self, edge_idx: petgraph::graph::EdgeIndex, label: u32) {
+        assert!(
+            edge_idx.index() < self.graph.edge_count(),
+            "edge {} does not exist",
+            edge_idx.index()
+        );
+        self.edge_labels.insert(edge_idx.index(), label);
+        self.edge_net_cache = OnceLock::new();
+    }
+
+    pub fn set_io_direction(&mut self, node: NetlistNode, direction: IoDirection) {
+        assert!(
+            node.macro_type == MacroType::IO,
+            "only IO nodes have a direction"
+        );
+        self.io_directions.insert(node, direction);
+    }
+
+    pub fn io_direction(&self, node: &NetlistNode) -> IoDirection {
+        self.io_directions.get(node).copied().unwrap_or_default()
+    }
+
+    pub fn set_net_weight(&mut self, net: NetId, weight: f32) {
+        assert!(net < self.net_count(), "net {} does not exist", net);
+        self.net_weights.insert(net, weight);
+    }
+
+    pub fn net_weight(&self, net: NetId) -> f32 {
+        self.net_weights.get(&net).copied().unwrap_or(1.0)
+    }
+
+    pub fn from_json(path: impl AsRef<std::path::Path>) -> Result<NetlistGraph, NetlistJsonError> {
+        let contents = std::fs::read_to_string(path)?;
+        NetlistGraph::from_json_str(&contents)
+    }
+
+    // per edge weights, e.g. criticalities from an external timing tool, from
+    // a csv with a "src_id,dst_id,weight" header or, for a .json path, a list
+    // of [src id, dst id, weight] triples
+    // a weight goes to the net of the edge from src to dst, so edges of one
+    // hyperedge net share it and the last one listed wins, nets not listed
+    // keep their weight, nothing is applied if any row fails
+    pub fn load_weights(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), WeightFileError> {
+        let path = path.as_ref();
+        let weights: Vec<(u32, u32, f32)> = if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            serde_json::from_str(&std::fs::read_to_string(path)?)?
+        } else {
+            csv::Reader::from_path(path)?
+                .deserialize::<EdgeWeightRecord>()
+                .map(|record| record.map(|record| (record.src_id, record.dst_id, record.weight)))
+                .collect::<Result<_, _>>()?
+        };
+
+        let node_indices = self
+            .graph
+            .node_indices()
+            .map(|node_idx| (self.graph[node_idx].id, node_idx))
+            .collect::<FxHashMap<_, _>>();
+        // looked up once rather than per row through set_net_weight
+        let (edge_nets, net_count) = self.cached_edge_nets();
+        let mut net_weights = Vec::with_capacity(weights.len());
+        for (source_id, target_id, weight) in weights {
+            let source_idx = *node_indices
+                .get(&source_id)
+                .ok_or(WeightFileError::UnknownNode(source_id))?;
+            let target_idx = *node_indices
+                .get(&target_id)
+                .ok_or(WeightFileError::UnknownNode(target_id))?;
+            let edge_idx = self
+                .graph
+                .find_edge(source_idx, target_idx)
+                .ok_or(WeightFileError::NoEdge(source_id, target_id))?;
+            net_weights.push((edge_nets[edge_idx.index()], weight));
+        }
+
+        for (net, weight) in net_weights {
+            assert!(net < net_count, "net {} does not exist", net);
+            self.net_weights.insert(net, weight);
+        }
+        Ok(())
+    }
+
+    pub fn from_json_str(contents: &str) -> Result<NetlistGraph, NetlistJsonError> {
+        let netlist_json: NetlistJson = serde_json::from_str(contents)?;
+
+        let mut graph = petgraph::graph::DiGraph::new();
+        let mut node_indices = FxHashMap::default();
+        let mut io_directions = FxHashMap::default();
+
+        for node_json in netlist_json.nodes {
+            let macro_type = node_json
+                .macro_type
+                .parse()
+                .map_err(NetlistJsonError::UnknownMacroType)?;
+
+            if node_indices.contains_key(&node_json.id) {
+                return Err(NetlistJsonError::DuplicateNode(node_json.id));
+            }
+
+            let node = NetlistNode {
+                id: node_json.id,
+                macro_type,
+            };
+            let node_idx = graph.add_node(node);
+            node_indices.insert(node_json.id, node_idx);
+
+            if let Some(io_direction) = node_json.io_direction {
+                if macro_type != MacroType::IO {
+                    return Err(NetlistJsonError::DirectionOnNonIo(node_json.id));
+                }
+                io_directions.insert(node, io_direction);
+            }
+        }
+
+        for (source_id, target_id) in netlist_json.edges {
+            let source_idx = *node_indices
+                .get(&source_id)
+                .ok_or(NetlistJsonError::UnknownNode(source_id))?;
+            let target_idx = *node_indices
+                .get(&target_id)
+                .ok_or(NetlistJsonError::UnknownNode(target_id))?;
+
+            graph.add_edge(source_idx, target_idx, ());
+        }
+
+        Ok(NetlistGraph {
+            graph,
+            io_directions,
+            net_weights: FxHashMap::default(),
+            edge_labels: FxHashMap::default(),
+            edge_net_cache: OnceLock::new(),
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let netlist_json = NetlistJson {
+            nodes: self
+                .graph
+                .node_weights()
+                .map(|node| NetlistNodeJson {
+                    id: node.id,
+                    macro_type: node.macro_type.to_string(),
+                    io_direction: self.io_directions.get(node).copied(),
+                })
+                .collect(),
+            edges: self
+                .graph
+                .edge_references()
+                .map(|edge| (self.graph[edge.source()].id, self.graph[edge.target()].id))
+                .collect(),
+        };
+
+        serde_json::to_string_pretty(&netlist_json).unwrap()
+    }
+
     pub fn all_nodes(&self) -> Vec<&NetlistNode> {
         self.graph.node_weights().collect()
     }
 
+    // all_nodes sorted by id so iteration order doesn't depend on how the graph was built
+    pub fn all_nodes_sorted(&self) -> Vec<&NetlistNode> {
+        let mut nodes = self.all_nodes();
+        nodes.sort_by_key(|node| node.id);
+        nodes
+    }
+
+    // nodes sharing an edge with the node in either direction, a node connected
+    // more than once shows up once per edge
+    pub fn neighbors(&self, node: &NetlistNode) -> Vec<NetlistNode> {
+        let node_idx = match self
+            .graph
+            .node_indices()
+            .find(|idx| self.graph[*idx] == *node)
+        {
+            Some(node_idx) => node_idx,
+            None => return Vec::new(),
+        };
+
+        self.graph
+            .neighbors_undirected(node_idx)
+            .map(|neighbor_idx| self.graph[neighbor_idx])
+            .collect()
+    }
+
+    // neighbors paired with the net of the edge connecting them to the node
+    pub fn neighbor_nets(&self, node: &NetlistNode) -> Vec<(NetlistNode, NetId)> {
+        let node_idx = match self
+            .graph
+            .node_indices()
+            .find(|idx| self.graph[*idx] == *node)
+        {
+            Some(node_idx) => node_idx,
+            None => return Vec::new(),
+        };
+
+        // looked up once rather than per edge through net_of_edge
+        let (edge_nets, _) = self.cached_edge_nets();
+        self.graph
+            .edges_directed(node_idx, petgraph::Direction::Outgoing)
+            .map(|edge| (edge.target(), edge.id()))
+            .chain(
+                self.graph
+                    .edges_directed(node_idx, petgraph::Direction::Incoming)
+                    .map(|edge| (edge.source(), edge.id())),
+            )
+            .map(|(neighbor_idx, edge_idx)| (self.graph[neighbor_idx], edge_nets[edge_idx.index()]))
+            .collect()
+    }
+
+    // pins of every net, indexed by NetId
+    // each unlabeled edge is its own two-pin net, edges sharing a label are
+    // merged into one net holding each of their endpoints once
+    pub fn nets(&self) -> Vec<Vec<NetlistNode>> {
+        let mut nets = vec![Vec::new(); self.net_count()];
+        for (edge, net) in self.graph.edge_references().zip(self.edge_nets()) {
+            for node in [self.graph[edge.source()], self.graph[edge.target()]] {
+                if !nets[net].contains(&node) {
+                    nets[net].push(node);
+                }
+            }
+        }
+        nets
+    }
+
+    // NetId of every edge, indexed by edge index
+    // nets are numbered in the order their first edge appears
+    pub fn edge_nets(&self) -> Vec<NetId> {
+        self.cached_edge_nets().0.into_owned()
+    }
+
+    // edge_nets and the net count, cached so lookups by edge don't walk every
+    // edge, the cache is dropped whenever a label changes and is only trusted
+    // while the edge count matches, so edges added to graph directly after
+    // it was built are still numbered right, just without the cache
+    fn cached_edge_nets(&self) -> (std::borrow::Cow<'_, [NetId]>, usize) {
+        let (edge_nets, net_count) = self.edge_net_cache.get_or_init(|| {
+            let edge_nets = self.build_edge_nets();
+            let net_count = edge_nets.iter().max().map_or(0, |net| net + 1);
+            (edge_nets, net_count)
+        });
+        if edge_nets.len() == self.graph.edge_count() {
+            return (std::borrow::Cow::Borrowed(edge_nets), *net_count);
+        }
+
+        let edge_nets = self.build_edge_nets();
+        let net_count = edge_nets.iter().max().map_or(0, |net| net + 1);
+        (std::borrow::Cow::Owned(edge_nets), net_count)
+    }
+
+    fn build_edge_nets(&self) -> Vec<NetId> {
+        if self.edge_labels.is_empty() {
+            return (0..self.graph.edge_count()).collect();
+        }
+
+        let mut label_nets = FxHashMap::default();
+        let mut net_count = 0;
+        (0..self.graph.edge_count())
+            .map(|edge| {
+                let mut new_net = || {
+                    net_count += 1;
+                    net_count - 1
+                };
+                match self.edge_labels.get(&edge) {
+                    Some(label) => *label_nets.entry(*label).or_insert_with(new_net),
+                    None => new_net(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn net_count(&self) -> usize {
+        if self.edge_labels.is_empty() {
+            return self.graph.edge_count();
+        }
+        self.cached_edge_nets().1
+    }
+
+    // remove self-loops and collapse parallel edges with the same source and target
+    // a self-loop always costs 0 so removing it doesn't change the cost, a duplicate
+    // edge counts its wirelength twice so removing it lowers the cost and stops the
+    // placer from pulling that pair together harder than any other connection
+    // edges in opposite directions are kept since they are distinct connections
+    // the first of a set of parallel edges is kept along with its net weight and
+    // the edges that stay keep their relative order
+    pub fn sanitize(&mut self) -> SanitizeReport {
+        let mut report = SanitizeReport::default();
+        let mut seen_edges = FxHashSet::default();
+
+        let edges = self
+            .graph
+            .edge_references()
+            .map(|edge| (edge.source(), edge.target()))
+            .collect::<Vec<_>>();
+        let edge_nets = self.edge_nets();
+        let net_weights = std::mem::take(&mut self.net_weights);
+        let edge_labels = std::mem::take(&mut self.edge_labels);
+        self.graph.clear_edges();
+
+        // old NetId of every kept edge, in new edge order
+        let mut kept_nets = Vec::new();
+        for (edge, (source_idx, target_idx)) in edges.into_iter().enumerate() {
+            if source_idx == target_idx {
+                report.self_loops_removed += 1;
+                continue;
+            }
+            if !seen_edges.insert((source_idx, target_idx)) {
+                report.duplicate_edges_removed += 1;
+                continue;
+            }
+
+            let edge_idx = self.graph.add_edge(source_idx, target_idx, ());
+            if let Some(label) = edge_labels.get(&edge) {
+                self.edge_labels.insert(edge_idx.index(), *label);
+            }
+            kept_nets.push(edge_nets[edge]);
+        }
+
+        self.edge_net_cache = OnceLock::new();
+        for (old_net, new_net) in kept_nets.into_iter().zip(self.edge_nets()) {
+            if let Some(weight) = net_weights.get(&old_net) {
+                self.net_weights.insert(new_net, *weight);
+            }
+        }
+
+        report
+    }
+
+    // lower bound on the total hpwl of any legal placement on a layout whose
+    // sites host one node each, see hpwl_lower_bound_with_capacity
+    pub fn hpwl_lower_bound(&self) -> f32 {
+        self.hpwl_lower_bound_with_capacity(1)
+    }
+
+    // lower bound on the total hpwl of any legal placement on a layout whose
+    // sites host at most max_capacity nodes each
+    // a net with k distinct pins spreads over at least ceil(k / max_capacity)
+    // sites, so its bounding box of w x h covers at least that many and has
+    // an hpwl of at least the smallest (w - 1) + (h - 1) over those boxes
+    // this ignores site types and blocked cells so it is loose
+    pub fn hpwl_lower_bound_with_capacity(&self, max_capacity: u32) -> f32 {
+        self.nets()
+            .iter()
+            .map(|pins| {
+                let n_pins = pins.iter().collect::<FxHashSet<_>>().len() as u32;
+                min_bounding_box_hpwl(n_pins.div_ceil(max_capacity.max(1)))
+            })
+            .sum::<u32>() as f32
+    }
+
+    pub fn net_of_edge(&self, edge_idx: petgraph::graph::EdgeIndex) -> NetId {
+        if self.edge_labels.is_empty() {
+            return edge_idx.index();
+        }
+        self.cached_edge_nets().0[edge_idx.index()]
+    }
+
     pub fn count_summary(&self) -> FxHashMap<MacroType, u32> {
         let mut count_summary = FxHashMap::default();
 
@@ -50,25 +705,393 @@ impl NetlistGraph {
 
         count_summary
     }
+
+    // histogram of node degree (in + out edges) to number of nodes with that degree
+    pub fn degree_distribution(&self) -> FxHashMap<u32, u32> {
+        let mut degree_distribution = FxHashMap::default();
+
+        for node_idx in self.graph.node_indices() {
+            let degree = self.graph.neighbors_undirected(node_idx).count() as u32;
+            *degree_distribution.entry(degree).or_insert(0) += 1;
+        }
+
+        degree_distribution
+    }
+
+    // (min, max, mean, population stddev) of node degree (in + out edges),
+    // all zero for an empty netlist
+    pub fn degree_stats(&self) -> (u32, u32, f32, f32) {
+        let degrees = self
+            .graph
+            .node_indices()
+            .map(|node_idx| self.graph.neighbors_undirected(node_idx).count() as u32)
+            .collect::<Vec<_>>();
+        if degrees.is_empty() {
+            return (0, 0, 0.0, 0.0);
+        }
+
+        let min = *degrees.iter().min().unwrap();
+        let max = *degrees.iter().max().unwrap();
+        let mean = degrees.iter().sum::<u32>() as f32 / degrees.len() as f32;
+        let variance = degrees
+            .iter()
+            .map(|degree| (*degree as f32 - mean).powi(2))
+            .sum::<f32>()
+            / degrees.len() as f32;
+
+        (min, max, mean, variance.sqrt())
+    }
+
+    // nodes with a degree (in + out edges) of at least threshold, sorted by id
+    pub fn hubs(&self, threshold: u32) -> Vec<NetlistNode> {
+        let mut hubs = self
+            .graph
+            .node_indices()
+            .filter(|node_idx| {
+                self.graph.neighbors_undirected(*node_idx).count() as u32 >= threshold
+            })
+            .map(|node_idx| self.graph[node_idx])
+            .collect::<Vec<_>>();
+        hubs.sort_by_key(|node| node.id);
+        hubs
+    }
+
+    // histogram of fanout (out edges, the sinks a node drives) to number of
+    // nodes with that fanout, nodes driving nothing count under 0
+    pub fn fanout_distribution(&self) -> FxHashMap<u32, u32> {
+        let mut fanout_distribution = FxHashMap::default();
+
+        for node_idx in self.graph.node_indices() {
+            let fanout = self
+                .graph
+                .neighbors_directed(node_idx, petgraph::Direction::Outgoing)
+                .count() as u32;
+            *fanout_distribution.entry(fanout).or_insert(0) += 1;
+        }
+
+        fanout_distribution
+    }
+
+    // (driver, fanout) of every node driving at least threshold edges, e.g. a
+    // clock or reset, highest fanout first and ties by id, the driver's out
+    // edges are the net, see net_of_edge for their NetIds
+    pub fn high_fanout_nets(&self, threshold: u32) -> Vec<(NetlistNode, u32)> {
+        let mut high_fanout_nets = self
+            .graph
+            .node_indices()
+            .map(|node_idx| {
+                let fanout = self
+                    .graph
+                    .neighbors_directed(node_idx, petgraph::Direction::Outgoing)
+                    .count() as u32;
+                (self.graph[node_idx], fanout)
+            })
+            .filter(|(_, fanout)| *fanout >= threshold)
+            .collect::<Vec<_>>();
+        high_fanout_nets.sort_by_key(|(node, fanout)| (std::cmp::Reverse(*fanout), node.id));
+        high_fanout_nets
+    }
+
+    // rent exponent p of T = t * B^p, T being the nets crossing the boundary of
+    // a block of B nodes, about 0.5 for a 2d mesh and higher for designs with
+    // more global wiring, 0.0 when there are too few blocks to fit
+    //
+    // the netlist is bisected recursively down to single nodes, each block is
+    // split by a breadth first search over shared nets from a pseudo
+    // peripheral node (the last node reached by a search from the block's
+    // first node) with the first half of the visit order becoming one half,
+    // which keeps the halves compact without a real min cut partitioner
+    // T and B are averaged per level of the recursion and p is the least
+    // squares slope of log T over log B across the levels, skipping the whole
+    // design, which has no boundary
+    pub fn rent_exponent(&self) -> f32 {
+        let node_index = self
+            .graph
+            .node_indices()
+            .map(|node_idx| (self.graph[node_idx], node_idx.index()))
+            .collect::<FxHashMap<_, _>>();
+        let nets = self
+            .nets()
+            .iter()
+            .map(|pins| pins.iter().map(|pin| node_index[pin]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut node_nets = vec![Vec::new(); self.graph.node_count()];
+        for (net, pins) in nets.iter().enumerate() {
+            for pin in pins {
+                node_nets[*pin].push(net);
+            }
+        }
+
+        // block each node is in while that block is being looked at
+        let mut block_of = vec![usize::MAX; self.graph.node_count()];
+        let mut block_id = 0;
+        // (sum of B, sum of T, blocks) per level
+        let mut levels: Vec<(f64, f64, u32)> = Vec::new();
+
+        let bfs_order = |block: &[usize], start: usize, block_of: &[usize], id: usize| {
+            let mut seen = FxHashSet::default();
+            let mut order = Vec::with_capacity(block.len());
+            for root in std::iter::once(start).chain(block.iter().copied()) {
+                if !seen.insert(root) {
+                    continue;
+                }
+                let mut queue = std::collections::VecDeque::from([root]);
+                while let Some(node) = queue.pop_front() {
+                    order.push(node);
+                    for net in &node_nets[node] {
+                        for pin in &nets[*net] {
+                            if block_of[*pin] == id && seen.insert(*pin) {
+                                queue.push_back(*pin);
+                            }
+                        }
+                    }
+                }
+            }
+            order
+        };
+
+        let all_nodes = (0..self.graph.node_count()).collect::<Vec<_>>();
+        let mut stack = vec![(all_nodes, 0)];
+        while let Some((block, level)) = stack.pop() {
+            block_id += 1;
+            for node in &block {
+                block_of[*node] = block_id;
+            }
+
+            if level > 0 {
+                let terminals = block
+                    .iter()
+                    .flat_map(|node| node_nets[*node].iter())
+                    .copied()
+                    .collect::<FxHashSet<_>>()
+                    .into_iter()
+                    .filter(|net| nets[*net].iter().any(|pin| block_of[*pin] != block_id))
+                    .count();
+                if levels.len() < level {
+                    levels.resize(level, (0.0, 0.0, 0));
+                }
+                let entry = &mut levels[level - 1];
+                entry.0 += block.len() as f64;
+                entry.1 += terminals as f64;
+                entry.2 += 1;
+            }
+
+            if block.len() < 2 {
+                continue;
+            }
+            let first_pass = bfs_order(&block, block[0], &block_of, block_id);
+            let order = bfs_order(&block, *first_pass.last().unwrap(), &block_of, block_id);
+            let (left, right) = order.split_at(order.len() / 2);
+            stack.push((right.to_vec(), level + 1));
+            stack.push((left.to_vec(), level + 1));
+        }
+
+        let points = levels
+            .iter()
+            .filter(|(_, terminals, _)| *terminals > 0.0)
+            .map(|(nodes, terminals, blocks)| {
+                (
+                    (nodes / *blocks as f64).ln(),
+                    (terminals / *blocks as f64).ln(),
+                )
+            })
+            .collect::<Vec<_>>();
+        if points.len() < 2 {
+            return 0.0;
+        }
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let covariance = points
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum::<f64>();
+        let variance = points
+            .iter()
+            .map(|(x, _)| (x - mean_x).powi(2))
+            .sum::<f64>();
+        if variance == 0.0 {
+            return 0.0;
+        }
+        (covariance / variance) as f32
+    }
 }
 
 pub fn build_simple_netlist(n_nodes: u32, n_io: u32, n_bram: u32) -> NetlistGraph {
-    let mut netlist = NetlistGraph {
-        graph: gnp_random_graph(
+    build_simple_netlist_with_rng(n_nodes, n_io, n_bram, &mut rand::thread_rng())
+}
+
+pub fn build_simple_netlist_with_rng<R: Rng + ?Sized>(
+    n_nodes: u32,
+    n_io: u32,
+    n_bram: u32,
+    rng: &mut R,
+) -> NetlistGraph {
+    let graph_seed = rng.gen();
+    let mut netlist = NetlistGraph::new_unchecked(
+        gnp_random_graph(
             n_nodes as usize,
             0.02,
-            None,
-            || NetlistNode {
-                id: rand::thread_rng().gen(),
-                macro_type: MacroType::CLB,
-            },
+            Some(graph_seed),
+            || new_clb_node(rng),
             || (), // default_edge_weight
         )
         .unwrap(),
-    };
+    );
+
+    assign_macro_types(&mut netlist, n_io, n_bram, rng);
+    connect_isolated_nodes(&mut netlist, rng);
+
+    netlist
+}
+
+// smallest (w - 1) + (h - 1) over all w x h boxes holding at least n_sites sites
+fn min_bounding_box_hpwl(n_sites: u32) -> u32 {
+    if n_sites <= 1 {
+        return 0;
+    }
+
+    (1..=n_sites)
+        .map(|w| {
+            let h = n_sites.div_ceil(w);
+            (w - 1) + (h - 1)
+        })
+        .min()
+        .unwrap()
+}
+
+fn new_clb_node<R: Rng + ?Sized>(rng: &mut R) -> NetlistNode {
+    NetlistNode {
+        id: rng.gen(),
+        macro_type: MacroType::CLB,
+    }
+}
+
+// scale-free netlist using preferential attachment, each new node connects to
+// m existing nodes with a probability proportional to their degree
+pub fn build_netlist_barabasi_albert(n_nodes: u32, m: u32, n_io: u32, n_bram: u32) -> NetlistGraph {
+    build_netlist_barabasi_albert_with_rng(n_nodes, m, n_io, n_bram, &mut rand::thread_rng())
+}
+
+pub fn build_netlist_barabasi_albert_with_rng<R: Rng + ?Sized>(
+    n_nodes: u32,
+    m: u32,
+    n_io: u32,
+    n_bram: u32,
+    rng: &mut R,
+) -> NetlistGraph {
+    assert!(
+        m >= 1 && m < n_nodes,
+        "m must be at least 1 and smaller than n_nodes"
+    );
+
+    let mut graph = petgraph::graph::DiGraph::new();
+    let node_indices: Vec<_> = (0..n_nodes)
+        .map(|_| graph.add_node(new_clb_node(rng)))
+        .collect();
+
+    // every endpoint of every edge, so picking uniformly from this list is
+    // picking a node proportional to its degree
+    let mut repeated_nodes = Vec::new();
+
+    // start from a star over the first m + 1 nodes so every node has a nonzero degree
+    for &node_idx in &node_indices[1..=m as usize] {
+        graph.add_edge(node_indices[0], node_idx, ());
+        repeated_nodes.push(node_indices[0]);
+        repeated_nodes.push(node_idx);
+    }
+
+    for &new_node_idx in &node_indices[(m as usize + 1)..] {
+        let mut targets = Vec::new();
+        while targets.len() < m as usize {
+            let target = *repeated_nodes.choose(rng).unwrap();
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
 
-    let mut rng: rand::rngs::ThreadRng = rand::thread_rng();
+        for target in targets {
+            graph.add_edge(target, new_node_idx, ());
+            repeated_nodes.push(target);
+            repeated_nodes.push(new_node_idx);
+        }
+    }
 
+    let mut netlist = NetlistGraph::new_unchecked(graph);
+    assign_macro_types(&mut netlist, n_io, n_bram, rng);
+
+    netlist
+}
+
+// small-world netlist, a ring lattice where each node connects to its k / 2
+// nearest neighbors on each side and each edge is rewired with probability beta
+pub fn build_netlist_watts_strogatz(
+    n_nodes: u32,
+    k: u32,
+    beta: f64,
+    n_io: u32,
+    n_bram: u32,
+) -> NetlistGraph {
+    build_netlist_watts_strogatz_with_rng(n_nodes, k, beta, n_io, n_bram, &mut rand::thread_rng())
+}
+
+pub fn build_netlist_watts_strogatz_with_rng<R: Rng + ?Sized>(
+    n_nodes: u32,
+    k: u32,
+    beta: f64,
+    n_io: u32,
+    n_bram: u32,
+    rng: &mut R,
+) -> NetlistGraph {
+    assert!(
+        k >= 2 && k.is_multiple_of(2) && k < n_nodes,
+        "k must be even, at least 2 and smaller than n_nodes"
+    );
+    assert!((0.0..=1.0).contains(&beta), "beta must be in [0, 1]");
+
+    let mut graph = petgraph::graph::DiGraph::new();
+    let node_indices: Vec<_> = (0..n_nodes)
+        .map(|_| graph.add_node(new_clb_node(rng)))
+        .collect();
+
+    for i in 0..n_nodes as usize {
+        for j in 1..=(k / 2) as usize {
+            let source = node_indices[i];
+            let mut target = node_indices[(i + j) % n_nodes as usize];
+
+            if rng.gen_bool(beta) {
+                // rewire to a random node that is not the source and not already connected to it
+                let candidates: Vec<_> = node_indices
+                    .iter()
+                    .filter(|&&node_idx| {
+                        node_idx != source && graph.find_edge_undirected(source, node_idx).is_none()
+                    })
+                    .collect();
+
+                // keep the lattice edge if the source is already connected to everything
+                if let Some(&&new_target) = candidates.choose(rng) {
+                    target = new_target;
+                }
+            }
+
+            graph.add_edge(source, target, ());
+        }
+    }
+
+    let mut netlist = NetlistGraph::new_unchecked(graph);
+    assign_macro_types(&mut netlist, n_io, n_bram, rng);
+
+    netlist
+}
+
+// shared by all the netlist generators: turn some of the generated CLBs into
+// IOs and BRAMs, and renumber the nodes if two of them drew the same id
+fn assign_macro_types<R: Rng + ?Sized>(
+    netlist: &mut NetlistGraph,
+    n_io: u32,
+    n_bram: u32,
+    rng: &mut R,
+) {
     fn get_clb_node_indices(netlist: &NetlistGraph) -> Vec<petgraph::graph::NodeIndex> {
         netlist
             .graph
@@ -81,8 +1104,8 @@ pub fn build_simple_netlist(n_nodes: u32, n_io: u32, n_bram: u32) -> NetlistGrap
 
     // pick n_io random clbs and change their type to io
     // use choose_multiple to avoid duplicates
-    let io_node_indices: Vec<_> = get_clb_node_indices(&netlist)
-        .choose_multiple(&mut rng, n_io as usize)
+    let io_node_indices: Vec<_> = get_clb_node_indices(netlist)
+        .choose_multiple(rng, n_io as usize)
         .cloned()
         .collect();
 
@@ -93,8 +1116,8 @@ pub fn build_simple_netlist(n_nodes: u32, n_io: u32, n_bram: u32) -> NetlistGrap
 
     // pick n_bram random clbs and change their type to bram
     // use choose_multiple to avoid duplicates
-    let bram_node_indices: Vec<_> = get_clb_node_indices(&netlist)
-        .choose_multiple(&mut rng, n_bram as usize)
+    let bram_node_indices: Vec<_> = get_clb_node_indices(netlist)
+        .choose_multiple(rng, n_bram as usize)
         .cloned()
         .collect();
 
@@ -103,6 +1126,15 @@ pub fn build_simple_netlist(n_nodes: u32, n_io: u32, n_bram: u32) -> NetlistGrap
         node.macro_type = MacroType::BRAM;
     }
 
+    // ids are drawn at random so they can collide, which is rare enough that
+    // netlists without a collision keep their random ids
+    if !netlist.duplicate_ids().is_empty() {
+        netlist.renumber_nodes();
+    }
+}
+
+// attach every node without any outgoing edges to a random connected node
+fn connect_isolated_nodes<R: Rng + ?Sized>(netlist: &mut NetlistGraph, rng: &mut R) {
     // connect any dis nodes
     let unconnected_node_indices: Vec<_> = netlist
         .graph
@@ -118,13 +1150,360 @@ pub fn build_simple_netlist(n_nodes: u32, n_io: u32, n_bram: u32) -> NetlistGrap
 
     for unconnected_node_idx in unconnected_node_indices {
         let connected_node_idx = connected_node_indices
-            .choose(&mut rng)
+            .choose(rng)
             .expect("No connected nodes found");
 
         netlist
             .graph
             .add_edge(*connected_node_idx, unconnected_node_idx, ());
     }
+}
 
-    netlist
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::brute_force::brute_force_optimal;
+    use crate::fpga_layout::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn clb(id: u32) -> NetlistNode {
+        NetlistNode {
+            id,
+            macro_type: MacroType::CLB,
+        }
+    }
+
+    #[test]
+    fn net_of_edge_follows_label_changes() {
+        let nodes = (0..5).map(clb).collect::<Vec<_>>();
+        let mut netlist = NetlistGraph::from_nets(
+            &nodes,
+            &[vec![nodes[0], nodes[1], nodes[2]], vec![nodes[3], nodes[4]]],
+        );
+        assert_eq!(netlist.net_count(), 2);
+        assert_eq!(netlist.edge_nets(), vec![0, 0, 1]);
+        let edge = petgraph::graph::EdgeIndex::new;
+        assert_eq!(netlist.net_of_edge(edge(2)), 1);
+
+        // an unlabeled edge added straight to the graph is a net of its own
+        let (a, b) = (
+            petgraph::graph::NodeIndex::new(1),
+            petgraph::graph::NodeIndex::new(3),
+        );
+        netlist.graph.add_edge(a, b, ());
+        assert_eq!(netlist.net_count(), 3);
+        assert_eq!(netlist.net_of_edge(edge(3)), 2);
+
+        netlist.set_edge_label(edge(3), 0);
+        assert_eq!(netlist.net_count(), 2);
+        assert_eq!(netlist.net_of_edge(edge(3)), 0);
+        netlist.set_net_weight(1, 2.0);
+        assert_eq!(
+            netlist.nets()[0],
+            vec![nodes[0], nodes[1], nodes[2], nodes[3]]
+        );
+    }
+    fn graph_with_a_shared_id() -> petgraph::graph::DiGraph<NetlistNode, ()> {
+        let mut graph = petgraph::graph::DiGraph::new();
+        let a = graph.add_node(clb(0));
+        let b = graph.add_node(clb(1));
+        let c = graph.add_node(NetlistNode {
+            id: 1,
+            macro_type: MacroType::DSP,
+        });
+        graph.add_edge(a, b, ());
+        graph.add_edge(a, c, ());
+        graph
+    }
+
+    #[test]
+    fn json_round_trips_and_rejects_bad_fields() {
+        let pad = NetlistNode {
+            id: 7,
+            macro_type: MacroType::IO,
+        };
+        let bram = NetlistNode {
+            id: 3,
+            macro_type: MacroType::BRAM,
+        };
+        let mut graph = petgraph::graph::DiGraph::new();
+        let pad_idx = graph.add_node(pad);
+        let clb_idx = graph.add_node(clb(0));
+        let bram_idx = graph.add_node(bram);
+        graph.add_edge(pad_idx, clb_idx, ());
+        graph.add_edge(clb_idx, bram_idx, ());
+        graph.add_edge(clb_idx, pad_idx, ());
+        let mut netlist = NetlistGraph::new(graph);
+        netlist.set_io_direction(pad, IoDirection::Input);
+
+        let json = netlist.to_json();
+        let loaded = NetlistGraph::from_json_str(&json).unwrap();
+        assert_eq!(loaded.all_nodes_sorted(), netlist.all_nodes_sorted());
+        assert_eq!(loaded.nets(), netlist.nets());
+        assert_eq!(loaded.io_direction(&pad), IoDirection::Input);
+        assert_eq!(loaded.to_json(), json);
+
+        let parse_error = |contents: &str| {
+            matches!(
+                NetlistGraph::from_json_str(contents),
+                Err(NetlistJsonError::Parse(_))
+            )
+        };
+        assert!(parse_error(r#"{"nodes": [], "edges": [], "nets": []}"#));
+        assert!(parse_error(
+            r#"{"nodes": [{"id": 0, "macro_type": "CLB", "x": 1}], "edges": []}"#
+        ));
+        assert!(parse_error(r#"{"nodes": []}"#));
+        assert!(parse_error(
+            r#"{"nodes": [{"id": -1, "macro_type": "CLB"}], "edges": []}"#
+        ));
+        assert!(parse_error(
+            r#"{"nodes": [{"id": 0, "macro_type": "IO", "io_direction": "Up"}], "edges": []}"#
+        ));
+        assert!(matches!(
+            NetlistGraph::from_json_str(r#"{"nodes": [{"id": 0, "macro_type": "LUT"}], "edges": []}"#),
+            Err(NetlistJsonError::UnknownMacroType(name)) if name == "LUT"
+        ));
+        assert!(matches!(
+            NetlistGraph::from_json_str(
+                r#"{"nodes": [{"id": 0, "macro_type": "CLB"}], "edges": [[0, 1]]}"#
+            ),
+            Err(NetlistJsonError::UnknownNode(1))
+        ));
+    }
+
+    #[test]
+    fn generators_build_the_expected_sizes_from_a_seed() {
+        let barabasi_albert = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            build_netlist_barabasi_albert_with_rng(50, 3, 4, 2, &mut rng)
+        };
+        let watts_strogatz = |seed, beta| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            build_netlist_watts_strogatz_with_rng(40, 4, beta, 4, 2, &mut rng)
+        };
+
+        // a star over the first m + 1 nodes, then m edges for every later node
+        let netlist = barabasi_albert(0);
+        assert_eq!(netlist.graph.node_count(), 50);
+        assert_eq!(netlist.graph.edge_count(), 3 + (50 - 3 - 1) * 3);
+        // k / 2 edges per node of the ring, rewiring keeps the count
+        let lattice = watts_strogatz(0, 0.0);
+        let rewired = watts_strogatz(0, 0.3);
+        for netlist in [&lattice, &rewired] {
+            assert_eq!(netlist.graph.node_count(), 40);
+            assert_eq!(netlist.graph.edge_count(), 40 * 4 / 2);
+        }
+        assert_eq!(
+            lattice.degree_distribution(),
+            FxHashMap::from_iter([(4, 40)])
+        );
+        for netlist in [&netlist, &lattice, &rewired] {
+            let counts = netlist.count_summary();
+            assert_eq!(counts[&MacroType::IO], 4);
+            assert_eq!(counts[&MacroType::BRAM], 2);
+            let ids = netlist
+                .graph
+                .node_weights()
+                .map(|node| node.id)
+                .collect::<FxHashSet<_>>();
+            assert_eq!(ids.len(), netlist.graph.node_count());
+        }
+
+        // the rng is the only source of randomness
+        assert_eq!(barabasi_albert(1).to_json(), barabasi_albert(1).to_json());
+        assert_ne!(barabasi_albert(1).to_json(), barabasi_albert(2).to_json());
+        assert_eq!(
+            watts_strogatz(1, 0.3).to_json(),
+            watts_strogatz(1, 0.3).to_json()
+        );
+        assert_ne!(
+            watts_strogatz(1, 0.3).to_json(),
+            watts_strogatz(2, 0.3).to_json()
+        );
+    }
+
+    #[test]
+    fn try_new_reports_shared_ids() {
+        let error = NetlistGraph::try_new(graph_with_a_shared_id()).unwrap_err();
+        assert_eq!(error.ids, vec![1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "node ids used more than once: 1")]
+    fn new_refuses_shared_ids() {
+        NetlistGraph::new(graph_with_a_shared_id());
+    }
+
+    #[test]
+    #[should_panic(expected = "node ids used more than once: 2")]
+    fn from_nets_refuses_shared_ids() {
+        let nodes = [clb(0), clb(2), clb(2)];
+        NetlistGraph::from_nets(&nodes, &[nodes.to_vec()]);
+    }
+
+    #[test]
+    fn load_weights_sets_the_weight_of_each_row_net() {
+        let nodes = (0..4).map(clb).collect::<Vec<_>>();
+        let mut netlist = NetlistGraph::from_nets(
+            &nodes,
+            &[vec![nodes[0], nodes[1], nodes[2]], vec![nodes[2], nodes[3]]],
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("weights.csv");
+        std::fs::write(&path, "src_id,dst_id,weight\n0,2,3.0\n2,3,0.5\n").unwrap();
+
+        netlist.load_weights(&path).unwrap();
+        assert_eq!(netlist.net_weight(0), 3.0);
+        assert_eq!(netlist.net_weight(1), 0.5);
+
+        std::fs::write(&path, "src_id,dst_id,weight\n0,1,2.0\n3,2,1.0\n").unwrap();
+        assert!(matches!(
+            netlist.load_weights(&path),
+            Err(WeightFileError::NoEdge(3, 2))
+        ));
+        assert_eq!(netlist.net_weight(0), 3.0);
+    }
+
+    #[test]
+    fn one_node_drives_a_high_fanout_net() {
+        // node 0 drives nodes 1 to 8, which drive a short chain between them
+        let nodes = (0..9).map(clb).collect::<Vec<_>>();
+        let mut nets = vec![nodes.clone()];
+        nets.extend(nodes[1..4].windows(2).map(|pair| pair.to_vec()));
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+
+        let fanout_distribution = netlist.fanout_distribution();
+        assert_eq!(fanout_distribution[&8], 1);
+        assert_eq!(fanout_distribution[&1], 2);
+        assert_eq!(fanout_distribution[&0], 6);
+        assert_eq!(fanout_distribution.values().sum::<u32>(), 9);
+
+        assert_eq!(netlist.high_fanout_nets(4), vec![(nodes[0], 8)]);
+        assert_eq!(
+            netlist.high_fanout_nets(1),
+            vec![(nodes[0], 8), (nodes[1], 1), (nodes[2], 1)]
+        );
+    }
+
+    #[test]
+    fn sanitize_drops_self_loops_and_duplicate_edges() {
+        let mut graph = petgraph::graph::DiGraph::new();
+        let (a, b, c) = (
+            graph.add_node(clb(0)),
+            graph.add_node(clb(1)),
+            graph.add_node(clb(2)),
+        );
+        graph.add_edge(a, b, ());
+        graph.add_edge(a, b, ());
+        graph.add_edge(a, a, ());
+        graph.add_edge(b, a, ());
+        graph.add_edge(b, c, ());
+        let mut netlist = NetlistGraph::new(graph);
+        netlist.set_net_weight(0, 2.0);
+        netlist.set_net_weight(4, 3.0);
+
+        let report = netlist.sanitize();
+        assert_eq!(
+            report,
+            SanitizeReport {
+                self_loops_removed: 1,
+                duplicate_edges_removed: 1,
+            }
+        );
+        // the edge back from b to a is a connection of its own
+        let edges = netlist
+            .graph
+            .edge_references()
+            .map(|edge| (edge.source(), edge.target()))
+            .collect::<Vec<_>>();
+        assert_eq!(edges, vec![(a, b), (b, a), (b, c)]);
+        assert_eq!(netlist.net_weight(0), 2.0);
+        assert_eq!(netlist.net_weight(1), 1.0);
+        assert_eq!(netlist.net_weight(2), 3.0);
+
+        assert_eq!(netlist.sanitize(), SanitizeReport::default());
+    }
+
+    #[test]
+    fn star_center_is_the_only_hub() {
+        let mut graph = petgraph::graph::DiGraph::new();
+        let center = graph.add_node(clb(0));
+        // three spokes driven by the center and one driving it
+        for id in 1..4 {
+            let spoke = graph.add_node(clb(id));
+            graph.add_edge(center, spoke, ());
+        }
+        let spoke = graph.add_node(clb(4));
+        graph.add_edge(spoke, center, ());
+        let netlist = NetlistGraph::new(graph);
+
+        let (min, max, mean, stddev) = netlist.degree_stats();
+        assert_eq!((min, max), (1, 4));
+        assert!((mean - 1.6).abs() < 1e-6);
+        assert!((stddev - 1.2).abs() < 1e-6);
+        assert_eq!(netlist.hubs(2), vec![clb(0)]);
+        assert_eq!(netlist.hubs(1).len(), 5);
+    }
+
+    #[test]
+    fn mesh_rent_exponent_is_about_a_half() {
+        let side = 16;
+        let nodes = (0..side * side).map(clb).collect::<Vec<_>>();
+        let mut nets = Vec::new();
+        for y in 0..side {
+            for x in 0..side {
+                let node = clb(y * side + x);
+                if x + 1 < side {
+                    nets.push(vec![node, clb(y * side + x + 1)]);
+                }
+                if y + 1 < side {
+                    nets.push(vec![node, clb((y + 1) * side + x)]);
+                }
+            }
+        }
+        let mesh = NetlistGraph::from_nets(&nodes, &nets);
+        let chain_nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        let chain = NetlistGraph::from_nets(&nodes, &chain_nets);
+        let single = NetlistGraph::from_nets(&nodes[..1], &[]);
+
+        let (mesh, chain) = (mesh.rent_exponent(), chain.rent_exponent());
+        assert!((0.35..0.65).contains(&mesh), "{}", mesh);
+        // a chain block has 2 nets crossing its boundary at any size
+        assert!(chain.abs() < 0.2, "{}", chain);
+        assert_eq!(single.rent_exponent(), 0.0);
+    }
+
+    #[test]
+    fn hpwl_lower_bound_holds_for_an_optimal_placement() {
+        let layout = build_simple_fpga_layout(5, 5);
+        let nodes = (0..6).map(clb).collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(
+            &nodes,
+            &[
+                nodes[..4].to_vec(),
+                vec![nodes[3], nodes[4], nodes[5]],
+                vec![nodes[0], nodes[5]],
+            ],
+        );
+        let optimal = brute_force_optimal(&layout, &netlist).unwrap();
+        // 4 pins need a 2 x 2 box, 3 pins a 1 x 3 or 2 x 2 one
+        assert_eq!(netlist.hpwl_lower_bound(), 2.0 + 2.0 + 1.0);
+        assert!(netlist.hpwl_lower_bound() <= optimal.cost_hpwl());
+
+        // every node fits on one site of capacity 6
+        let mut shared = layout.clone();
+        shared.config_capacity(FPGALayoutCoordinate::new(2, 2), 6);
+        let optimal = brute_force_optimal(&shared, &netlist).unwrap();
+        assert_eq!(optimal.cost_hpwl(), 0.0);
+        assert_eq!(
+            netlist.hpwl_lower_bound_with_capacity(shared.max_capacity()),
+            0.0
+        );
+        assert_eq!(netlist.hpwl_lower_bound_with_capacity(2), 1.0 + 1.0 + 0.0);
+    }
 }
@@ -1,13 +1,25 @@
 use std::process::Command;
+use std::simd::prelude::*;
+use std::sync::Arc;
 
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use rand::Rng;
+use rand::SeedableRng;
 use rayon::prelude::*;
+use rustworkx_core::petgraph::graph::EdgeIndex;
 use rustworkx_core::petgraph::visit::EdgeRef;
+use serde::{Deserialize, Serialize};
 use tempfile::tempdir;
 
+use super::analytical::*;
+use super::cost_function::*;
 use super::fpga_layout::*;
+use super::net_cache::*;
 use super::netlist::*;
+use super::relative_group::*;
+use super::spatial_index::*;
+use super::timing::*;
 
 use rustc_hash::FxHashMap;
 use rustc_hash::FxHashSet;
@@ -18,7 +30,316 @@ use itertools::Itertools;
 pub enum PlacementAction {
     Move,
     Swap,
+    // toward the centroid of the node's neighbors
     MoveDirected,
+    // toward the centroid of every node in the design
+    MoveDirectedGlobal,
+    // the node with the most wirelength jumps to the free site nearest its neighbors
+    Teleport,
+    // a random node moves to one of up to k sampled sites, favoring the ones
+    // that shorten its nets the most, see action_move_biased
+    MoveBiased(usize),
+}
+
+// what a directed move pulls the node toward
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CentroidMode {
+    // every node in the design
+    Global,
+    // only the nodes sharing a net with the moved node
+    #[default]
+    Neighbors,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceError {
+    NodeNotInNetlist(NetlistNode),
+    OutOfBounds(FPGALayoutCoordinate),
+    WrongSiteType(FPGALayoutCoordinate),
+    // the site holds a node of a different type, or the node being placed has
+    // no location of its own to swap the occupant into
+    Occupied(NetlistNode),
+    // the node, or the occupant it would swap with, is part of a relative group
+    Grouped(NetlistNode),
+    // the node, or the occupant it would swap with, is locked in place
+    Locked(NetlistNode),
+    // the site is forbidden or outside the region
+    NotAllowed(FPGALayoutCoordinate),
+    // the site is closer than the type's min spacing to another node of it
+    TooClose(FPGALayoutCoordinate),
+}
+
+impl std::fmt::Display for PlaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlaceError::NodeNotInNetlist(node) => {
+                write!(f, "node {} is not in the netlist", node.id)
+            }
+            PlaceError::OutOfBounds(location) => {
+                write!(f, "({}, {}) is outside the layout", location.x, location.y)
+            }
+            PlaceError::WrongSiteType(location) => write!(
+                f,
+                "({}, {}) can't host this macro type",
+                location.x, location.y
+            ),
+            PlaceError::Occupied(node) => write!(f, "site is occupied by node {}", node.id),
+            PlaceError::Grouped(node) => {
+                write!(f, "node {} only moves with its relative group", node.id)
+            }
+            PlaceError::Locked(node) => write!(f, "node {} is locked in place", node.id),
+            PlaceError::NotAllowed(location) => write!(
+                f,
+                "({}, {}) is forbidden or outside the region",
+                location.x, location.y
+            ),
+            PlaceError::TooClose(location) => write!(
+                f,
+                "({}, {}) is closer than the min spacing to a node of the same type",
+                location.x, location.y
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlaceError {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlacementMetrics {
+    pub hpwl: f32,
+    pub max_net: f32,
+    pub beta: f32,
+    // critical path delay, see TimingGraph
+    pub timing: f32,
+    pub lambda: f32,
+    // weighted hpwl + beta * max_net + lambda * timing
+    pub objective: f32,
+    // hpwl / NetlistGraph::hpwl_lower_bound_with_capacity, 1.0 would be a
+    // provably optimal placement
+    pub lower_bound_ratio: f32,
+    // fraction of the placeable sites in use, see PlacementSolution::utilization
+    pub utilization: f32,
+}
+
+impl std::fmt::Display for PlacementMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "HPWL: {}", self.hpwl)?;
+        writeln!(f, "Max Net: {}", self.max_net)?;
+        writeln!(f, "Beta: {}", self.beta)?;
+        writeln!(f, "Timing: {}", self.timing)?;
+        writeln!(f, "Lambda: {}", self.lambda)?;
+        writeln!(f, "Objective: {}", self.objective)?;
+        writeln!(f, "Lower Bound Ratio: {}", self.lower_bound_ratio)?;
+        writeln!(f, "Utilization: {:.1}%", self.utilization * 100.0)
+    }
+}
+
+// what a cost is divided by so designs of different sizes can be compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CostNormalization {
+    // mean cost per net
+    #[default]
+    PerNet,
+    // cost / NetlistGraph::hpwl_lower_bound_with_capacity
+    LowerBound,
+}
+
+impl CostNormalization {
+    // never below 1.0 so an empty netlist doesn't divide by 0
+    pub fn divisor(&self, netlist: &NetlistGraph, layout: &FPGALayout) -> f32 {
+        match self {
+            CostNormalization::PerNet => netlist.net_count() as f32,
+            CostNormalization::LowerBound => {
+                netlist.hpwl_lower_bound_with_capacity(layout.max_capacity())
+            }
+        }
+        .max(1.0)
+    }
+}
+
+// see PlacementSolution::net_length_histogram
+// bin i counts the nets with min + i * (max - min + 1) / n_bins <= length <
+// min + (i + 1) * (max - min + 1) / n_bins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetLengthHistogram {
+    pub bins: Vec<u32>,
+    pub min: u32,
+    pub max: u32,
+}
+
+// how render_svg draws the nets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NetStyle {
+    // a line along every edge of the netlist
+    #[default]
+    Lines,
+    // one translucent rectangle over the sites each net's bounding box spans
+    BoundingBoxes,
+    // the rectilinear minimum spanning tree of each net's pins
+    SpanningTrees,
+}
+
+#[derive(Debug, Clone)]
+pub struct SvgRenderOptions {
+    pub coord_system: CoordSystem,
+    // criticality of each net indexed by NetId, 0.0 (lots of slack) draws the
+    // net green and 1.0 (critical) draws it red, nets are black when this is None
+    pub criticality: Option<Vec<f32>>,
+    // width and height of a site in pixels, text and strokes scale with it
+    pub cell_size: u32,
+    // node id labels are left out when more nodes than this are placed,
+    // None always draws them
+    pub max_labeled_nodes: Option<usize>,
+    pub net_style: NetStyle,
+}
+
+impl Default for SvgRenderOptions {
+    fn default() -> Self {
+        Self {
+            coord_system: CoordSystem::default(),
+            criticality: None,
+            cell_size: 100,
+            max_labeled_nodes: None,
+            net_style: NetStyle::default(),
+        }
+    }
+}
+
+// green to red gradient for a criticality in [0, 1]
+fn criticality_color(criticality: f32) -> String {
+    let criticality = criticality.clamp(0.0, 1.0);
+    format!(
+        "rgb({},{},0)",
+        (255.0 * criticality).round() as u8,
+        (255.0 * (1.0 - criticality)).round() as u8
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapError {
+    NodeNotPlaced(NetlistNode),
+    // the node can't be placed on the other node's site, or the site is forbidden
+    IncompatibleSite(NetlistNode, FPGALayoutCoordinate),
+    // the node is part of a relative group, which only moves as a whole
+    Grouped(NetlistNode),
+    Locked(NetlistNode),
+}
+
+impl std::fmt::Display for SwapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SwapError::NodeNotPlaced(node) => write!(f, "node {} is not placed", node.id),
+            SwapError::IncompatibleSite(node, location) => write!(
+                f,
+                "node {} can't be placed at ({}, {})",
+                node.id, location.x, location.y
+            ),
+            SwapError::Grouped(node) => {
+                write!(f, "node {} only moves with its relative group", node.id)
+            }
+            SwapError::Locked(node) => write!(f, "node {} is locked in place", node.id),
+        }
+    }
+}
+
+impl std::error::Error for SwapError {}
+
+// a rule broken by a placement, see PlacementSolution::validate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementViolation {
+    Unplaced(NetlistNode),
+    NotInNetlist(NetlistNode),
+    // the site holds more nodes than its capacity, the site's first node and
+    // the one over capacity
+    SharedSite(FPGALayoutCoordinate, NetlistNode, NetlistNode),
+    // the site is empty, outside the layout, of another type, or an IO site
+    // with an incompatible direction
+    WrongSiteType(NetlistNode, FPGALayoutCoordinate),
+    OutsideRegion(NetlistNode, FPGALayoutCoordinate),
+    // a member of the relative group with this anchor is off its offset
+    BrokenGroup(NetlistNode),
+    // two nodes of a type with a min_spacing sit closer than it, lower id first
+    TooClose(NetlistNode, NetlistNode),
+}
+
+impl std::fmt::Display for PlacementViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlacementViolation::Unplaced(node) => write!(f, "node {} is not placed", node.id),
+            PlacementViolation::NotInNetlist(node) => {
+                write!(f, "node {} is not in the netlist", node.id)
+            }
+            PlacementViolation::SharedSite(location, a, b) => write!(
+                f,
+                "nodes {} and {} share ({}, {})",
+                a.id, b.id, location.x, location.y
+            ),
+            PlacementViolation::WrongSiteType(node, location) => write!(
+                f,
+                "({}, {}) can't host node {}",
+                location.x, location.y, node.id
+            ),
+            PlacementViolation::OutsideRegion(node, location) => write!(
+                f,
+                "node {} at ({}, {}) is outside the region",
+                node.id, location.x, location.y
+            ),
+            PlacementViolation::BrokenGroup(anchor) => write!(
+                f,
+                "relative group anchored at node {} is out of shape",
+                anchor.id
+            ),
+            PlacementViolation::TooClose(a, b) => write!(
+                f,
+                "nodes {} and {} are closer than the minimum {} spacing",
+                a.id, b.id, a.macro_type
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegalizeError {
+    // every legal site for the node is already taken
+    NoFreeSite(NetlistNode),
+    // the node is already a member of a relative group
+    AlreadyGrouped(NetlistNode),
+}
+
+impl std::fmt::Display for LegalizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LegalizeError::NoFreeSite(node) => {
+                write!(f, "no free legal site left for node {}", node.id)
+            }
+            LegalizeError::AlreadyGrouped(node) => {
+                write!(f, "node {} is already in a relative group", node.id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LegalizeError {}
+
+// a region the nodes prefer but may leave, each node outside it costs
+// penalty_per_cell times its manhattan distance to the region, see
+// PlacementSolution::cost_soft_regions
+#[derive(Debug, Clone)]
+pub struct SoftRegion {
+    pub nodes: Vec<NetlistNode>,
+    pub bounds: Region,
+    pub penalty_per_cell: f32,
+}
+
+impl SoftRegion {
+    // cells between the location and the nearest site of the region, 0 inside it
+    pub fn distance_outside(&self, location: &FPGALayoutCoordinate) -> u32 {
+        let dx = self.bounds.x_min.saturating_sub(location.x)
+            + location.x.saturating_sub(self.bounds.x_max);
+        let dy = self.bounds.y_min.saturating_sub(location.y)
+            + location.y.saturating_sub(self.bounds.y_max);
+        dx + dy
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -26,28 +347,215 @@ pub struct PlacementSolution<'a> {
     pub layout: &'a FPGALayout,
     pub netlist: &'a NetlistGraph,
     pub solution_map: FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+    // macro types missing from this map use a 1x1 footprint
+    pub footprints: FxHashMap<MacroType, Footprint>,
+    // when set nodes may only be placed inside this region
+    pub region: Option<Region>,
+    // per-net bounding boxes kept up to date by set_location, see enable_net_cache
+    pub net_cache: Option<NetBboxCache>,
+    // rigid blocks of nodes, see add_relative_group
+    pub groups: Vec<RelativeGroup>,
+    // net weights indexed by NetId used in place of the netlist's own, the
+    // placer fills this in from PlacerConfig::auto_weight
+    pub net_weights: Option<Arc<Vec<f32>>>,
+    // nodes none of the actions move, see lock_node
+    pub locked: FxHashSet<NetlistNode>,
+    // placed nodes by block of sites kept up to date by set_location, see enable_spatial_index
+    pub spatial_index: Option<SpatialIndex>,
+    // two nodes of a type in this map must be at least this manhattan
+    // distance apart, get_possible_sites leaves out sites that are too close
+    // so moves keep it, set it before placing since the initial placers and
+    // legalize don't look at it, validate reports the pairs breaking it
+    pub min_spacing: FxHashMap<MacroType, u32>,
+    // delay per unit of distance of an edge touching a node of the type, an
+    // edge takes the mean of its two ends, types not in the map count 1.0
+    // so an empty map is plain manhattan timing, see TimingGraph
+    pub delay_multipliers: FxHashMap<MacroType, f32>,
+    // preferred regions added to the placer's objective, unlike region they
+    // never make a move illegal, see add_soft_region
+    pub soft_regions: Arc<Vec<SoftRegion>>,
+    // sites no move, swap or legalize puts a node on, e.g. space held for a
+    // macro added later, nodes already on them stay until they move or get
+    // legalized, see forbid_region
+    pub forbidden: FxHashSet<FPGALayoutCoordinate>,
+    // edges into and out of every node, built once by new from the netlist
+    // and shared between clones, see incident_edges
+    pub node_to_edges: Arc<FxHashMap<NetlistNode, Vec<EdgeIndex>>>,
 }
 
 impl<'a> PlacementSolution<'a> {
     pub fn new(layout: &'a FPGALayout, netlist: &'a NetlistGraph) -> Self {
+        let mut node_to_edges: FxHashMap<NetlistNode, Vec<EdgeIndex>> = netlist
+            .graph
+            .node_weights()
+            .map(|node| (*node, Vec::new()))
+            .collect();
+        for edge in netlist.graph.edge_references() {
+            let source = netlist.graph[edge.source()];
+            let target = netlist.graph[edge.target()];
+            node_to_edges.get_mut(&source).unwrap().push(edge.id());
+            if target != source {
+                node_to_edges.get_mut(&target).unwrap().push(edge.id());
+            }
+        }
+
         Self {
             layout,
             netlist,
             solution_map: FxHashMap::default(),
+            footprints: FxHashMap::default(),
+            region: None,
+            net_cache: None,
+            groups: Vec::new(),
+            net_weights: None,
+            locked: FxHashSet::default(),
+            spatial_index: None,
+            min_spacing: FxHashMap::default(),
+            delay_multipliers: FxHashMap::default(),
+            soft_regions: Arc::new(Vec::new()),
+            forbidden: FxHashSet::default(),
+            node_to_edges: Arc::new(node_to_edges),
+        }
+    }
+
+    // build the per-net bounding box cache from the current placement, from then
+    // on every move made through the solution's methods keeps it up to date
+    // writing to solution_map directly bypasses the cache, see recompute_cost
+    pub fn enable_net_cache(&mut self) {
+        let weights = (0..self.netlist.net_count())
+            .map(|net| self.net_weight(net))
+            .collect();
+        self.net_cache = Some(NetBboxCache::new(
+            self.layout,
+            self.netlist,
+            &self.solution_map,
+            weights,
+        ));
+    }
+
+    // rebuild whichever of the net cache and spatial index are enabled from
+    // solution_map, after writing to it or to net_weights directly, this also
+    // drops any float drift in the cached weighted hpwl
+    pub fn recompute_cost(&mut self) {
+        if self.net_cache.is_some() {
+            self.enable_net_cache();
+        }
+        if let Some(spatial_index) = &self.spatial_index {
+            let bucket_size = spatial_index.bucket_size();
+            self.enable_spatial_index(bucket_size);
+        }
+    }
+
+    pub fn net_weight(&self, net: NetId) -> f32 {
+        match &self.net_weights {
+            Some(net_weights) => net_weights[net],
+            None => self.netlist.net_weight(net),
+        }
+    }
+
+    // every move goes through here so the net cache only refreshes the nets of the moved node
+    fn set_location(&mut self, node: NetlistNode, location: FPGALayoutCoordinate) {
+        let old_location = self.solution_map.insert(node, location);
+        if let Some(net_cache) = self.net_cache.as_mut() {
+            net_cache.update_node(&node, &self.solution_map);
+        }
+        if let Some(spatial_index) = self.spatial_index.as_mut() {
+            if let Some(old_location) = old_location {
+                spatial_index.remove(node, old_location);
+            }
+            spatial_index.insert(node, location);
+        }
+    }
+
+    fn clear_location(&mut self, node: NetlistNode) {
+        let old_location = self.solution_map.remove(&node);
+        if let Some(net_cache) = self.net_cache.as_mut() {
+            net_cache.update_node(&node, &self.solution_map);
+        }
+        if let (Some(spatial_index), Some(old_location)) =
+            (self.spatial_index.as_mut(), old_location)
+        {
+            spatial_index.remove(node, old_location);
+        }
+    }
+
+    pub fn footprint(&self, macro_type: MacroType) -> Footprint {
+        self.footprints
+            .get(&macro_type)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn footprint_overlaps(&self) -> Vec<(NetlistNode, NetlistNode)> {
+        // half-open rectangles [x0, x1) x [y0, y1) so touching macros don't overlap
+        let mut rects = self
+            .solution_map
+            .iter()
+            .map(|(node, location)| {
+                let footprint = self.footprint(node.macro_type);
+                (
+                    *node,
+                    location.x,
+                    location.x + footprint.width,
+                    location.y,
+                    location.y + footprint.height,
+                )
+            })
+            .collect_vec();
+
+        // sweep along x, only rectangles still open at the current x0 can overlap
+        rects.sort_by_key(|&(node, x0, _, y0, _)| (x0, y0, node.id));
+
+        let mut overlaps = Vec::new();
+        let mut active: Vec<(NetlistNode, u32, u32, u32, u32)> = Vec::new();
+
+        for rect in rects {
+            let (node, x0, _, y0, y1) = rect;
+            active.retain(|&(_, _, active_x1, _, _)| active_x1 > x0);
+
+            for &(active_node, _, _, active_y0, active_y1) in &active {
+                if y0 < active_y1 && active_y0 < y1 {
+                    overlaps.push((active_node, node));
+                }
+            }
+
+            active.push(rect);
         }
+
+        overlaps
+    }
+
+    // nodes the random actions pick from, every node that isn't locked in id
+    // order, so with most of the design locked (see reoptimize) steps only
+    // sample the rest instead of mostly landing on a locked node
+    fn movable_nodes(&self) -> Vec<NetlistNode> {
+        self.netlist
+            .all_nodes_sorted()
+            .into_iter()
+            .filter(|node| !self.locked.contains(node))
+            .copied()
+            .collect()
     }
 
     pub fn action_move(&mut self) {
-        let mut rng = rand::thread_rng();
+        self.action_move_with_rng(&mut rand::thread_rng());
+    }
 
+    pub fn action_move_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) {
         // Randomly select a node
-        let node = match self.netlist.all_nodes().choose(&mut rng) {
+        let node = match self.movable_nodes().choose(rng) {
             Some(n) => *n,
             None => return,
         };
 
+        // grouped nodes drag the rest of their group along
+        if let Some(group_idx) = self.group_of(&node) {
+            self.action_move_group_with_rng(group_idx, rng);
+            return;
+        }
+
         // Get possible sites
-        let possible_sites = self.get_possible_sites(node.macro_type);
+        let possible_sites = self.get_possible_sites_for(&node);
 
         // Return if there are no possible sites
         if possible_sites.is_empty() {
@@ -55,113 +563,503 @@ impl<'a> PlacementSolution<'a> {
         }
 
         // Randomly select a location
-        let location = match possible_sites.choose(&mut rng) {
+        let location = match possible_sites.choose(rng) {
             Some(l) => *l,
             None => return,
         };
 
-        self.solution_map.insert(*node, location);
+        self.set_location(node, location);
     }
 
-    pub fn action_swap(&mut self) {
-        let mut rng = rand::thread_rng();
+    pub fn action_move_biased(&mut self, k: usize) {
+        self.action_move_biased_with_rng(k, &mut rand::thread_rng());
+    }
 
-        // Randomly select a node (node_a)
-        let node_a = match self.netlist.all_nodes().choose(&mut rng) {
+    // like action_move but samples up to k of the node's legal sites, scores
+    // each by the node's wirelength there and picks one with weight
+    // worst score - score + 1, so the best site is the most likely but every
+    // sampled site can still win
+    pub fn action_move_biased_with_rng<R: Rng + ?Sized>(&mut self, k: usize, rng: &mut R) {
+        let node = match self.movable_nodes().choose(rng) {
             Some(n) => *n,
             None => return,
         };
 
-        // Filter nodes of the same type as node_a
-        let nodes_same_type = self
-            .netlist
-            .all_nodes()
-            .into_iter()
-            .filter(|node| node.macro_type == node_a.macro_type)
-            .collect_vec();
+        if let Some(group_idx) = self.group_of(&node) {
+            self.action_move_group_with_rng(group_idx, rng);
+            return;
+        }
 
-        // If no nodes of the same type, return
-        if nodes_same_type.is_empty() {
+        let current_location = match self.solution_map.get(&node) {
+            Some(location) => *location,
+            None => return,
+        };
+        let possible_sites = self.get_possible_sites_for(&node);
+        let candidates = possible_sites
+            .choose_multiple(rng, k)
+            .copied()
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
             return;
         }
 
-        // Randomly select another node (node_b) of the same type
-        let node_b = match nodes_same_type.choose(&mut rng) {
+        // try each site in place, set_location keeps the net cache in step
+        let scores = candidates
+            .iter()
+            .map(|site| {
+                self.set_location(node, *site);
+                self.node_wirelength(&node)
+            })
+            .collect::<Vec<_>>();
+        self.set_location(node, current_location);
+
+        let worst = scores.iter().copied().fold(f32::MIN, f32::max);
+        let location = candidates
+            .iter()
+            .zip(&scores)
+            .collect::<Vec<_>>()
+            .choose_weighted(rng, |(_, score)| worst - **score + 1.0)
+            .map(|(site, _)| **site)
+            .unwrap();
+
+        self.set_location(node, location);
+    }
+
+    pub fn action_swap(&mut self) {
+        self.action_swap_with_rng(&mut rand::thread_rng());
+    }
+
+    pub fn action_swap_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        // Randomly select a node (node_a)
+        let movable_nodes = self.movable_nodes();
+        let node_a = match movable_nodes.choose(rng) {
             Some(n) => *n,
             None => return,
         };
 
-        // Clone the locations first to avoid borrowing issues
-        let loc_a = self.solution_map.get(node_a).cloned();
-        let loc_b = self.solution_map.get(node_b).cloned();
+        // Randomly select another node (node_b) among the ones try_swap
+        // accepts for node_a, anywhere on the fabric
+        let node_b = match self.candidate_swaps(&node_a, u32::MAX).choose(rng) {
+            Some(n) => *n,
+            None => return,
+        };
 
         // Perform the swap
-        if let (Some(loc_a), Some(loc_b)) = (loc_a, loc_b) {
-            self.solution_map.insert(*node_a, loc_b);
-            self.solution_map.insert(*node_b, loc_a);
+        let _ = self.try_swap(node_a, node_b);
+    }
+
+    // swap the locations of two placed nodes if each one can legally sit on the other's site
+    pub fn try_swap(&mut self, node_a: NetlistNode, node_b: NetlistNode) -> Result<(), SwapError> {
+        for node in [node_a, node_b] {
+            if self.group_of(&node).is_some() {
+                return Err(SwapError::Grouped(node));
+            }
+            if self.locked.contains(&node) {
+                return Err(SwapError::Locked(node));
+            }
+        }
+
+        let loc_a = *self
+            .solution_map
+            .get(&node_a)
+            .ok_or(SwapError::NodeNotPlaced(node_a))?;
+        let loc_b = *self
+            .solution_map
+            .get(&node_b)
+            .ok_or(SwapError::NodeNotPlaced(node_b))?;
+
+        if !self.site_accepts(&node_a, &loc_b) || self.forbidden.contains(&loc_b) {
+            return Err(SwapError::IncompatibleSite(node_a, loc_b));
+        }
+        if !self.site_accepts(&node_b, &loc_a) || self.forbidden.contains(&loc_a) {
+            return Err(SwapError::IncompatibleSite(node_b, loc_a));
         }
+
+        self.set_location(node_a, loc_b);
+        self.set_location(node_b, loc_a);
+
+        Ok(())
     }
 
     pub fn action_move_directed(&mut self) {
-        let node_count = self.netlist.graph.node_count() as u32;
+        self.action_move_directed_with_rng(&mut rand::thread_rng());
+    }
 
-        if node_count == 0 {
-            panic!("No nodes in netlist; cannot compute mean for MOVE_DIRECTED");
-        }
-        let x_mean = self
-            .netlist
-            .all_nodes()
+    pub fn action_move_directed_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.action_move_directed_toward_with_rng(CentroidMode::Neighbors, rng);
+    }
+
+    // mean location of the placed nodes the mode selects, None when none of them are placed
+    // neighbors are weighted by the weight of the net connecting them so
+    // heavy (e.g. critical) connections pull harder, with unit weights this is
+    // the plain mean, the mean is rounded down
+    pub fn centroid(&self, node: &NetlistNode, mode: CentroidMode) -> Option<(u32, u32)> {
+        let nodes = match mode {
+            CentroidMode::Global => self
+                .netlist
+                .graph
+                .node_weights()
+                .map(|node| (*node, 1.0))
+                .collect_vec(),
+            // same neighbors as NetlistGraph::neighbor_nets, but through the
+            // node's own edges instead of a search for its node index
+            CentroidMode::Neighbors => self
+                .incident_edges(node)
+                .iter()
+                .map(|edge_idx| {
+                    let (source_idx, target_idx) =
+                        self.netlist.graph.edge_endpoints(*edge_idx).unwrap();
+                    let neighbor = if self.netlist.graph[source_idx] == *node {
+                        self.netlist.graph[target_idx]
+                    } else {
+                        self.netlist.graph[source_idx]
+                    };
+                    let net = self.netlist.net_of_edge(*edge_idx);
+                    (neighbor, self.net_weight(net) as f64)
+                })
+                .collect_vec(),
+        };
+
+        let locations = nodes
             .iter()
-            .map(|node| self.solution_map.get(node).unwrap().x)
-            .sum::<u32>()
-            / node_count;
+            .filter_map(|(node, weight)| {
+                self.solution_map
+                    .get(node)
+                    .map(|location| (location, *weight))
+            })
+            .collect_vec();
+        let total_weight = locations.iter().map(|(_, weight)| weight).sum::<f64>();
+        if locations.is_empty() || total_weight <= 0.0 {
+            return None;
+        }
 
-        let y_mean = self
-            .netlist
-            .all_nodes()
+        let x_mean = locations
             .iter()
-            .map(|node| self.solution_map.get(node).unwrap().y)
-            .sum::<u32>()
-            / node_count;
+            .map(|(location, weight)| location.x as f64 * weight)
+            .sum::<f64>()
+            / total_weight;
+        let y_mean = locations
+            .iter()
+            .map(|(location, weight)| location.y as f64 * weight)
+            .sum::<f64>()
+            / total_weight;
+
+        Some((x_mean.floor() as u32, y_mean.floor() as u32))
+    }
 
-        let mut rng = rand::thread_rng();
+    pub fn action_move_directed_toward_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        mode: CentroidMode,
+        rng: &mut R,
+    ) {
+        if self.netlist.graph.node_count() == 0 {
+            panic!("No nodes in netlist; cannot compute mean for MOVE_DIRECTED");
+        }
 
         // pick a random node
-        let node = self.netlist.all_nodes()[rng.gen_range(0..node_count as usize)];
+        let nodes = self.movable_nodes();
+        if nodes.is_empty() {
+            return;
+        }
+        let node = &nodes[rng.gen_range(0..nodes.len())];
+        if self.group_of(node).is_some() {
+            return;
+        }
+
+        // a node with no placed neighbors has nowhere to be pulled toward
+        let (x_mean, y_mean) = match self.centroid(node, mode) {
+            Some(centroid) => centroid,
+            None => return,
+        };
 
-        let valid_locations = self.get_possible_sites(node.macro_type);
-        let valid_closest_location = valid_locations
+        let mean = FPGALayoutCoordinate::new(x_mean, y_mean);
+        let valid_locations = self.get_possible_sites_for(node);
+        let valid_closest_location = match valid_locations
             .iter()
-            .min_by(|a, b| {
-                let a_distance =
-                    (a.x as i32 - x_mean as i32).abs() + (a.y as i32 - y_mean as i32).abs();
-                let b_distance =
-                    (b.x as i32 - x_mean as i32).abs() + (b.y as i32 - y_mean as i32).abs();
-                a_distance.cmp(&b_distance)
-            })
-            .unwrap();
+            .min_by_key(|location| location.manhattan_distance(&mean))
+        {
+            Some(location) => location,
+            None => return,
+        };
 
         // if the new location is futher away from the mean than the current location, return
-        let current_location = self.solution_map.get(node).unwrap();
-        let current_distance = (current_location.x as i32 - x_mean as i32).abs()
-            + (current_location.y as i32 - y_mean as i32).abs();
-        let new_distance = (valid_closest_location.x as i32 - x_mean as i32).abs()
-            + (valid_closest_location.y as i32 - y_mean as i32).abs();
-        if new_distance > current_distance {
+        let current_location = match self.solution_map.get(node) {
+            Some(location) => location,
+            None => return,
+        };
+        if valid_closest_location.manhattan_distance(&mean)
+            > current_location.manhattan_distance(&mean)
+        {
             return;
         }
 
-        self.solution_map.insert(*node, *valid_closest_location);
+        self.set_location(*node, *valid_closest_location);
+    }
+
+    // net based: the hpwl of every net the node is a pin of, each net counted
+    // once even if the node shows up on it twice, with two-pin nets this is the
+    // manhattan length of every edge touching the node
+    pub fn node_wirelength(&self, node: &NetlistNode) -> f32 {
+        if let Some(net_cache) = &self.net_cache {
+            return net_cache
+                .node_nets(node)
+                .iter()
+                .map(|net| net_cache.net_hpwl(*net) as f32)
+                .sum();
+        }
+
+        self.netlist
+            .nets()
+            .iter()
+            .filter(|pins| pins.contains(node))
+            .map(|pins| self.net_hpwl(pins))
+            .sum()
+    }
+
+    // node_wirelength of every node at once
+    pub fn node_wirelengths(&self) -> FxHashMap<NetlistNode, f32> {
+        let mut node_wirelengths = FxHashMap::default();
+        for pins in self.netlist.nets() {
+            let net_hpwl = self.net_hpwl(&pins);
+            for pin in pins.iter().unique() {
+                *node_wirelengths.entry(*pin).or_insert(0.0) += net_hpwl;
+            }
+        }
+        node_wirelengths
+    }
+
+    // the placed node with the most wirelength, ties go to the lowest id
+    pub fn worst_node(&self) -> Option<NetlistNode> {
+        self.worst_node_of(self.netlist.all_nodes_sorted().into_iter().copied())
+    }
+
+    // worst_node out of the given nodes
+    fn worst_node_of(&self, nodes: impl IntoIterator<Item = NetlistNode>) -> Option<NetlistNode> {
+        let node_wirelengths = self.node_wirelengths();
+        nodes
+            .into_iter()
+            .filter(|node| self.solution_map.contains_key(node))
+            .fold(None, |worst: Option<(NetlistNode, f32)>, node| {
+                let wirelength = node_wirelengths.get(&node).copied().unwrap_or(0.0);
+                match worst {
+                    Some((_, worst_wirelength)) if worst_wirelength >= wirelength => worst,
+                    _ => Some((node, wirelength)),
+                }
+            })
+            .map(|(node, _)| node)
+    }
+
+    // pins of every net the node is on in NetId order, found from the node's
+    // incident edges, only a labeled net needs the other edges with its label
+    fn node_net_pins(&self, node: &NetlistNode) -> Vec<(NetId, Vec<NetlistNode>)> {
+        let mut net_pins: Vec<(NetId, Vec<NetlistNode>)> = Vec::new();
+        for edge_idx in self.incident_edges(node) {
+            let net = self.netlist.net_of_edge(*edge_idx);
+            if net_pins.iter().any(|(seen, _)| *seen == net) {
+                continue;
+            }
+            if let Some(net_cache) = &self.net_cache {
+                net_pins.push((net, net_cache.net_pins(net).to_vec()));
+                continue;
+            }
+
+            let edges = match self.netlist.edge_labels.get(&edge_idx.index()) {
+                Some(label) => self
+                    .netlist
+                    .edge_labels
+                    .iter()
+                    .filter(|(_, edge_label)| *edge_label == label)
+                    .map(|(edge, _)| EdgeIndex::new(*edge))
+                    .collect_vec(),
+                None => vec![*edge_idx],
+            };
+            let mut pins = Vec::new();
+            for edge in edges {
+                let (source_idx, target_idx) = self.netlist.graph.edge_endpoints(edge).unwrap();
+                for pin in [
+                    self.netlist.graph[source_idx],
+                    self.netlist.graph[target_idx],
+                ] {
+                    if !pins.contains(&pin) {
+                        pins.push(pin);
+                    }
+                }
+            }
+            net_pins.push((net, pins));
+        }
+        net_pins.sort_by_key(|(net, _)| *net);
+        net_pins
+    }
+
+    // (d/dx, d/dy) of the weighted hpwl of the node's nets, stepping along the
+    // negative of it is the direction that shortens them the most
+    // per net and axis the node's coordinate only counts when it alone sets an
+    // edge of the box, +1 past every other pin's max and -1 below their min
+    // at a corner, where the node shares the edge with another pin or the
+    // other pins span nothing, the subgradient is an interval containing 0 and
+    // 0 is used since the box can't shrink by moving the node alone
+    // coordinates are taken as planar, nets with no other placed pin and an
+    // unplaced node give 0
+    pub fn wirelength_gradient(&self, node: &NetlistNode) -> (f32, f32) {
+        let location = match self.solution_map.get(node) {
+            Some(location) => *location,
+            None => return (0.0, 0.0),
+        };
+
+        let axis_slope = |value: u32, others: &[u32]| -> f32 {
+            match (others.iter().min(), others.iter().max()) {
+                (Some(min), _) if value < *min => -1.0,
+                (_, Some(max)) if value > *max => 1.0,
+                _ => 0.0,
+            }
+        };
+
+        let mut gradient = (0.0, 0.0);
+        for (net, pins) in self.node_net_pins(node) {
+            let others = pins
+                .iter()
+                .filter(|pin| *pin != node)
+                .filter_map(|pin| self.solution_map.get(pin))
+                .collect_vec();
+            let xs = others.iter().map(|other| other.x).collect_vec();
+            let ys = others.iter().map(|other| other.y).collect_vec();
+            let weight = self.net_weight(net);
+            gradient.0 += weight * axis_slope(location.x, &xs);
+            gradient.1 += weight * axis_slope(location.y, &ys);
+        }
+
+        gradient
+    }
+
+    // the free legal site (or the node's own) closest to where the node's
+    // incident hpwl is lowest with every other node held fixed, None when it
+    // has no placed neighbors or nowhere to go
+    // per axis a net's cost is flat between the lowest and highest of its other
+    // pins and rises by the net weight per cell outside it, so the sum is
+    // lowest at the weighted median of all those bounds, each net adding its
+    // low and high bound with its weight, the lower median is used when a
+    // whole interval ties, and the site nearest that point wins, ties going to
+    // the lowest x and then y
+    pub fn optimal_site_for(&self, node: &NetlistNode) -> Option<FPGALayoutCoordinate> {
+        let mut xs = Vec::new();
+        let mut ys = Vec::new();
+        for (net, pins) in self.node_net_pins(node) {
+            let others = pins
+                .iter()
+                .filter(|pin| *pin != node)
+                .filter_map(|pin| self.solution_map.get(pin))
+                .collect_vec();
+            let (Some(x_min), Some(x_max)) = (
+                others.iter().map(|other| other.x).min(),
+                others.iter().map(|other| other.x).max(),
+            ) else {
+                continue;
+            };
+            let y_min = others.iter().map(|other| other.y).min().unwrap();
+            let y_max = others.iter().map(|other| other.y).max().unwrap();
+            let weight = self.net_weight(net);
+            xs.extend([(x_min, weight), (x_max, weight)]);
+            ys.extend([(y_min, weight), (y_max, weight)]);
+        }
+
+        let weighted_median = |mut values: Vec<(u32, f32)>| -> Option<u32> {
+            values.sort_by_key(|(value, _)| *value);
+            let half = values.iter().map(|(_, weight)| weight).sum::<f32>() / 2.0;
+            let mut cumulative = 0.0;
+            for (value, weight) in &values {
+                cumulative += weight;
+                if cumulative >= half {
+                    return Some(*value);
+                }
+            }
+            values.last().map(|(value, _)| *value)
+        };
+        let target_x = weighted_median(xs)?;
+        let target_y = weighted_median(ys)?;
+
+        let mut sites = self.get_possible_sites_for(node);
+        if let Some(location) = self.solution_map.get(node) {
+            sites.push(*location);
+        }
+        sites.into_iter().min_by_key(|site| {
+            (
+                site.x.abs_diff(target_x) + site.y.abs_diff(target_y),
+                site.x,
+                site.y,
+            )
+        })
+    }
+
+    // farthest distance under the layout's topology any node placed in both
+    // solutions sits from its location in the other one
+    pub fn max_displacement(&self, other: &PlacementSolution) -> u32 {
+        self.solution_map
+            .iter()
+            .filter_map(|(node, location)| {
+                other
+                    .solution_map
+                    .get(node)
+                    .map(|other_location| self.layout.distance(location, other_location))
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // rescue move for a node stuck far from its neighbors, it ignores any
+    // distance limit and jumps straight to the free legal site closest to the
+    // centroid of its neighbors, the placer only keeps it if the cost drops
+    pub fn action_teleport(&mut self) {
+        let node = match self.worst_node_of(self.movable_nodes()) {
+            Some(node) if self.group_of(&node).is_none() => node,
+            _ => return,
+        };
+
+        let (x_mean, y_mean) = match self.centroid(&node, CentroidMode::Neighbors) {
+            Some(centroid) => centroid,
+            None => return,
+        };
+
+        let location = self
+            .get_possible_sites_for(&node)
+            .into_iter()
+            .min_by_key(|site| site.x.abs_diff(x_mean) + site.y.abs_diff(y_mean));
+
+        if let Some(location) = location {
+            self.set_location(node, location);
+        }
     }
 
     pub fn action(&mut self, action: PlacementAction) {
+        self.action_with_rng(action, &mut rand::thread_rng());
+    }
+
+    pub fn action_with_rng<R: Rng + ?Sized>(&mut self, action: PlacementAction, rng: &mut R) {
         match action {
-            PlacementAction::Move => self.action_move(),
-            PlacementAction::Swap => self.action_swap(),
-            PlacementAction::MoveDirected => self.action_move_directed(),
+            PlacementAction::Move => self.action_move_with_rng(rng),
+            PlacementAction::Swap => self.action_swap_with_rng(rng),
+            PlacementAction::MoveDirected => self.action_move_directed_with_rng(rng),
+            PlacementAction::MoveDirectedGlobal => {
+                self.action_move_directed_toward_with_rng(CentroidMode::Global, rng)
+            }
+            PlacementAction::Teleport => self.action_teleport(),
+            PlacementAction::MoveBiased(k) => self.action_move_biased_with_rng(k, rng),
         }
     }
 
+    // apply k random moves / swaps in place, e.g. to kick a converged solution
+    // out of its basin before annealing again, every action only picks legal
+    // sites so the solution stays legal, returns the change in hpwl
+    pub fn perturb<R: Rng + ?Sized>(&mut self, k: usize, rng: &mut R) -> f32 {
+        let cost_before = self.hpwl();
+        for _ in 0..k {
+            let action = *[PlacementAction::Move, PlacementAction::Swap]
+                .choose(rng)
+                .unwrap();
+            self.action_with_rng(action, rng);
+        }
+        self.hpwl() - cost_before
+    }
+
     pub fn cost_bb(&self) -> f32 {
         let mut cost = 0;
 
@@ -175,23 +1073,499 @@ impl<'a> PlacementSolution<'a> {
             let source_location = self.solution_map.get(source).unwrap();
             let target_location = self.solution_map.get(target).unwrap();
 
-            let x_distance = source_location.x.abs_diff(target_location.x);
-            let y_distance = source_location.y.abs_diff(target_location.y);
-
-            let distance = x_distance + y_distance;
-            cost += distance;
+            cost += self.layout.distance(source_location, target_location);
         }
 
         cost as f32
     }
 
-    pub fn render_svg(&self) -> String {
-        let mut svg = String::new();
+    // cost_bb computed 8 edges at a time, the edges that don't fill a full
+    // vector fall back to the scalar loop
+    // all the math is on integers so the result matches cost_bb exactly
+    // the vector path is planar only, a torus layout falls back to cost_bb
+    pub fn cost_bb_simd(&self) -> f32 {
+        const LANES: usize = 8;
 
-        svg.push_str(&format!(
-            "<svg xmlns=\"http://www.w3.org/2000/svg\" style=\"background-color:white\" viewBox=\"0 0 {} {}\">\n",
-            self.layout.width * 100,
-            self.layout.height * 100
+        if self.layout.topology != Topology::Planar {
+            return self.cost_bb();
+        }
+
+        let edge_count = self.netlist.graph.edge_count();
+        let mut source_x = Vec::with_capacity(edge_count);
+        let mut source_y = Vec::with_capacity(edge_count);
+        let mut target_x = Vec::with_capacity(edge_count);
+        let mut target_y = Vec::with_capacity(edge_count);
+
+        for edge in self.netlist.graph.edge_references() {
+            let source = self.netlist.graph.node_weight(edge.source()).unwrap();
+            let target = self.netlist.graph.node_weight(edge.target()).unwrap();
+
+            let source_location = self.solution_map.get(source).unwrap();
+            let target_location = self.solution_map.get(target).unwrap();
+
+            source_x.push(source_location.x);
+            source_y.push(source_location.y);
+            target_x.push(target_location.x);
+            target_y.push(target_location.y);
+        }
+
+        let simd_len = edge_count - edge_count % LANES;
+        let mut cost_lanes = u32x8::splat(0);
+        for i in (0..simd_len).step_by(LANES) {
+            let sx = u32x8::from_slice(&source_x[i..i + LANES]);
+            let sy = u32x8::from_slice(&source_y[i..i + LANES]);
+            let tx = u32x8::from_slice(&target_x[i..i + LANES]);
+            let ty = u32x8::from_slice(&target_y[i..i + LANES]);
+
+            // unsigned abs diff is max - min
+            cost_lanes += sx.simd_max(tx) - sx.simd_min(tx);
+            cost_lanes += sy.simd_max(ty) - sy.simd_min(ty);
+        }
+
+        let mut cost = cost_lanes.reduce_sum();
+        for i in simd_len..edge_count {
+            cost += source_x[i].abs_diff(target_x[i]) + source_y[i].abs_diff(target_y[i]);
+        }
+
+        cost as f32
+    }
+
+    // (min_x, max_x, min_y, max_y) over the placed pins of a net
+    pub fn net_bounding_box(&self, pins: &[NetlistNode]) -> Option<NetBoundingBox> {
+        pins_bounding_box(pins, &self.solution_map)
+    }
+
+    // follows the layout's topology, unlike net_bounding_box
+    pub fn net_hpwl(&self, pins: &[NetlistNode]) -> f32 {
+        pins_hpwl(pins, &self.solution_map, self.layout) as f32
+    }
+
+    // recompute one net's bounding box from scratch, refreshing the cached box
+    // if the net cache is enabled, compare against net_cache.net_bbox to verify it
+    pub fn recompute_net_bbox(&mut self, net: NetId) -> Option<NetBoundingBox> {
+        match self.net_cache.as_mut() {
+            Some(net_cache) => net_cache.refresh_net(net, &self.solution_map),
+            None => self.net_bounding_box(&self.netlist.nets()[net]),
+        }
+    }
+
+    // half-perimeter wirelength summed over all nets, always recomputed from scratch
+    pub fn cost_hpwl(&self) -> f32 {
+        self.netlist
+            .nets()
+            .iter()
+            .map(|pins| self.net_hpwl(pins))
+            .sum()
+    }
+
+    // the net cache's running hpwl total when enabled, otherwise cost_hpwl
+    pub fn hpwl(&self) -> f32 {
+        match &self.net_cache {
+            Some(net_cache) => net_cache.total_hpwl(),
+            None => self.cost_hpwl(),
+        }
+    }
+
+    // hpwl of the nets with an IO pin, what PlacerConfig::pad_attraction weighs
+    pub fn io_net_hpwl(&self) -> f32 {
+        match &self.net_cache {
+            Some(net_cache) => net_cache.total_io_hpwl(),
+            None => self
+                .netlist
+                .nets()
+                .iter()
+                .filter(|pins| pins.iter().any(|pin| pin.macro_type == MacroType::IO))
+                .map(|pins| self.net_hpwl(pins))
+                .sum(),
+        }
+    }
+
+    // hpwl with every net scaled by its weight, what the placer minimizes
+    pub fn weighted_hpwl(&self) -> f32 {
+        match &self.net_cache {
+            Some(net_cache) => net_cache.total_weighted_hpwl(),
+            None => self
+                .netlist
+                .nets()
+                .iter()
+                .enumerate()
+                .map(|(net, pins)| self.net_hpwl(pins) * self.net_weight(net))
+                .sum(),
+        }
+    }
+
+    // weighted_hpwl with each net's x span scaled by x_weight and its y span by
+    // y_weight, for fabrics where one routing direction is scarcer, 1.0 and 1.0
+    // give weighted_hpwl
+    pub fn weighted_hpwl_xy(&self, x_weight: f32, y_weight: f32) -> f32 {
+        match &self.net_cache {
+            Some(net_cache) => net_cache.total_weighted_hpwl_xy(x_weight, y_weight),
+            None => self
+                .netlist
+                .nets()
+                .iter()
+                .enumerate()
+                .map(|(net, pins)| {
+                    let (x_span, y_span) = pins_spans(pins, &self.solution_map, self.layout);
+                    (x_weight * x_span as f32 + y_weight * y_span as f32) * self.net_weight(net)
+                })
+                .sum(),
+        }
+    }
+
+    // pin pairs joined by the rectilinear minimum spanning tree over a net's
+    // placed pins, Prim's algorithm on the manhattan distances, so O(pins^2)
+    // per net, a pin that shows up twice is only counted once
+    pub fn net_rmst_edges(&self, pins: &[NetlistNode]) -> Vec<(NetlistNode, NetlistNode)> {
+        let pins = pins
+            .iter()
+            .unique()
+            .filter_map(|pin| self.solution_map.get(pin).map(|location| (*pin, *location)))
+            .collect_vec();
+        if pins.len() < 2 {
+            return Vec::new();
+        }
+
+        // closest tree pin and its distance for every pin not in the tree yet
+        let mut closest = pins[1..]
+            .iter()
+            .map(|(_, location)| (0, self.layout.distance(&pins[0].1, location)))
+            .collect_vec();
+        let mut outside = (1..pins.len()).collect_vec();
+        let mut edges = Vec::with_capacity(pins.len() - 1);
+        while !outside.is_empty() {
+            let next = (0..outside.len())
+                .min_by_key(|i| closest[outside[*i] - 1].1)
+                .unwrap();
+            let pin = outside.swap_remove(next);
+            edges.push((pins[closest[pin - 1].0].0, pins[pin].0));
+            for &other in &outside {
+                let distance = self.layout.distance(&pins[pin].1, &pins[other].1);
+                if distance < closest[other - 1].1 {
+                    closest[other - 1] = (pin, distance);
+                }
+            }
+        }
+        edges
+    }
+
+    // length of the net's rectilinear minimum spanning tree, a closer estimate
+    // of the routed length of a multi-pin net than its hpwl, which it never
+    // undercuts, the two agree on 2 pin nets
+    pub fn net_rmst(&self, pins: &[NetlistNode]) -> f32 {
+        self.net_rmst_edges(pins)
+            .iter()
+            .map(|(a, b)| {
+                self.layout
+                    .distance(&self.solution_map[a], &self.solution_map[b])
+            })
+            .sum::<u32>() as f32
+    }
+
+    // net_rmst summed over all nets, always recomputed from scratch and
+    // quadratic in each net's pin count, so much more expensive than hpwl
+    pub fn cost_rmst(&self) -> f32 {
+        self.netlist
+            .nets()
+            .iter()
+            .map(|pins| self.net_rmst(pins))
+            .sum()
+    }
+
+    // net_hpwl of every net bucketed into n_bins equal width bins spanning
+    // the shortest to the longest net
+    pub fn net_length_histogram(&self, n_bins: usize) -> NetLengthHistogram {
+        assert!(n_bins > 0, "need at least one bin");
+
+        let lengths = self
+            .netlist
+            .nets()
+            .iter()
+            .map(|pins| pins_hpwl(pins, &self.solution_map, self.layout))
+            .collect_vec();
+
+        let min = lengths.iter().copied().min().unwrap_or(0);
+        let max = lengths.iter().copied().max().unwrap_or(0);
+        let span = (max - min + 1) as usize;
+
+        let mut bins = vec![0; n_bins];
+        for length in lengths {
+            bins[(length - min) as usize * n_bins / span] += 1;
+        }
+
+        NetLengthHistogram { bins, min, max }
+    }
+
+    // nets with every placed pin inside the inclusive rectangle from (x0, y0)
+    // to (x1, y1), and nets with placed pins both inside and outside it,
+    // unplaced pins are ignored and nets entirely outside are in neither list
+    // the corners can be given in either order
+    pub fn nets_in_region(&self, x0: u32, y0: u32, x1: u32, y1: u32) -> (Vec<NetId>, Vec<NetId>) {
+        let region = Region::new(x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1));
+        let mut internal = Vec::new();
+        let mut crossing = Vec::new();
+
+        for (net, pins) in self.netlist.nets().iter().enumerate() {
+            let (mut inside, mut outside) = (false, false);
+            for location in pins.iter().filter_map(|pin| self.solution_map.get(pin)) {
+                if region.contains(location) {
+                    inside = true;
+                } else {
+                    outside = true;
+                }
+            }
+            match (inside, outside) {
+                (true, false) => internal.push(net),
+                (true, true) => crossing.push(net),
+                _ => {}
+            }
+        }
+
+        (internal, crossing)
+    }
+
+    // special (BRAM / DSP) columns strictly between the left and right edge of
+    // each net's bounding box, scaled by the net weight and summed, a net with
+    // a pin on a special column doesn't cross it
+    pub fn cost_column_crossing(&self) -> f32 {
+        self.cost_column_crossing_with(&self.layout.special_columns())
+    }
+
+    // cost_column_crossing with FPGALayout::special_columns computed up front
+    pub fn cost_column_crossing_with(&self, special_columns: &[u32]) -> f32 {
+        self.netlist
+            .nets()
+            .iter()
+            .enumerate()
+            .map(|(net, pins)| match self.net_bounding_box(pins) {
+                Some((min_x, max_x, _, _)) => {
+                    // saturating for a net inside a single special column
+                    let crossed = special_columns
+                        .partition_point(|x| *x < max_x)
+                        .saturating_sub(special_columns.partition_point(|x| *x <= min_x));
+                    crossed as f32 * self.net_weight(net)
+                }
+                None => 0.0,
+            })
+            .sum()
+    }
+
+    // area of the convex hull of a net's placed pins in sites squared, 0 for
+    // nets with fewer than 3 distinct pin locations or all of them on a line
+    // O(pins log pins) from sorting the pins for the monotone chain
+    pub fn net_hull_area(&self, pins: &[NetlistNode]) -> f32 {
+        let mut points = pins
+            .iter()
+            .filter_map(|pin| self.solution_map.get(pin))
+            .map(|location| (location.x as i64, location.y as i64))
+            .collect_vec();
+        points.sort_unstable();
+        points.dedup();
+        if points.len() < 3 {
+            return 0.0;
+        }
+
+        // z of the cross product of oa and ob, positive for a left turn
+        let cross = |o: (i64, i64), a: (i64, i64), b: (i64, i64)| {
+            (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+        };
+
+        // andrew's monotone chain, lower hull then upper hull
+        let mut hull: Vec<(i64, i64)> = Vec::with_capacity(points.len() * 2);
+        for pass in [points.clone(), points.into_iter().rev().collect_vec()] {
+            let start = hull.len();
+            for point in pass {
+                while hull.len() >= start + 2
+                    && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0
+                {
+                    hull.pop();
+                }
+                hull.push(point);
+            }
+            // the last point of each chain starts the other one
+            hull.pop();
+        }
+
+        // shoelace formula
+        let twice_area: i64 = (0..hull.len())
+            .map(|i| {
+                let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+                a.0 * b.1 - b.0 * a.1
+            })
+            .sum();
+        twice_area.abs() as f32 / 2.0
+    }
+
+    // net_hull_area summed over all nets, a wirelength proxy that, unlike
+    // hpwl, tells a net spread over its whole bounding box from one along its
+    // diagonal
+    pub fn cost_hull_area(&self) -> f32 {
+        self.netlist
+            .nets()
+            .iter()
+            .map(|pins| self.net_hull_area(pins))
+            .sum()
+    }
+
+    // edges with the node as source or target in edge index order, a self loop
+    // listed once, empty for a node not in the netlist
+    pub fn incident_edges(&self, node: &NetlistNode) -> &[EdgeIndex] {
+        self.node_to_edges
+            .get(node)
+            .map(|edges| edges.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn add_soft_region(&mut self, soft_region: SoftRegion) {
+        Arc::make_mut(&mut self.soft_regions).push(soft_region);
+    }
+
+    // penalty of every placed node outside a soft region it belongs to, a node
+    // in several soft regions pays for each one it is outside of
+    pub fn cost_soft_regions(&self) -> f32 {
+        self.soft_regions
+            .iter()
+            .map(|soft_region| {
+                let cells = soft_region
+                    .nodes
+                    .iter()
+                    .filter_map(|node| self.solution_map.get(node))
+                    .map(|location| soft_region.distance_outside(location))
+                    .sum::<u32>();
+                soft_region.penalty_per_cell * cells as f32
+            })
+            .sum()
+    }
+
+    // half-perimeter wirelength of the longest net
+    pub fn cost_max_net(&self) -> f32 {
+        if let Some(net_cache) = &self.net_cache {
+            return (0..net_cache.net_count())
+                .map(|net| net_cache.net_hpwl(net))
+                .max()
+                .unwrap_or(0) as f32;
+        }
+
+        self.netlist
+            .nets()
+            .iter()
+            .map(|pins| self.net_hpwl(pins))
+            .fold(0.0, f32::max)
+    }
+
+    // hpwl divided by the number of nets
+    pub fn normalized_cost(&self) -> f32 {
+        self.normalized_cost_with(CostNormalization::PerNet)
+    }
+
+    pub fn normalized_cost_with(&self, normalization: CostNormalization) -> f32 {
+        self.hpwl() / normalization.divisor(self.netlist, self.layout)
+    }
+
+    pub fn metrics(&self, beta: f32, lambda: f32) -> PlacementMetrics {
+        let hpwl = self.cost_hpwl();
+        let weighted_hpwl = self.weighted_hpwl();
+        let max_net = self.cost_max_net();
+        let timing = self.cost_timing();
+        let lower_bound = self
+            .netlist
+            .hpwl_lower_bound_with_capacity(self.layout.max_capacity());
+
+        PlacementMetrics {
+            hpwl,
+            max_net,
+            beta,
+            timing,
+            lambda,
+            objective: weighted_hpwl + beta * max_net + lambda * timing,
+            lower_bound_ratio: if lower_bound > 0.0 {
+                hpwl / lower_bound
+            } else {
+                1.0
+            },
+            utilization: self.utilization(),
+        }
+    }
+
+    // placed nodes / placeable sites, EMPTY sites don't count
+    pub fn utilization(&self) -> f32 {
+        let placeable_sites = self
+            .layout
+            .count_summary()
+            .iter()
+            .filter(|(layout_type, _)| **layout_type != FPGALayoutType::EMPTY)
+            .map(|(_, count)| count)
+            .sum::<u32>();
+
+        if placeable_sites == 0 {
+            return 0.0;
+        }
+        self.solution_map.len() as f32 / placeable_sites as f32
+    }
+
+    // utilization of each macro type's own sites, types the layout has no
+    // sites for are left out, a multi type site counts toward every type it
+    // accepts
+    pub fn utilization_by_type(&self) -> FxHashMap<MacroType, f32> {
+        let mut placed: FxHashMap<MacroType, u32> = FxHashMap::default();
+        for node in self.solution_map.keys() {
+            *placed.entry(node.macro_type).or_insert(0) += 1;
+        }
+
+        let mut sites_by_type: FxHashMap<MacroType, u32> = FxHashMap::default();
+        for (layout_type, sites) in self.layout.count_summary() {
+            for macro_type in layout_type.macro_types() {
+                *sites_by_type.entry(macro_type).or_insert(0) += sites;
+            }
+        }
+
+        sites_by_type
+            .into_iter()
+            .filter(|(_, sites)| *sites > 0)
+            .map(|(macro_type, sites)| {
+                (
+                    macro_type,
+                    placed.get(&macro_type).copied().unwrap_or(0) as f32 / sites as f32,
+                )
+            })
+            .collect()
+    }
+
+    pub fn render_svg(&self) -> String {
+        self.render_svg_with_options(&SvgRenderOptions::default())
+    }
+
+    // render_svg with a rectangle per net instead of a line per edge, shaded
+    // from green to red by the net's hpwl relative to the longest net
+    pub fn render_svg_bboxes(&self) -> String {
+        self.render_svg_with_options(&SvgRenderOptions {
+            net_style: NetStyle::BoundingBoxes,
+            ..Default::default()
+        })
+    }
+
+    // render_svg with each net's rectilinear minimum spanning tree instead of
+    // a line per edge
+    pub fn render_mst_svg(&self) -> String {
+        self.render_svg_with_options(&SvgRenderOptions {
+            net_style: NetStyle::SpanningTrees,
+            ..Default::default()
+        })
+    }
+
+    pub fn render_svg_with_options(&self, options: &SvgRenderOptions) -> String {
+        let row = |y: u32| options.coord_system.display_y(y, self.layout.height);
+        let cell = options.cell_size;
+        let draw_labels = options
+            .max_labeled_nodes
+            .is_none_or(|max_labeled_nodes| self.solution_map.len() <= max_labeled_nodes);
+
+        let mut svg = String::new();
+
+        svg.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" style=\"background-color:white\" viewBox=\"0 0 {} {}\">\n",
+            self.layout.width * cell,
+            self.layout.height * cell
         ));
 
         // draw the white background manually
@@ -199,8 +1573,8 @@ impl<'a> PlacementSolution<'a> {
             "\t<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"white\"/>\n",
             0,
             0,
-            self.layout.width * 100,
-            self.layout.height * 100
+            self.layout.width * cell,
+            self.layout.height * cell
         ));
 
         // draw boxes for each location
@@ -213,20 +1587,45 @@ impl<'a> PlacementSolution<'a> {
                     FPGALayoutType::MacroType(MacroType::DSP) => "blue",
                     FPGALayoutType::MacroType(MacroType::BRAM) => "green",
                     FPGALayoutType::MacroType(MacroType::IO) => "yellow",
+                    FPGALayoutType::MultiType(_) => "purple",
                     FPGALayoutType::EMPTY => "gray",
                 };
 
                 svg.push_str(&format!(
-                    "\t<rect x=\"{}\" y=\"{}\" width=\"100\" height=\"100\" fill=\"{}\" fill-opacity=\"0.25\" stroke=\"black\" stroke-width=\"2\"/>\n",
-                    x * 100,
-                    y * 100,
-                    color
+                    "\t<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" fill-opacity=\"0.25\" stroke=\"black\" stroke-width=\"{}\"/>\n",
+                    x * cell,
+                    row(y) * cell,
+                    cell,
+                    cell,
+                    color,
+                    (cell / 50).max(1)
                 ));
             }
         }
 
+        // nodes sharing a multi-node site split its cell into vertical slices
+        // ordered by id, slot maps a node to its slice and the slice width
+        let mut site_nodes: FxHashMap<FPGALayoutCoordinate, Vec<NetlistNode>> =
+            FxHashMap::default();
+        for (node, location) in self.solution_map.iter() {
+            site_nodes.entry(*location).or_default().push(*node);
+        }
+        let mut slots = FxHashMap::default();
+        for nodes in site_nodes.values_mut() {
+            nodes.sort_by_key(|node| node.id);
+            let slice_width = cell / nodes.len() as u32;
+            for (i, node) in nodes.iter().enumerate() {
+                slots.insert(*node, (i as u32 * slice_width, slice_width));
+            }
+        }
+        let center_x = |node: &NetlistNode, location: &FPGALayoutCoordinate| {
+            let (offset, slice_width) = slots[node];
+            location.x * cell + offset + slice_width / 2
+        };
+
         // draw boxes for each netlist node
         for (node, location) in self.solution_map.iter() {
+            let (offset, slice_width) = slots[node];
             let color = match node.macro_type {
                 MacroType::CLB => "red",
                 MacroType::DSP => "blue",
@@ -235,21 +1634,59 @@ impl<'a> PlacementSolution<'a> {
             };
 
             svg.push_str(&format!(
-                "\t<rect x=\"{}\" y=\"{}\" width=\"100\" height=\"100\" fill=\"{}\"/>\n",
-                location.x * 100,
-                location.y * 100,
+                "\t<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                location.x * cell + offset,
+                row(location.y) * cell,
+                slice_width,
+                cell,
                 color
             ));
 
-            svg.push_str(&format!(
-                "\t<text x=\"{}\" y=\"{}\" fill=\"black\" font-size=\"50\">{}</text>\n",
-                location.x * 100 + 10,
-                location.y * 100 + 70,
-                node.id
-            ));
+            if draw_labels {
+                svg.push_str(&format!(
+                    "\t<text x=\"{}\" y=\"{}\" fill=\"black\" font-size=\"{}\">{}</text>\n",
+                    location.x * cell + offset + slice_width / 10,
+                    row(location.y) * cell + cell * 7 / 10,
+                    (cell / 2).min(slice_width),
+                    node.id
+                ));
+            }
+        }
+
+        if options.net_style == NetStyle::BoundingBoxes {
+            self.push_net_bboxes_svg(&mut svg, options);
+            svg.push_str("</svg>\n");
+            return svg;
+        }
+
+        if options.net_style == NetStyle::SpanningTrees {
+            for (net_id, pins) in self.netlist.nets().iter().enumerate() {
+                let color = match &options.criticality {
+                    Some(criticality) => {
+                        criticality_color(criticality.get(net_id).copied().unwrap_or(0.0))
+                    }
+                    None => "rgb(0,0,0)".to_string(),
+                };
+                for (a, b) in self.net_rmst_edges(pins) {
+                    let a_location = &self.solution_map[&a];
+                    let b_location = &self.solution_map[&b];
+                    svg.push_str(&format!(
+                        "\t<line class=\"net-mst\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" style=\"stroke:{};stroke-width:{}\" />\n",
+                        center_x(&a, a_location),
+                        row(a_location.y) * cell + cell / 2,
+                        center_x(&b, b_location),
+                        row(b_location.y) * cell + cell / 2,
+                        color,
+                        (cell / 25).max(1)
+                    ));
+                }
+            }
+            svg.push_str("</svg>\n");
+            return svg;
         }
 
         // draw lines for each netlist edge
+        let edge_nets = self.netlist.edge_nets();
         for edge in self.netlist.graph.edge_references() {
             let source_idx = edge.source();
             let target_idx = edge.target();
@@ -260,12 +1697,22 @@ impl<'a> PlacementSolution<'a> {
             let source_location = self.solution_map.get(source).unwrap();
             let target_location = self.solution_map.get(target).unwrap();
 
+            let color = match &options.criticality {
+                Some(criticality) => {
+                    let net_id = edge_nets[edge.id().index()];
+                    criticality_color(criticality.get(net_id).copied().unwrap_or(0.0))
+                }
+                None => "rgb(0,0,0)".to_string(),
+            };
+
             svg.push_str(&format!(
-                "\t<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" style=\"stroke:rgb(0,0,0);stroke-width:4\" />\n",
-                source_location.x * 100 + 50,
-                source_location.y * 100 + 50,
-                target_location.x * 100 + 50,
-                target_location.y * 100 + 50
+                "\t<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" style=\"stroke:{};stroke-width:{}\" />\n",
+                center_x(source, source_location),
+                row(source_location.y) * cell + cell / 2,
+                center_x(target, target_location),
+                row(target_location.y) * cell + cell / 2,
+                color,
+                (cell / 25).max(1)
             ));
         }
 
@@ -274,400 +1721,4718 @@ impl<'a> PlacementSolution<'a> {
         svg
     }
 
-    pub fn get_unplaced_nodes(&self) -> Vec<NetlistNode> {
-        let mut unplaced_nodes: Vec<NetlistNode> = Vec::new();
+    // NetStyle::BoundingBoxes, the criticality takes over the color when set
+    fn push_net_bboxes_svg(&self, svg: &mut String, options: &SvgRenderOptions) {
+        let row = |y: u32| options.coord_system.display_y(y, self.layout.height);
+        let cell = options.cell_size;
 
-        for node in self.netlist.graph.node_weights() {
-            if !self.solution_map.contains_key(node) {
-                unplaced_nodes.push(*node);
+        let nets = self.netlist.nets();
+        let hpwls = nets.iter().map(|pins| self.net_hpwl(pins)).collect_vec();
+        let max_hpwl = hpwls.iter().copied().fold(0.0, f32::max).max(1.0);
+
+        for (net_id, pins) in nets.iter().enumerate() {
+            let (min_x, max_x, min_y, max_y) = match self.net_bounding_box(pins) {
+                Some(bbox) => bbox,
+                None => continue,
+            };
+            let color = match &options.criticality {
+                Some(criticality) => {
+                    criticality_color(criticality.get(net_id).copied().unwrap_or(0.0))
+                }
+                None => criticality_color(hpwls[net_id] / max_hpwl),
+            };
+
+            svg.push_str(&format!(
+                "\t<rect class=\"net-bbox\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" fill-opacity=\"0.2\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                min_x * cell,
+                row(min_y).min(row(max_y)) * cell,
+                (max_x - min_x + 1) * cell,
+                (max_y - min_y + 1) * cell,
+                color,
+                color,
+                (cell / 25).max(1)
+            ));
+        }
+    }
+
+    // routing demand per bin_size x bin_size bin, indexed [bin_y][bin_x]
+    // every net's bounding box adds the number of its sites inside the bin,
+    // so bins many boxes pile up over have high demand
+    pub fn demand_grid(&self, bin_size: u32) -> Vec<Vec<u32>> {
+        assert!(bin_size > 0, "bin_size must be positive");
+        let bins_x = self.layout.width.div_ceil(bin_size);
+        let bins_y = self.layout.height.div_ceil(bin_size);
+        let mut grid = vec![vec![0; bins_x as usize]; bins_y as usize];
+
+        for pins in self.netlist.nets() {
+            let Some((min_x, max_x, min_y, max_y)) = self.net_bounding_box(&pins) else {
+                continue;
+            };
+            for bin_y in min_y / bin_size..=max_y / bin_size {
+                let overlap_y =
+                    (max_y + 1).min((bin_y + 1) * bin_size) - min_y.max(bin_y * bin_size);
+                for bin_x in min_x / bin_size..=max_x / bin_size {
+                    let overlap_x =
+                        (max_x + 1).min((bin_x + 1) * bin_size) - min_x.max(bin_x * bin_size);
+                    grid[bin_y as usize][bin_x as usize] += overlap_x * overlap_y;
+                }
             }
         }
 
-        unplaced_nodes
+        grid
     }
 
-    pub fn get_possible_sites(&self, macro_type: MacroType) -> Vec<FPGALayoutCoordinate> {
-        let mut possible_sites = Vec::new();
+    // render_svg with demand_grid drawn over it as a translucent red layer,
+    // the most congested bin is the most opaque
+    pub fn render_demand_svg(&self, bin_size: u32) -> String {
+        let cell = SvgRenderOptions::default().cell_size;
+        let grid = self.demand_grid(bin_size);
+        let max_demand = grid.iter().flatten().copied().max().unwrap_or(0).max(1);
 
-        let mut placed_locations = FxHashSet::default();
-        for location in self.solution_map.values() {
-            placed_locations.insert(*location);
-        }
+        let mut svg = self.render_svg();
+        svg.truncate(svg.len() - "</svg>\n".len());
 
-        for x in 0..self.layout.width {
-            for y in 0..self.layout.height {
-                // check if the location is unplaced
-                if placed_locations.contains(&FPGALayoutCoordinate::new(x, y)) {
+        for (bin_y, row) in grid.iter().enumerate() {
+            for (bin_x, demand) in row.iter().enumerate() {
+                if *demand == 0 {
                     continue;
                 }
+                let x = bin_x as u32 * bin_size;
+                let y = bin_y as u32 * bin_size;
+                svg.push_str(&format!(
+                    "\t<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"red\" fill-opacity=\"{:.3}\"/>\n",
+                    x * cell,
+                    y * cell,
+                    bin_size.min(self.layout.width - x) * cell,
+                    bin_size.min(self.layout.height - y) * cell,
+                    0.6 * *demand as f32 / max_demand as f32
+                ));
+            }
+        }
+
+        svg.push_str("</svg>\n");
 
-                let layout_type = self
-                    .layout
-                    .map
-                    .get(&FPGALayoutCoordinate::new(x, y))
-                    .unwrap();
+        svg
+    }
 
-                match layout_type {
-                    FPGALayoutType::MacroType(layout_macro_type) => {
-                        if layout_macro_type == &macro_type {
-                            possible_sites.push(FPGALayoutCoordinate::new(x, y));
-                        }
-                    }
-                    FPGALayoutType::EMPTY => {}
-                }
-            }
+    // placement in VPR's .place format, every node becomes the block "n<id>"
+    // and blocks are numbered in id order, unplaced nodes are left out
+    // VPR counts y up from the bottom of the array so rows are flipped on the
+    // way out, and the blocks sharing a multi-node site get subblocks 0, 1, ..
+    // in id order
+    // the Netlist_ID hash is a placeholder of zeros, VPR has to be told not to
+    // verify it (--verify_file_digests off) to read the file back
+    pub fn to_vpr_place(&self, netlist_file: &str, arch_file: &str) -> String {
+        let mut place = String::new();
+
+        place.push_str(&format!(
+            "Netlist_File: {} Netlist_ID: SHA256:{}\n",
+            netlist_file,
+            "0".repeat(64)
+        ));
+        place.push_str(&format!("Architecture_File: {}\n", arch_file));
+        place.push_str(&format!(
+            "Array size: {} x {} logic blocks\n\n",
+            self.layout.width, self.layout.height
+        ));
+        place.push_str("#block name\tx\ty\tsubblk\tblock number\n");
+        place.push_str("#----------\t--\t--\t------\t------------\n");
+
+        let placed = self
+            .netlist
+            .all_nodes_sorted()
+            .into_iter()
+            .filter_map(|node| self.solution_map.get(node).map(|location| (node, location)));
+        let mut subblocks: FxHashMap<FPGALayoutCoordinate, u32> = FxHashMap::default();
+        for (block_number, (node, location)) in placed.enumerate() {
+            let subblock = subblocks.entry(*location).or_insert(0);
+            place.push_str(&format!(
+                "n{}\t{}\t{}\t{}\t#{}\n",
+                node.id,
+                location.x,
+                CoordSystem::BottomLeft.display_y(location.y, self.layout.height),
+                subblock,
+                block_number
+            ));
+            *subblock += 1;
         }
 
-        possible_sites
+        place
     }
 
-    pub fn place_node(&mut self, node: NetlistNode, location: FPGALayoutCoordinate) {
-        self.solution_map.insert(node, location);
+    // same layout as FPGALayout::to_grid, but each cell holds the grid_code of
+    // the macro type of the node placed there and 0 for free sites
+    pub fn to_occupancy_grid(&self) -> Vec<Vec<u8>> {
+        let mut grid = vec![vec![0; self.layout.width as usize]; self.layout.height as usize];
+        for (node, location) in self.solution_map.iter() {
+            if location.x < self.layout.width && location.y < self.layout.height {
+                grid[location.y as usize][location.x as usize] =
+                    FPGALayoutType::MacroType(node.macro_type).grid_code();
+            }
+        }
+        grid
     }
 
-    pub fn valid(&self) -> bool {
-        // let netlist_nodes: Vec<NetlistNode> = self.netlist.all_nodes();
-        let netlist_nodes = self.netlist.graph.node_weights().collect_vec();
-        let netlist_nodes_ids = netlist_nodes
-            .iter()
-            .map(|node| node.id)
-            .collect::<Vec<u32>>();
+    // FPGALayout::render_ascii with the placed node ids drawn over their sites,
+    // ids wider than a cell fall back to the type letter in brackets, a site
+    // holding several nodes shows how many as *n and free sites keep the bare
+    // fabric letter
+    pub fn render_ascii(&self) -> String {
+        let mut placed: FxHashMap<FPGALayoutCoordinate, Vec<NetlistNode>> = FxHashMap::default();
+        for (node, location) in self.solution_map.iter() {
+            placed.entry(*location).or_default().push(*node);
+        }
+
+        self.layout.render_ascii_with(|location| {
+            match placed.get(location).map(|nodes| &nodes[..]) {
+                Some([node]) => {
+                    let id = node.id.to_string();
+                    if id.len() <= 3 {
+                        format!("{:^3}", id)
+                    } else {
+                        format!(
+                            "[{}]",
+                            FPGALayoutType::MacroType(node.macro_type).ascii_char()
+                        )
+                    }
+                }
+                Some(nodes) if !nodes.is_empty() => format!("{:^3}", format!("*{}", nodes.len())),
+                _ => {
+                    let layout_type = self.layout.get(location).unwrap_or(FPGALayoutType::EMPTY);
+                    format!(" {} ", layout_type.ascii_char())
+                }
+            }
+        })
+    }
+
+    pub fn get_unplaced_nodes(&self) -> Vec<NetlistNode> {
+        let mut unplaced_nodes: Vec<NetlistNode> = Vec::new();
 
-        // Check that all the nodes in the netlist are in the solution map
         for node in self.netlist.graph.node_weights() {
             if !self.solution_map.contains_key(node) {
-                return false;
+                unplaced_nodes.push(*node);
             }
         }
 
-        // Check that all the nodes in the solution map are in the netlist
-        for node in self.solution_map.keys() {
-            if !netlist_nodes_ids.contains(&node.id) {
-                return false;
-            }
-        }
+        unplaced_nodes
+    }
 
-        // check that each location in the layout is only used once
-        let mut used_locations = FxHashSet::default();
-        for location in self.solution_map.values() {
-            if used_locations.contains(location) {
-                return false;
-            }
-            used_locations.insert(*location);
+    // whether the site can host the node, ignoring whether it is occupied
+    pub fn site_accepts(&self, node: &NetlistNode, location: &FPGALayoutCoordinate) -> bool {
+        if !self
+            .layout
+            .get(location)
+            .is_some_and(|layout_type| layout_type.accepts(node.macro_type))
+        {
+            return false;
         }
 
-        // check that each node in the netlist is only used once
-        let mut used_nodes = FxHashSet::default();
-        for node in self.solution_map.keys() {
-            if used_nodes.contains(node) {
+        if node.macro_type == MacroType::IO {
+            let node_direction = self.netlist.io_direction(node);
+            if !node_direction.fits_site(self.layout.io_direction(location)) {
                 return false;
             }
-            used_nodes.insert(*node);
         }
 
-        // check that nodes are placed on the correct type of macro
-        for (node, location) in self.solution_map.iter() {
-            let layout_type: FPGALayoutType = self.layout.get(location).unwrap();
+        true
+    }
 
-            match layout_type {
-                FPGALayoutType::MacroType(macro_type) => {
-                    if node.macro_type != macro_type {
-                        return false;
-                    }
-                }
-                FPGALayoutType::EMPTY => return false,
-            }
+    // get_possible_sites narrowed down to the sites this specific node can use,
+    // which for IO nodes also respects the pad direction
+    pub fn get_possible_sites_for(&self, node: &NetlistNode) -> Vec<FPGALayoutCoordinate> {
+        // the node doesn't keep itself from moving a short distance
+        let possible_sites = self.possible_sites(node.macro_type, &[*node]);
+        if node.macro_type != MacroType::IO {
+            return possible_sites;
         }
 
-        true
+        possible_sites
+            .into_iter()
+            .filter(|location| self.site_accepts(node, location))
+            .collect()
     }
-}
 
-pub enum InitialPlacerMethod {
+    // number of nodes placed on each occupied site
+    pub fn occupancy(&self) -> FxHashMap<FPGALayoutCoordinate, u32> {
+        let mut occupancy = FxHashMap::default();
+        for location in self.solution_map.values() {
+            *occupancy.entry(*location).or_insert(0) += 1;
+        }
+        occupancy
+    }
+
+    pub fn get_possible_sites(&self, macro_type: MacroType) -> Vec<FPGALayoutCoordinate> {
+        self.possible_sites(macro_type, &[])
+    }
+
+    // sites within distance of the location, clipped to the layout
+    fn sites_within(
+        &self,
+        location: &FPGALayoutCoordinate,
+        distance: u32,
+    ) -> impl Iterator<Item = FPGALayoutCoordinate> {
+        let (width, height) = (self.layout.width, self.layout.height);
+        let (x0, y0) = (location.x, location.y);
+        (x0.saturating_sub(distance)..=(x0 + distance).min(width.saturating_sub(1))).flat_map(
+            move |x| {
+                let y_distance = distance - x.abs_diff(x0);
+                (y0.saturating_sub(y_distance)..=(y0 + y_distance).min(height.saturating_sub(1)))
+                    .map(move |y| FPGALayoutCoordinate::new(x, y))
+            },
+        )
+    }
+
+    // sites closer than min_spacing to a placed node of the type other than
+    // the ignored ones, empty when the type has no spacing
+    pub(crate) fn spacing_blocked_sites(
+        &self,
+        macro_type: MacroType,
+        ignore: &[NetlistNode],
+    ) -> FxHashSet<FPGALayoutCoordinate> {
+        let mut blocked = FxHashSet::default();
+        let spacing = self.min_spacing.get(&macro_type).copied().unwrap_or(0);
+        if spacing == 0 {
+            return blocked;
+        }
+
+        for (node, location) in &self.solution_map {
+            if node.macro_type == macro_type && !ignore.contains(node) {
+                blocked.extend(self.sites_within(location, spacing - 1));
+            }
+        }
+        blocked
+    }
+
+    // whether the site can host the type and the solution lets nodes onto
+    // it, i.e. it isn't forbidden and is inside the region, capacity and min
+    // spacing depend on which nodes move so callers check those
+    fn site_allowed(&self, macro_type: MacroType, location: &FPGALayoutCoordinate) -> bool {
+        self.layout
+            .get(location)
+            .is_some_and(|layout_type| layout_type.accepts(macro_type))
+            && !self.forbidden.contains(location)
+            && self.region.is_none_or(|region| region.contains(location))
+    }
+
+    // every free site a node of the type can go to, walking x in the outer
+    // loop and y in the inner one, the one filter get_possible_sites,
+    // free_sites and free_site_count all go through: room left under the
+    // site's capacity, no closer than min spacing to a placed node of the type
+    // other than the ignored ones, and site_allowed
+    fn free_sites_iter(
+        &self,
+        macro_type: MacroType,
+        ignore: &[NetlistNode],
+    ) -> impl Iterator<Item = FPGALayoutCoordinate> + '_ {
+        let occupancy = self.occupancy();
+        let blocked = self.spacing_blocked_sites(macro_type, ignore);
+
+        // only the sites inside the region can pass site_allowed
+        let (x_range, y_range) = match &self.region {
+            Some(region) => (
+                region.x_min..region.x_max.saturating_add(1).min(self.layout.width),
+                region.y_min..region.y_max.saturating_add(1).min(self.layout.height),
+            ),
+            None => (0..self.layout.width, 0..self.layout.height),
+        };
+
+        x_range
+            .flat_map(move |x| {
+                y_range
+                    .clone()
+                    .map(move |y| FPGALayoutCoordinate::new(x, y))
+            })
+            .filter(move |location| {
+                occupancy.get(location).copied().unwrap_or(0) < self.layout.capacity(location)
+                    && !blocked.contains(location)
+                    && self.site_allowed(macro_type, location)
+            })
+    }
+
+    fn possible_sites(
+        &self,
+        macro_type: MacroType,
+        ignore: &[NetlistNode],
+    ) -> Vec<FPGALayoutCoordinate> {
+        self.free_sites_iter(macro_type, ignore).collect()
+    }
+
+    // free sites of the type (inside the region when one is set), always
+    // sorted by x and then y so the order only depends on which sites are free
+    pub fn free_sites(&self, macro_type: MacroType) -> Vec<FPGALayoutCoordinate> {
+        self.get_possible_sites(macro_type)
+    }
+
+    // free_sites(macro_type).len() without building the list
+    pub fn free_site_count(&self, macro_type: MacroType) -> usize {
+        self.free_sites_iter(macro_type, &[]).count()
+    }
+
+    pub fn place_node(&mut self, node: NetlistNode, location: FPGALayoutCoordinate) {
+        self.set_location(node, location);
+    }
+
+    // move a node to a specific site, swapping with the occupant if it is a
+    // node of the same type, a site with room left is simply joined and on a
+    // full multi-node site the occupant with the lowest id is swapped out
+    // both the node's new site and the occupant's are held to the same rules
+    // as get_possible_sites_for (forbidden sites, the region, min spacing),
+    // soft regions only add cost so they don't stop a placement
+    pub fn try_place_at(
+        &mut self,
+        node: NetlistNode,
+        location: FPGALayoutCoordinate,
+    ) -> Result<(), PlaceError> {
+        if !self.netlist.graph.node_weights().any(|n| *n == node) {
+            return Err(PlaceError::NodeNotInNetlist(node));
+        }
+        if self.group_of(&node).is_some() {
+            return Err(PlaceError::Grouped(node));
+        }
+        if self.locked.contains(&node) {
+            return Err(PlaceError::Locked(node));
+        }
+
+        if self.layout.get(&location).is_none() {
+            return Err(PlaceError::OutOfBounds(location));
+        }
+        if !self.site_accepts(&node, &location) {
+            return Err(PlaceError::WrongSiteType(location));
+        }
+        if !self.site_allowed(node.macro_type, &location) {
+            return Err(PlaceError::NotAllowed(location));
+        }
+
+        let current_location = self.solution_map.get(&node).copied();
+        if current_location == Some(location) {
+            return Ok(());
+        }
+
+        let occupants = self
+            .solution_map
+            .iter()
+            .filter(|(_, occupied_location)| **occupied_location == location)
+            .map(|(occupant, _)| *occupant)
+            .collect_vec();
+        let occupant = if (occupants.len() as u32) < self.layout.capacity(&location) {
+            None
+        } else {
+            occupants.into_iter().min_by_key(|occupant| occupant.id)
+        };
+
+        // the swapped nodes trade places so neither counts against the other's spacing
+        let moving = [Some(node), occupant].into_iter().flatten().collect_vec();
+        if self
+            .spacing_blocked_sites(node.macro_type, &moving)
+            .contains(&location)
+        {
+            return Err(PlaceError::TooClose(location));
+        }
+
+        match (occupant, current_location) {
+            (None, _) => {}
+            (Some(occupant), _) if self.group_of(&occupant).is_some() => {
+                return Err(PlaceError::Grouped(occupant))
+            }
+            (Some(occupant), _) if self.locked.contains(&occupant) => {
+                return Err(PlaceError::Locked(occupant))
+            }
+            (Some(occupant), Some(current_location))
+                if self.site_accepts(&occupant, &current_location)
+                    && self.site_allowed(occupant.macro_type, &current_location)
+                    && !self
+                        .spacing_blocked_sites(occupant.macro_type, &moving)
+                        .contains(&current_location) =>
+            {
+                self.set_location(occupant, current_location);
+            }
+            (Some(occupant), _) => return Err(PlaceError::Occupied(occupant)),
+        }
+
+        self.set_location(node, location);
+
+        Ok(())
+    }
+
+    // forbid every site in the rectangle with inclusive bounds, clipped to the
+    // layout, on top of whatever is already forbidden
+    pub fn forbid_region(&mut self, x0: u32, y0: u32, x1: u32, y1: u32) {
+        let region = Region::new(x0, y0, x1, y1);
+        for x in region.x_min..=region.x_max.min(self.layout.width.saturating_sub(1)) {
+            for y in region.y_min..=region.y_max.min(self.layout.height.saturating_sub(1)) {
+                self.forbidden.insert(FPGALayoutCoordinate::new(x, y));
+            }
+        }
+    }
+
+    pub fn clear_forbidden(&mut self) {
+        self.forbidden.clear();
+    }
+
+    // try_place_at, then keep the node on that site for good, e.g. an IO pad
+    // pinned by a constraint file
+    pub fn lock_node(
+        &mut self,
+        node: NetlistNode,
+        location: FPGALayoutCoordinate,
+    ) -> Result<(), PlaceError> {
+        self.try_place_at(node, location)?;
+        self.locked.insert(node);
+        Ok(())
+    }
+
+    // lock_node for every (node, site) pair in order, e.g. the pads of a
+    // constraint file or a benchmark's fixed nodes, the first pair that fails
+    // rolls back the ones already locked so on an error the solution is left
+    // as it was
+    pub fn lock_nodes(
+        &mut self,
+        pins: &[(NetlistNode, FPGALayoutCoordinate)],
+    ) -> Result<(), (NetlistNode, PlaceError)> {
+        let before = self.clone();
+        for (node, location) in pins {
+            if let Err(err) = self.lock_node(*node, *location) {
+                *self = before;
+                return Err((*node, err));
+            }
+        }
+        Ok(())
+    }
+
+    // anneal only the part of the placement a netlist edit touched, for ECO
+    // flows where a full rerun is overkill
+    // the affected nodes are the changed nodes, every node sharing a net with
+    // one of them and every unplaced node (e.g. newly added ones), for removed
+    // nodes pass the nodes they were connected to instead, every other node is
+    // locked for the run so legalizing the unplaced nodes onto free sites and
+    // the moves themselves only ever pick affected nodes
+    // each step makes one move in place and costs it through the net cache,
+    // which only refreshes the nets of the moved nodes, a rejected move puts
+    // the affected nodes back, so a step costs about the nets of a node rather
+    // than the whole placement
+    // only the hpwl term of config is minimized (x_weight, y_weight and
+    // auto_weight), with n_steps, initial_temperature, cooling_rate,
+    // cooling_schedule over the affected nodes, biased_move_k and seed, the
+    // other terms and n_neighbors are ignored
+    // the locks in place before the call are kept, and a config.n_steps in
+    // line with the number of affected nodes is enough
+    pub fn reoptimize(
+        &mut self,
+        changed_nodes: &[NetlistNode],
+        config: PlacerConfig,
+    ) -> Result<(), LegalizeError> {
+        let changed_nodes = changed_nodes.iter().collect::<FxHashSet<_>>();
+        let mut affected = FxHashSet::default();
+        for pins in self.netlist.nets() {
+            if pins.iter().any(|pin| changed_nodes.contains(pin)) {
+                affected.extend(pins);
+            }
+        }
+        affected.extend(changed_nodes.into_iter().copied());
+        affected.extend(
+            self.netlist
+                .graph
+                .node_weights()
+                .filter(|node| !self.solution_map.contains_key(*node)),
+        );
+
+        let locked = self.locked.clone();
+        self.locked.extend(
+            self.netlist
+                .graph
+                .node_weights()
+                .filter(|node| !affected.contains(*node)),
+        );
+        if let Err(err) = self.legalize() {
+            self.locked = locked;
+            return Err(err);
+        }
+
+        if config.auto_weight != WeightPolicy::Manual {
+            self.net_weights = Some(Arc::new(config.auto_weight.net_weights(self.netlist)));
+        }
+        self.enable_net_cache();
+        let hpwl_cost = HpwlCost {
+            x_weight: config.x_weight,
+            y_weight: config.y_weight,
+        };
+        let mut rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut actions = Placer::ACTIONS.to_vec();
+        if config.biased_move_k > 0 {
+            actions.push(PlacementAction::MoveBiased(config.biased_move_k));
+        }
+        let affected = affected
+            .into_iter()
+            .filter(|node| !self.locked.contains(node))
+            .sorted_by_key(|node| node.id)
+            .collect_vec();
+        let moves_per_temperature = config
+            .cooling_schedule
+            .moves_per_temperature(affected.len());
+
+        let mut temperature = config.initial_temperature;
+        for step in 1..=config.n_steps {
+            let old_locations = affected
+                .iter()
+                .map(|node| self.solution_map[node])
+                .collect_vec();
+            let current_cost = hpwl_cost.cost(self);
+            self.action_with_rng(*actions.choose(&mut rng).unwrap(), &mut rng);
+            let delta = hpwl_cost.cost(self) - current_cost;
+
+            let accepted = delta < 0.0
+                || (temperature > 0.0 && rng.gen::<f32>() < (-delta / temperature).exp());
+            if !accepted {
+                for (node, old_location) in affected.iter().zip(old_locations) {
+                    if self.solution_map[node] != old_location {
+                        self.set_location(*node, old_location);
+                    }
+                }
+            }
+            if step.is_multiple_of(moves_per_temperature) {
+                temperature *= config.cooling_rate;
+            }
+        }
+
+        // drop the float drift of the running weighted hpwl
+        self.recompute_cost();
+        self.locked = locked;
+        Ok(())
+    }
+
+    // repair a near-legal placement by greedily moving each offending node
+    // (unplaced, on a site that can't host it, outside the region, or sharing a
+    // site) to the nearest free legal site, returns how many nodes were moved
+    // when two nodes share a site the one with the lower id keeps it, placed
+    // locked nodes never move and keep their site over any other node
+    // relative groups move as a unit to the nearest anchor that fits them,
+    // ahead of the ungrouped nodes
+    pub fn legalize(&mut self) -> Result<usize, LegalizeError> {
+        let (offending, groups) = self.take_offending_nodes();
+        self.legalize_groups(&offending, &groups)?;
+
+        for (node, old_location) in &offending {
+            if self.group_of(node).is_some() {
+                continue;
+            }
+            let possible_sites = self.get_possible_sites_for(node);
+            let location = match old_location {
+                Some(old_location) => possible_sites.iter().min_by_key(|site| {
+                    site.x.abs_diff(old_location.x) + site.y.abs_diff(old_location.y)
+                }),
+                None => possible_sites.first(),
+            };
+
+            match location {
+                Some(location) => self.set_location(*node, *location),
+                None => return Err(LegalizeError::NoFreeSite(*node)),
+            }
+        }
+
+        Ok(offending.len())
+    }
+
+    // like legalize but the offending nodes are assigned together to keep
+    // their summed manhattan displacement low instead of one at a time
+    // every (node, free site) pair is tried shortest first and taken when
+    // both are still unassigned, legal nodes never move and unplaced nodes
+    // take the first free site afterwards without counting toward the total
+    // relative groups go first and as a unit, as in legalize
+    // the pairs are gathered outward from each node's old location in
+    // rounds of doubling radius, only the nodes still unassigned search
+    // further, so a mostly legal placement never builds every node x site pair
+    // returns the total displacement of the nodes that had a location
+    pub fn legalize_min_displacement(&mut self) -> Result<u32, LegalizeError> {
+        let (offending, groups) = self.take_offending_nodes();
+        let group_displacement = self.legalize_groups(&offending, &groups)?;
+
+        let mut assigned = offending
+            .iter()
+            .map(|(node, _)| self.group_of(node).is_some())
+            .collect_vec();
+        // the free sites each type can take before any of the nodes land
+        let mut free_sites: FxHashMap<MacroType, FxHashSet<FPGALayoutCoordinate>> =
+            FxHashMap::default();
+        for (node, _) in &offending {
+            free_sites.entry(node.macro_type).or_insert_with(|| {
+                self.possible_sites(node.macro_type, &[])
+                    .into_iter()
+                    .collect()
+            });
+        }
+
+        let mut occupancy = self.occupancy();
+        let mut total_displacement = group_displacement;
+        // every pair within radius is gathered again each round, the ones a
+        // node passed over before are taken by then so the order of the
+        // assignments is the same as sorting every pair at once
+        let max_radius = self.layout.width + self.layout.height;
+        let mut radius = 1;
+        loop {
+            let mut candidates = Vec::new();
+            for (node_idx, (node, old_location)) in offending.iter().enumerate() {
+                let Some(old_location) = old_location else {
+                    continue;
+                };
+                if assigned[node_idx] {
+                    continue;
+                }
+                for site in self.sites_within(old_location, radius) {
+                    if free_sites[&node.macro_type].contains(&site)
+                        && self.site_accepts(node, &site)
+                    {
+                        let displacement =
+                            site.x.abs_diff(old_location.x) + site.y.abs_diff(old_location.y);
+                        candidates.push((displacement, node.id, node_idx, site));
+                    }
+                }
+            }
+            // node id and site break ties so the assignment is deterministic
+            candidates.sort_by_key(|&(displacement, node_id, _, site)| {
+                (displacement, node_id, site.x, site.y)
+            });
+
+            for (displacement, _, node_idx, site) in candidates {
+                let site_occupancy = occupancy.entry(site).or_insert(0);
+                if assigned[node_idx] || *site_occupancy >= self.layout.capacity(&site) {
+                    continue;
+                }
+                assigned[node_idx] = true;
+                *site_occupancy += 1;
+                total_displacement += displacement;
+                self.set_location(offending[node_idx].0, site);
+            }
+
+            let searching = offending
+                .iter()
+                .zip(&assigned)
+                .any(|((_, old_location), assigned)| old_location.is_some() && !assigned);
+            if !searching || radius >= max_radius {
+                break;
+            }
+            radius = (radius * 2).min(max_radius);
+        }
+
+        for (node_idx, (node, _)) in offending.iter().enumerate() {
+            if assigned[node_idx] {
+                continue;
+            }
+            match self.get_possible_sites_for(node).first() {
+                Some(location) => self.set_location(*node, *location),
+                None => return Err(LegalizeError::NoFreeSite(*node)),
+            }
+        }
+
+        Ok(total_displacement)
+    }
+
+    // move each group take_offending_nodes unplaced to the anchor nearest its
+    // anchor's old location, returns the summed displacement of the members
+    // that had a location
+    fn legalize_groups(
+        &mut self,
+        offending: &[(NetlistNode, Option<FPGALayoutCoordinate>)],
+        groups: &[usize],
+    ) -> Result<u32, LegalizeError> {
+        let old_locations = offending.iter().copied().collect::<FxHashMap<_, _>>();
+
+        let mut total_displacement = 0;
+        for group_idx in groups {
+            let group = &self.groups[*group_idx];
+            let anchor = group.anchor();
+            let location = self
+                .nearest_group_anchor(group, old_locations.get(&anchor).copied().flatten())
+                .ok_or(LegalizeError::NoFreeSite(anchor))?;
+            self.move_group(*group_idx, location);
+
+            for (node, _, _) in &self.groups[*group_idx].members {
+                if let Some(Some(old_location)) = old_locations.get(node) {
+                    total_displacement += self.solution_map[node].manhattan_distance(old_location);
+                }
+            }
+        }
+
+        Ok(total_displacement as u32)
+    }
+
+    // drop nodes that aren't in the netlist and unplace every node that is
+    // unplaced, on a site that can't host it, outside the region, or over a
+    // site's capacity (the lowest ids keep a shared site), returning those nodes in id
+    // order with the location they had
+    // placed locked nodes stay put whatever they break and claim their site
+    // before anything else, so an unlocked node sharing it is the one moved
+    // a relative group is taken as a unit: when any member would be taken or
+    // the members are off their offsets every member is, and the indices of
+    // those groups come back too, whole groups claim their sites right after
+    // the locked nodes, and a group with a placed locked member is left as is
+    fn take_offending_nodes(
+        &mut self,
+    ) -> (Vec<(NetlistNode, Option<FPGALayoutCoordinate>)>, Vec<usize>) {
+        let nodes = self
+            .netlist
+            .all_nodes_sorted()
+            .into_iter()
+            .copied()
+            .collect_vec();
+
+        // entries for nodes that aren't in the netlist can't be repaired, only dropped
+        let netlist_nodes = nodes.iter().copied().collect::<FxHashSet<_>>();
+        let stray_nodes = self
+            .solution_map
+            .keys()
+            .filter(|node| !netlist_nodes.contains(node))
+            .copied()
+            .collect_vec();
+        for node in stray_nodes {
+            self.clear_location(node);
+        }
+
+        let mut claimed_locations: FxHashMap<FPGALayoutCoordinate, u32> = FxHashMap::default();
+        for node in &nodes {
+            if let (true, Some(location)) =
+                (self.locked.contains(node), self.solution_map.get(node))
+            {
+                *claimed_locations.entry(*location).or_insert(0) += 1;
+            }
+        }
+
+        let keeps_site =
+            |node: &NetlistNode,
+             location: &FPGALayoutCoordinate,
+             claimed_locations: &FxHashMap<FPGALayoutCoordinate, u32>| {
+                self.site_accepts(node, location)
+                    && self.region.is_none_or(|region| region.contains(location))
+                    && !self.forbidden.contains(location)
+                    && claimed_locations.get(location).copied().unwrap_or(0)
+                        < self.layout.capacity(location)
+            };
+        let is_locked =
+            |node: &NetlistNode| self.locked.contains(node) && self.solution_map.contains_key(node);
+
+        let broken_groups = self.broken_groups();
+        let mut offending_groups = Vec::new();
+        for (group_idx, group) in self.groups.iter().enumerate() {
+            if group.members.iter().any(|(node, _, _)| is_locked(node)) {
+                continue;
+            }
+            let whole = !broken_groups.contains(&group.anchor())
+                && group.members.iter().all(|(node, _, _)| {
+                    self.solution_map
+                        .get(node)
+                        .is_some_and(|location| keeps_site(node, location, &claimed_locations))
+                });
+            if whole {
+                for (node, _, _) in &group.members {
+                    *claimed_locations
+                        .entry(self.solution_map[node])
+                        .or_insert(0) += 1;
+                }
+            } else {
+                offending_groups.push(group_idx);
+            }
+        }
+        let offending_members = offending_groups
+            .iter()
+            .flat_map(|group_idx| &self.groups[*group_idx].members)
+            .map(|(node, _, _)| *node)
+            .collect::<FxHashSet<_>>();
+
+        let mut offending = Vec::new();
+        for node in nodes {
+            let location = self.solution_map.get(&node).copied();
+            if offending_members.contains(&node) {
+                offending.push((node, location));
+                continue;
+            }
+            if is_locked(&node) || self.group_of(&node).is_some() {
+                continue;
+            }
+            match location {
+                Some(location) if keeps_site(&node, &location, &claimed_locations) => {
+                    *claimed_locations.entry(location).or_insert(0) += 1;
+                }
+                location => offending.push((node, location)),
+            }
+        }
+
+        // free every offending site first so the nodes can land on each other's old sites
+        for (node, _) in &offending {
+            self.clear_location(*node);
+        }
+
+        (offending, offending_groups)
+    }
+
+    pub fn valid(&self) -> bool {
+        self.validate(false).is_empty()
+    }
+
+    // every way the placement breaks the rules, empty for a legal placement
+    // with allow_unplaced nodes missing from solution_map are fine, which is
+    // what incremental flows that only place part of the design need
+    pub fn validate(&self, allow_unplaced: bool) -> Vec<PlacementViolation> {
+        let mut violations = Vec::new();
+
+        // Check that all the nodes in the netlist are in the solution map
+        if !allow_unplaced {
+            for node in self.netlist.all_nodes_sorted() {
+                if !self.solution_map.contains_key(node) {
+                    violations.push(PlacementViolation::Unplaced(*node));
+                }
+            }
+        }
+
+        let netlist_nodes = self.netlist.graph.node_weights().collect::<FxHashSet<_>>();
+
+        let mut placed = self.solution_map.iter().collect_vec();
+        placed.sort_by_key(|(node, _)| node.id);
+
+        let mut used_locations: FxHashMap<FPGALayoutCoordinate, Vec<NetlistNode>> =
+            FxHashMap::default();
+        for (node, location) in placed {
+            // Check that all the nodes in the solution map are in the netlist
+            if !netlist_nodes.contains(node) {
+                violations.push(PlacementViolation::NotInNetlist(*node));
+            }
+
+            // check that each location in the layout holds at most its capacity
+            let occupants = used_locations.entry(*location).or_default();
+            if occupants.len() as u32 >= self.layout.capacity(location) {
+                violations.push(PlacementViolation::SharedSite(
+                    *location,
+                    occupants[0],
+                    *node,
+                ));
+            }
+            occupants.push(*node);
+
+            // check that nodes are placed on the correct type of macro and
+            // IO pads sit on a site with a compatible direction
+            if !self.site_accepts(node, location) {
+                violations.push(PlacementViolation::WrongSiteType(*node, *location));
+            }
+
+            // check that nodes stay inside the region
+            if let Some(region) = &self.region {
+                if !region.contains(location) {
+                    violations.push(PlacementViolation::OutsideRegion(*node, *location));
+                }
+            }
+        }
+
+        for anchor in self.broken_groups() {
+            violations.push(PlacementViolation::BrokenGroup(anchor));
+        }
+
+        // check that nodes of a type with a min spacing keep it
+        let mut by_location: FxHashMap<FPGALayoutCoordinate, Vec<NetlistNode>> =
+            FxHashMap::default();
+        for (node, location) in &self.solution_map {
+            if self.min_spacing.get(&node.macro_type).copied().unwrap_or(0) > 0 {
+                by_location.entry(*location).or_default().push(*node);
+            }
+        }
+        let mut too_close = Vec::new();
+        for (node, location) in &self.solution_map {
+            let spacing = self.min_spacing.get(&node.macro_type).copied().unwrap_or(0);
+            if spacing == 0 {
+                continue;
+            }
+            for site in self.sites_within(location, spacing - 1) {
+                for other in by_location.get(&site).into_iter().flatten() {
+                    if other.macro_type == node.macro_type && other.id > node.id {
+                        too_close.push((*node, *other));
+                    }
+                }
+            }
+        }
+        too_close.sort_by_key(|(a, b)| (a.id, b.id));
+        violations.extend(
+            too_close
+                .into_iter()
+                .map(|(a, b)| PlacementViolation::TooClose(a, b)),
+        );
+
+        violations
+    }
+
+    // wirelength over the edges with both endpoints placed, so a partial
+    // placement can be scored without placing the rest of the design
+    pub fn cost_partial(&self) -> f32 {
+        let mut cost = 0;
+
+        for edge in self.netlist.graph.edge_references() {
+            let source = &self.netlist.graph[edge.source()];
+            let target = &self.netlist.graph[edge.target()];
+
+            if let (Some(source_location), Some(target_location)) =
+                (self.solution_map.get(source), self.solution_map.get(target))
+            {
+                cost += self.layout.distance(source_location, target_location);
+            }
+        }
+
+        cost as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitialPlacementError {
+    // the layout has fewer sites of a type than the netlist has nodes of it
+    NotEnoughSites {
+        macro_type: MacroType,
+        needed: u32,
+        available: u32,
+    },
+    // the sites left over can't host the node, e.g. every site in the region
+    // or every IO site with a matching direction is already taken
+    NoFreeSite(NetlistNode),
+    // the regions of two designs placed on one fabric share sites, by index
+    OverlappingRegions(usize, usize),
+}
+
+impl std::fmt::Display for InitialPlacementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InitialPlacementError::NotEnoughSites {
+                macro_type,
+                needed,
+                available,
+            } => write!(
+                f,
+                "netlist needs {} {} sites but the layout only has {}",
+                needed, macro_type, available
+            ),
+            InitialPlacementError::NoFreeSite(node) => {
+                write!(f, "no free site left for node {}", node.id)
+            }
+            InitialPlacementError::OverlappingRegions(first, second) => {
+                write!(f, "regions of designs {} and {} overlap", first, second)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InitialPlacementError {}
+
+// a reason the netlist can't be placed on the layout, see check_placeable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatIssue {
+    // the layout has no sites at all
+    EmptyFabric,
+    // the layout has fewer sites of a type than the netlist has nodes of it
+    NotEnoughSites {
+        macro_type: MacroType,
+        needed: u32,
+        available: u32,
+    },
+    // fewer IO sites a pad of this direction fits on than pads of it
+    NotEnoughIoSites {
+        direction: IoDirection,
+        needed: u32,
+        available: u32,
+    },
+}
+
+impl std::fmt::Display for CompatIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompatIssue::EmptyFabric => write!(f, "the layout has no sites"),
+            CompatIssue::NotEnoughSites {
+                macro_type,
+                needed,
+                available,
+            } => write!(
+                f,
+                "netlist needs {} {} sites but the layout only has {}",
+                needed, macro_type, available
+            ),
+            CompatIssue::NotEnoughIoSites {
+                direction,
+                needed,
+                available,
+            } => write!(
+                f,
+                "netlist has {} {:?} IO pads but the layout only has {} IO sites they fit on",
+                needed, direction, available
+            ),
+        }
+    }
+}
+
+// check up front that the netlist can fit on the layout, reporting every
+// problem found instead of stopping at the first one
+// macro types are a closed set and unknown ones are already rejected when a
+// netlist is parsed, so a type the fabric lacks shows up as NotEnoughSites
+// with 0 available
+// the per type and per direction counts are necessary but not sufficient, a
+// placement can still fail on regions or pads competing for the same sites
+pub fn check_placeable(
+    layout: &FPGALayout,
+    netlist: &NetlistGraph,
+) -> Result<(), Vec<CompatIssue>> {
+    let mut issues = Vec::new();
+
+    let count_summary_layout = layout.count_summary();
+    let count_summary_netlist = netlist.count_summary();
+
+    let n_sites = layout.width * layout.height
+        - count_summary_layout
+            .get(&FPGALayoutType::EMPTY)
+            .copied()
+            .unwrap_or(0);
+    if n_sites == 0 {
+        issues.push(CompatIssue::EmptyFabric);
+    }
+
+    for &macro_type in &[
+        MacroType::CLB,
+        MacroType::DSP,
+        MacroType::BRAM,
+        MacroType::IO,
+    ] {
+        // a site counts once for every node it can hold, and a multi type
+        // site toward each of its types
+        let available = layout
+            .map
+            .iter()
+            .filter(|(_, layout_type)| layout_type.accepts(macro_type))
+            .map(|(location, _)| layout.capacity(location))
+            .sum::<u32>();
+        let needed = count_summary_netlist.get(&macro_type).copied().unwrap_or(0);
+        if available < needed {
+            issues.push(CompatIssue::NotEnoughSites {
+                macro_type,
+                needed,
+                available,
+            });
+        }
+    }
+
+    let io_sites = layout
+        .map
+        .iter()
+        .filter(|(_, layout_type)| layout_type.accepts(MacroType::IO))
+        .map(|(location, _)| (layout.io_direction(location), layout.capacity(location)))
+        .collect_vec();
+    let io_nodes = netlist
+        .graph
+        .node_weights()
+        .filter(|node| node.macro_type == MacroType::IO)
+        .map(|node| netlist.io_direction(node))
+        .collect_vec();
+    for direction in [
+        IoDirection::Input,
+        IoDirection::Output,
+        IoDirection::Bidirectional,
+    ] {
+        let needed = io_nodes.iter().filter(|node| **node == direction).count() as u32;
+        let available = io_sites
+            .iter()
+            .filter(|(site_direction, _)| direction.fits_site(*site_direction))
+            .map(|(_, capacity)| capacity)
+            .sum::<u32>();
+        if available < needed {
+            issues.push(CompatIssue::NotEnoughIoSites {
+                direction,
+                needed,
+                available,
+            });
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+pub(crate) fn check_site_counts(
+    layout: &FPGALayout,
+    netlist: &NetlistGraph,
+) -> Result<(), InitialPlacementError> {
+    let issues = match check_placeable(layout, netlist) {
+        Ok(()) => return Ok(()),
+        Err(issues) => issues,
+    };
+
+    // the other issues surface as NoFreeSite once placement gets to them
+    for issue in issues {
+        if let CompatIssue::NotEnoughSites {
+            macro_type,
+            needed,
+            available,
+        } = issue
+        {
+            return Err(InitialPlacementError::NotEnoughSites {
+                macro_type,
+                needed,
+                available,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+pub enum InitialPlacerMethod {
     Random,
     Greedy,
+    // see gen_scarcity_ordered_placement_with_rng
+    ScarcityOrdered,
+    // see gen_analytical_placement_with_rng
+    Analytical,
+    // see gen_hilbert_placement
+    HilbertCurve,
+}
+
+pub fn gen_random_placement<'a>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    gen_random_placement_with_rng(layout, netlist, &mut rand::thread_rng())
+}
+
+pub fn gen_random_placement_with_rng<'a, R: Rng + ?Sized>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+    rng: &mut R,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    let mut solution = PlacementSolution::new(layout, netlist);
+
+    check_site_counts(layout, netlist)?;
+
+    for node in solution.netlist.all_nodes_sorted() {
+        let location = *solution
+            .get_possible_sites_for(node)
+            .choose(rng)
+            .ok_or(InitialPlacementError::NoFreeSite(*node))?;
+        solution.place_node(*node, location);
+    }
+
+    assert!(solution.valid());
+
+    Ok(solution)
+}
+
+pub fn gen_random_placement_in_region<'a>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+    region: Region,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    gen_random_placement_in_region_with_rng(layout, netlist, region, &mut rand::thread_rng())
+}
+
+pub fn gen_random_placement_in_region_with_rng<'a, R: Rng + ?Sized>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+    region: Region,
+    rng: &mut R,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    let mut solution = PlacementSolution::new(layout, netlist);
+    solution.region = Some(region);
+
+    for node in solution.netlist.all_nodes_sorted() {
+        let location = *solution
+            .get_possible_sites_for(node)
+            .choose(rng)
+            .ok_or(InitialPlacementError::NoFreeSite(*node))?;
+        solution.place_node(*node, location);
+    }
+
+    assert!(solution.valid());
+
+    Ok(solution)
+}
+
+// random placement that places the nodes with the fewest sites able to host
+// them first, so IO pads with a direction, BRAM and DSP get their pick before
+// the abundant CLBs (and bidirectional pads before the ones that could also
+// use a directional site), ties in order of node id
+pub fn gen_scarcity_ordered_placement_with_rng<'a, R: Rng + ?Sized>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+    rng: &mut R,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    let mut solution = PlacementSolution::new(layout, netlist);
+
+    check_site_counts(layout, netlist)?;
+
+    // sites per node, which only depends on the macro type and pad direction
+    let mut site_counts: FxHashMap<(MacroType, IoDirection), usize> = FxHashMap::default();
+    let mut nodes = netlist
+        .all_nodes_sorted()
+        .into_iter()
+        .map(|node| {
+            let site_count = *site_counts
+                .entry((node.macro_type, netlist.io_direction(node)))
+                .or_insert_with(|| solution.get_possible_sites_for(node).len());
+            (site_count, *node)
+        })
+        .collect_vec();
+    nodes.sort_by_key(|(site_count, node)| (*site_count, node.id));
+
+    for (_, node) in nodes {
+        let location = *solution
+            .get_possible_sites_for(&node)
+            .choose(rng)
+            .ok_or(InitialPlacementError::NoFreeSite(node))?;
+        solution.place_node(node, location);
+    }
+
+    assert!(solution.valid());
+
+    Ok(solution)
+}
+
+pub fn gen_greedy_placement<'a>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    // place nodes in the first spot in the layout closest to the origin (0,0) which is the top left corner
+
+    let mut solution = PlacementSolution::new(layout, netlist);
+
+    check_site_counts(layout, netlist)?;
+
+    let nodes = solution.netlist.all_nodes_sorted();
+    for node in nodes {
+        let possible_sites = solution.get_possible_sites_for(node);
+        // get the site with the min manhattan distance to the origin (0,0)
+        let location = possible_sites
+            .iter()
+            .min_by_key(|location| location.x as u64 + location.y as u64)
+            .ok_or(InitialPlacementError::NoFreeSite(*node))?;
+
+        solution.place_node(*node, *location);
+    }
+
+    assert!(solution.valid());
+
+    Ok(solution)
+}
+
+// walk the netlist breadth first, each connected component from its lowest
+// id node with neighbors in order of id, and lay the walk out along
+// FPGALayout::hilbert_order, the n-th node of the walk goes to the free site
+// of its type closest to the n-th position on the curve, so nodes a few hops
+// apart in the netlist tend to land a few sites apart on the fabric and the
+// placement fills a compact patch of the fabric starting at its origin
+pub fn gen_hilbert_placement<'a>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    let mut solution = PlacementSolution::new(layout, netlist);
+
+    check_site_counts(layout, netlist)?;
+
+    let graph = &netlist.graph;
+    let mut roots = graph.node_indices().collect_vec();
+    roots.sort_by_key(|idx| graph[*idx].id);
+    let mut visited = vec![false; graph.node_count()];
+    let mut walk = Vec::with_capacity(graph.node_count());
+    for root in roots {
+        if visited[root.index()] {
+            continue;
+        }
+        visited[root.index()] = true;
+        let mut queue = std::collections::VecDeque::from([root]);
+        while let Some(idx) = queue.pop_front() {
+            walk.push(graph[idx]);
+            let mut neighbors = graph
+                .neighbors_undirected(idx)
+                .filter(|neighbor| !visited[neighbor.index()])
+                .collect_vec();
+            neighbors.sort_by_key(|neighbor| graph[*neighbor].id);
+            neighbors.dedup();
+            for neighbor in neighbors {
+                visited[neighbor.index()] = true;
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    // curve positions of the sites of each type
+    let curve = layout.hilbert_order();
+    let mut curve_sites: FxHashMap<MacroType, Vec<usize>> = FxHashMap::default();
+    for node in &walk {
+        curve_sites.entry(node.macro_type).or_insert_with(|| {
+            (0..curve.len())
+                .filter(|position| {
+                    layout
+                        .get(&curve[*position])
+                        .is_some_and(|layout_type| layout_type.accepts(node.macro_type))
+                })
+                .collect()
+        });
+    }
+
+    let mut occupancy: FxHashMap<FPGALayoutCoordinate, u32> = FxHashMap::default();
+    for (cursor, node) in walk.iter().enumerate() {
+        let sites = &curve_sites[&node.macro_type];
+        // search outward from the cursor, the closer of the two sides first
+        let mut below = sites.partition_point(|position| *position < cursor);
+        let mut above = below;
+        let location = loop {
+            let position = match (below.checked_sub(1), sites.get(above)) {
+                (Some(next_below), Some(&position))
+                    if position - cursor <= cursor - sites[next_below] =>
+                {
+                    above += 1;
+                    position
+                }
+                (Some(next_below), _) => {
+                    below = next_below;
+                    sites[next_below]
+                }
+                (None, Some(&position)) => {
+                    above += 1;
+                    position
+                }
+                (None, None) => return Err(InitialPlacementError::NoFreeSite(*node)),
+            };
+            let location = curve[position];
+            if occupancy.get(&location).copied().unwrap_or(0) < layout.capacity(&location)
+                && solution.site_accepts(node, &location)
+            {
+                break location;
+            }
+        };
+        *occupancy.entry(location).or_insert(0) += 1;
+        solution.place_node(*node, location);
+    }
+
+    assert!(solution.valid());
+
+    Ok(solution)
+}
+
+pub fn gen_initial_placement<'a>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+    method: InitialPlacerMethod,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    gen_initial_placement_with_rng(layout, netlist, method, &mut rand::thread_rng())
+}
+
+pub fn gen_initial_placement_with_rng<'a, R: Rng + ?Sized>(
+    layout: &'a FPGALayout,
+    netlist: &'a NetlistGraph,
+    method: InitialPlacerMethod,
+    rng: &mut R,
+) -> Result<PlacementSolution<'a>, InitialPlacementError> {
+    match method {
+        InitialPlacerMethod::Random => gen_random_placement_with_rng(layout, netlist, rng),
+        InitialPlacerMethod::Greedy => gen_greedy_placement(layout, netlist),
+        InitialPlacerMethod::ScarcityOrdered => {
+            gen_scarcity_ordered_placement_with_rng(layout, netlist, rng)
+        }
+        InitialPlacerMethod::Analytical => gen_analytical_placement_with_rng(layout, netlist, rng),
+        InitialPlacerMethod::HilbertCurve => gen_hilbert_placement(layout, netlist),
+    }
+}
+
+// which recorded frames end up in the video
+#[derive(Debug, Clone, Copy)]
+pub enum FrameSelection {
+    // every n-th frame
+    EveryN(usize),
+    // a frame whenever the cost moved by more than the threshold since the last
+    // selected frame, frames recorded without a cost are always selected
+    // the framerate stays fixed so stretches where the placement barely changes
+    // are skipped over and the video spends its time on the big changes, the
+    // video length no longer maps linearly onto placer steps
+    CostChange(f32),
+}
+
+// output files of Renderer::render_to_video_with_formats, all named after output_name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    // h.264 .mp4, the smallest file but lossy and chroma subsampled
+    Mp4,
+    // half size .gif with a 32 color palette, small and plays anywhere but
+    // bands the heatmap gradients
+    Gif,
+    // animated png (.apng), lossless and full color so gradients stay crisp,
+    // at the cost of files often 10x or more the size of the mp4
+    Apng,
+}
+
+#[derive(Clone)]
+pub struct Renderer {
+    pub svg_renders: Vec<String>,
+    // cost of the solution in each frame, if it was recorded with one
+    pub frame_costs: Vec<Option<f32>>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer {
+    pub fn new() -> Renderer {
+        Renderer {
+            svg_renders: Vec::new(),
+            frame_costs: Vec::new(),
+        }
+    }
+
+    pub fn add_frame(&mut self, svg: String) {
+        self.svg_renders.push(svg);
+        self.frame_costs.push(None);
+    }
+
+    pub fn add_frame_with_cost(&mut self, svg: String, cost: f32) {
+        self.svg_renders.push(svg);
+        self.frame_costs.push(Some(cost));
+    }
+
+    // indices of the frames that go into the video, in order, always ending
+    // with the last frame so the converged placement is shown whatever the stride
+    pub fn selected_frames(&self, selection: FrameSelection) -> Vec<usize> {
+        let mut selected = match selection {
+            FrameSelection::EveryN(every_n_frames) => (0..self.svg_renders.len())
+                .filter(|frame_number| frame_number % every_n_frames == 0)
+                .collect(),
+            FrameSelection::CostChange(threshold) => {
+                let mut selected = Vec::new();
+                let mut last_cost: Option<f32> = None;
+
+                for (frame_number, cost) in self.frame_costs.iter().enumerate() {
+                    let keep = match (cost, last_cost) {
+                        (Some(cost), Some(last_cost)) => (cost - last_cost).abs() > threshold,
+                        _ => true,
+                    };
+                    if keep {
+                        selected.push(frame_number);
+                        if cost.is_some() {
+                            last_cost = *cost;
+                        }
+                    }
+                }
+
+                selected
+            }
+        };
+
+        if let Some(last_frame) = self.svg_renders.len().checked_sub(1) {
+            if selected.last() != Some(&last_frame) {
+                selected.push(last_frame);
+            }
+        }
+
+        selected
+    }
+
+    pub fn render_to_video(
+        self,
+        output_name: &str,
+        output_dir: &str,
+        framerate: f64,
+        every_n_frames: usize,
+        make_gif: bool,
+    ) {
+        self.render_to_video_with_selection(
+            output_name,
+            output_dir,
+            framerate,
+            FrameSelection::EveryN(every_n_frames),
+            make_gif,
+        );
+    }
+
+    pub fn render_to_video_with_selection(
+        self,
+        output_name: &str,
+        output_dir: &str,
+        framerate: f64,
+        selection: FrameSelection,
+        make_gif: bool,
+    ) {
+        let formats: &[VideoFormat] = if make_gif {
+            &[VideoFormat::Mp4, VideoFormat::Gif]
+        } else {
+            &[VideoFormat::Mp4]
+        };
+        self.render_to_video_with_formats(output_name, output_dir, framerate, selection, formats);
+    }
+
+    // the frames are converted to png once and encoded into every format
+    pub fn render_to_video_with_formats(
+        self,
+        output_name: &str,
+        output_dir: &str,
+        framerate: f64,
+        selection: FrameSelection,
+        formats: &[VideoFormat],
+    ) {
+        let dir = tempdir().unwrap();
+        let frame_dir = dir.path().join("frames");
+        std::fs::create_dir(&frame_dir).unwrap();
+
+        // number the selected frames sequentially to not confuse ffmpeg
+        let mut input_frames_svg_paths_renumbered = Vec::new();
+        for (sequence_number, frame_number) in
+            self.selected_frames(selection).into_iter().enumerate()
+        {
+            let svg = &self.svg_renders[frame_number];
+            let frame_fp = frame_dir.join(format!("frame_{}.svg", sequence_number));
+            std::fs::write(&frame_fp, svg).expect("Unable to write file");
+            input_frames_svg_paths_renumbered.push(frame_fp);
+        }
+
+        // convert the frames to pngs
+        input_frames_svg_paths_renumbered
+            .par_iter()
+            .for_each(|svg_fp| {
+                let png_fp = svg_fp.with_extension("png");
+                println!("Converting {:?} to {:?} ... ", svg_fp, png_fp);
+                svg_to_png(svg_fp, &png_fp);
+            });
+
+        if formats.contains(&VideoFormat::Mp4) {
+            // use ffmpeg to convert the frames to a video
+            let mut ffmpeg_cmd = Command::new("ffmpeg");
+            ffmpeg_cmd.arg("-y");
+            ffmpeg_cmd.arg("-framerate");
+            ffmpeg_cmd.arg(format!("{}", framerate));
+            ffmpeg_cmd.arg("-i");
+            ffmpeg_cmd.arg(frame_dir.join("frame_%d.png").to_str().unwrap());
+            ffmpeg_cmd.arg("-c:v");
+            ffmpeg_cmd.arg("libx264");
+            ffmpeg_cmd.arg("-pix_fmt");
+            ffmpeg_cmd.arg("yuv420p");
+            ffmpeg_cmd.arg(format!("{}/{}.mp4", output_dir, output_name));
+
+            let child = ffmpeg_cmd.spawn().expect("failed to execute ffmpeg");
+            child.wait_with_output().expect("failed to wait on ffmpeg");
+        }
+
+        if formats.contains(&VideoFormat::Gif) {
+            // use ffmpeg to convert the frames to a gif
+            let mut ffmpeg_cmd = Command::new("ffmpeg");
+            ffmpeg_cmd.arg("-y");
+            ffmpeg_cmd.arg("-framerate");
+            ffmpeg_cmd.arg(format!("{}", framerate));
+            ffmpeg_cmd.arg("-i");
+            ffmpeg_cmd.arg(frame_dir.join("frame_%d.png").to_str().unwrap());
+
+            // Optimize gif size using rescaling and color pallet reduction
+            ffmpeg_cmd.arg("-filter_complex");
+            ffmpeg_cmd.arg("scale=iw/2:-1,split [a][b];[a] palettegen=stats_mode=diff:max_colors=32[p]; [b][p] paletteuse=dither=bayer");
+
+            ffmpeg_cmd.arg(format!("{}/{}.gif", output_dir, output_name));
+
+            let child = ffmpeg_cmd.spawn().expect("failed to execute ffmpeg");
+            child.wait_with_output().expect("failed to wait on ffmpeg");
+        }
+
+        if formats.contains(&VideoFormat::Apng) {
+            // use ffmpeg's apng encoder, full size and full color, looping forever
+            let mut ffmpeg_cmd = Command::new("ffmpeg");
+            ffmpeg_cmd.arg("-y");
+            ffmpeg_cmd.arg("-framerate");
+            ffmpeg_cmd.arg(format!("{}", framerate));
+            ffmpeg_cmd.arg("-i");
+            ffmpeg_cmd.arg(frame_dir.join("frame_%d.png").to_str().unwrap());
+            ffmpeg_cmd.arg("-c:v");
+            ffmpeg_cmd.arg("apng");
+            ffmpeg_cmd.arg("-plays");
+            ffmpeg_cmd.arg("0");
+            ffmpeg_cmd.arg("-f");
+            ffmpeg_cmd.arg("apng");
+            ffmpeg_cmd.arg(format!("{}/{}.apng", output_dir, output_name));
+
+            let child = ffmpeg_cmd.spawn().expect("failed to execute ffmpeg");
+            child.wait_with_output().expect("failed to wait on ffmpeg");
+        }
+    }
+}
+
+// rasterize a frame in process, 800 pixels on its longer side on a white
+// background, which skips starting a process per frame
+#[cfg(feature = "resvg")]
+fn svg_to_png(svg_fp: &std::path::Path, png_fp: &std::path::Path) {
+    use resvg::{tiny_skia, usvg};
+
+    let svg = std::fs::read(svg_fp).expect("Unable to read file");
+    let tree = usvg::Tree::from_data(&svg, &usvg::Options::default()).expect("invalid frame svg");
+    let size = tree.size();
+    let scale = 800.0 / size.width().max(size.height());
+    // libx264 with yuv420p only takes even frame sizes
+    let even_pixels = |length: f32| ((length * scale / 2.0).round() as u32 * 2).max(2);
+    let mut pixmap = tiny_skia::Pixmap::new(even_pixels(size.width()), even_pixels(size.height()))
+        .expect("frame has no area");
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.save_png(png_fp).expect("Unable to write file");
+}
+
+// without the resvg feature every frame goes through imagemagick
+#[cfg(not(feature = "resvg"))]
+fn svg_to_png(svg_fp: &std::path::Path, png_fp: &std::path::Path) {
+    let _output: std::process::Output = std::process::Command::new("magick")
+        .arg("convert")
+        .arg("-size")
+        .arg("800x800")
+        .arg(svg_fp)
+        .arg(png_fp)
+        .output()
+        .expect("failed to execute magick");
+}
+
+// snapshot of the placer state handed to the progress callback after every step
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressInfo {
+    pub step: u32,
+    pub n_steps: u32,
+    pub cost: f32,
+    pub delta: f32,
+    pub temperature: f32,
+    // fraction of the steps so far that changed the current solution
+    pub acceptance_ratio: f32,
+}
+
+// progress callback used by fast_sa_placer when verbose is set
+pub fn print_progress(info: ProgressInfo) {
+    if info.step.is_multiple_of(10) {
+        println!("Current Itteration: {:?}", info.step);
+        println!("Delta Cost: {:?}", info.delta);
+        println!("Current Cost: {:?}", info.cost);
+    }
+}
+
+// how often an action was tried, how often it produced a step's best
+// neighbor and what came of it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ActionStats {
+    // neighbors the action produced, whether or not they won their step
+    pub proposed: u32,
+    // steps whose best neighbor came from the action
+    pub selected: u32,
+    pub accepted: u32,
+    // cost decrease summed over the accepted steps, uphill moves taken at
+    // temperature count against it
+    pub total_improvement: f32,
+}
+
+pub struct PlacerOutput<'a> {
+    pub initial_solution: PlacementSolution<'a>,
+    pub final_solution: PlacementSolution<'a>,
+    // lowest cost placement of the run and its cost, final_solution is moved
+    // onto it at the end only with PlacerConfig::restart set
+    pub best_solution: PlacementSolution<'a>,
+    pub best_cost: f32,
+    pub x_steps: Vec<u32>,
+    pub y_cost: Vec<f32>,
+    // y_cost divided by normalization's divisor, lines up with x_steps
+    pub y_cost_normalized: Vec<f32>,
+    pub normalization: CostNormalization,
+    // n_neighbors the schedule picked at each step, lines up with x_steps
+    pub n_neighbors_per_step: Vec<usize>,
+    // temperature each step ran at, lines up with x_steps
+    pub temperature_per_step: Vec<f32>,
+    // farthest any node moved at each step, 0 for rejected steps, lines up
+    // with x_steps when PlacerConfig::record_move_distances is set and is
+    // empty otherwise
+    pub move_distances: Vec<u32>,
+    // each term of the objective at every step keyed by the names
+    // Placer::cost_terms gives them, lines up with x_steps and adds up to y_cost
+    pub cost_components: FxHashMap<String, Vec<f32>>,
+    // lowest cost seen up to and including each step, lines up with x_steps
+    pub best_cost_per_step: Vec<f32>,
+    // steps at which PlacerConfig::restart reset the run to its best
+    // solution, the last one at n_steps when the run was moved onto it at the end
+    pub restarts: Vec<u32>,
+    pub reheats: Vec<ReheatEvent>,
+    // steps run at each temperature, see CoolingSchedule
+    pub moves_per_temperature: u32,
+    // keyed by the PlacementAction's name, proposed sums to the number of
+    // neighbors tried, selected to the number of steps and accepted to the
+    // number of accepted steps
+    pub action_stats: FxHashMap<String, ActionStats>,
+    pub renderer: Option<Renderer>,
+    // metrics of the final solution
+    pub metrics: PlacementMetrics,
+    pub final_temperature: f32,
+    rng: StdRng,
+}
+
+// columns of the history csv written by PlacerOutput::write_history_csv and
+// Placer::run_streaming
+pub const HISTORY_CSV_HEADER: [&str; 3] = ["step", "obj_fn_value", "temperature"];
+
+impl<'a> PlacerOutput<'a> {
+    // the cost and temperature of every step, one row per entry of x_steps
+    pub fn write_history_csv<W: std::io::Write>(
+        &self,
+        history: &mut csv::Writer<W>,
+    ) -> Result<(), csv::Error> {
+        history.write_record(HISTORY_CSV_HEADER)?;
+        for ((step, cost), temperature) in self
+            .x_steps
+            .iter()
+            .zip(&self.y_cost)
+            .zip(&self.temperature_per_step)
+        {
+            history.write_record(&[step.to_string(), cost.to_string(), temperature.to_string()])?;
+        }
+        history.flush()?;
+        Ok(())
+    }
+
+    // where the run stopped, to pick it back up with fast_sa_placer_from
+    pub fn into_state(self) -> PlacerState<'a> {
+        PlacerState {
+            solution: self.final_solution,
+            temperature: self.final_temperature,
+            rng: self.rng,
+        }
+    }
+}
+
+// everything a run needs to continue where an earlier one stopped instead of
+// starting cold, for incremental flows placing a slightly edited netlist again
+#[derive(Debug, Clone)]
+pub struct PlacerState<'a> {
+    pub solution: PlacementSolution<'a>,
+    pub temperature: f32,
+    pub rng: StdRng,
+}
+
+impl<'a> PlacerState<'a> {
+    // carry the placement over to an edited netlist
+    // nodes are matched by NetlistNode (id and macro type), so an edit has to
+    // keep the ids of the nodes it doesn't touch, a node whose id changed is
+    // treated as removed and re-added and loses its location
+    // removed nodes are dropped and new nodes are legalized onto free sites,
+    // relative groups are not carried over
+    pub fn with_netlist(self, netlist: &'a NetlistGraph) -> Result<Self, LegalizeError> {
+        let mut solution = PlacementSolution::new(self.solution.layout, netlist);
+        solution.footprints = self.solution.footprints;
+        solution.region = self.solution.region;
+        solution.min_spacing = self.solution.min_spacing;
+        solution.delay_multipliers = self.solution.delay_multipliers;
+        solution.soft_regions = self.solution.soft_regions;
+        solution.forbidden = self.solution.forbidden;
+        let nodes = netlist.graph.node_weights().collect::<FxHashSet<_>>();
+        for (node, location) in self.solution.solution_map {
+            if nodes.contains(&node) {
+                solution.solution_map.insert(node, location);
+            }
+        }
+        solution.locked = self
+            .solution
+            .locked
+            .into_iter()
+            .filter(|node| nodes.contains(node))
+            .collect();
+        solution.legalize()?;
+
+        Ok(PlacerState {
+            solution,
+            temperature: self.temperature,
+            rng: self.rng,
+        })
+    }
+}
+
+// how the number of neighbors explored per step changes over a run
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum NeighborSchedule {
+    // always explore n_neighbors
+    #[default]
+    Constant,
+    // start at n_neighbors and narrow linearly down to min as the temperature
+    // cools, with annealing disabled it narrows over the steps instead
+    Cooling {
+        min: usize,
+    },
+}
+
+// how many steps run at each temperature before it is lowered by cooling_rate
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum CoolingSchedule {
+    // cool after every step
+    #[default]
+    EveryStep,
+    // VPR's inner loop, inner_num * n_blocks^(4/3) steps per temperature so
+    // the effort spent at each temperature grows with the design
+    // n_steps still caps the total number of steps
+    Vpr {
+        inner_num: f32,
+    },
+}
+
+impl CoolingSchedule {
+    pub fn moves_per_temperature(&self, n_blocks: usize) -> u32 {
+        match self {
+            CoolingSchedule::EveryStep => 1,
+            // n * cbrt(n) rather than powf(4 / 3) so a cube like 8 blocks
+            // gives exactly 16 and doesn't round up to 17
+            CoolingSchedule::Vpr { inner_num } => {
+                let n_blocks = n_blocks as f64;
+                ((*inner_num as f64 * n_blocks * n_blocks.cbrt()).ceil() as u32).max(1)
+            }
+        }
+    }
+}
+
+// raise the temperature again when the run stagnates before reaching a target cost
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReheatConfig {
+    // acceptance ratio is measured over windows of this many steps, only
+    // accepted moves that changed the cost count toward it
+    pub window: u32,
+    // a window accepting fewer moves than this fraction counts as stagnating
+    pub acceptance_threshold: f32,
+    // only reheat while the cost is above this
+    pub target_cost: f32,
+    // the temperature is multiplied by this on a reheat
+    pub factor: f32,
+    pub max_reheats: u32,
+}
+
+impl Default for ReheatConfig {
+    fn default() -> Self {
+        Self {
+            window: 50,
+            acceptance_threshold: 0.02,
+            target_cost: 0.0,
+            factor: 10.0,
+            max_reheats: 5,
+        }
+    }
+}
+
+// basin hopping: snap back to the best placement seen so far when the run
+// goes too long without beating it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RestartConfig {
+    // steps without a new best cost before the current solution is reset to the best
+    pub patience: u32,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self { patience: 200 }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReheatEvent {
+    pub step: u32,
+    pub cost: f32,
+    pub temperature_before: f32,
+    pub temperature_after: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlacerConfig {
+    pub n_steps: u32,
+    // number of neighbors to explore at each step, at least 1, every neighbor
+    // is a copy of the whole current solution that gets costed, so a step
+    // costs about n_neighbors clones and cost evaluations of the design
+    pub n_neighbors: usize,
+    pub neighbor_schedule: NeighborSchedule,
+    // starting temperature for accepting uphill moves with probability
+    // exp(-delta / T), 0.0 only ever accepts improving moves
+    pub initial_temperature: f32,
+    // the temperature is multiplied by this every time it cools
+    pub cooling_rate: f32,
+    pub cooling_schedule: CoolingSchedule,
+    // None never reheats, a run without a temperature can't be reheated either
+    pub reheat: Option<ReheatConfig>,
+    // None never resets to the best solution, with it set the run also ends
+    // on the best solution seen
+    // fields from here on marked serde(default) came after the first
+    // experiment records, so records written before them still load
+    #[serde(default)]
+    pub restart: Option<RestartConfig>,
+    pub verbose: bool,
+    pub render: bool,
+    // weight of the longest net in the objective
+    pub beta: f32,
+    // weight of the critical path delay in the objective, the placer minimizes
+    // weighted hpwl + beta * max_net + lambda * timing
+    pub lambda: f32,
+    // how each net's weight in the hpwl term is picked
+    pub auto_weight: WeightPolicy,
+    // scale of the x and y spans of every net in the hpwl term, e.g. a higher
+    // y_weight when vertical routing is scarcer, see weighted_hpwl_xy
+    #[serde(default = "default_axis_weight")]
+    pub x_weight: f32,
+    #[serde(default = "default_axis_weight")]
+    pub y_weight: f32,
+    // weight of PlacementSolution::cost_column_crossing in the objective
+    pub column_crossing_weight: f32,
+    // weight of PlacementSolution::cost_hull_area in the objective
+    #[serde(default)]
+    pub hull_area_weight: f32,
+    // extra weight on the hpwl of nets touching an IO, pulling logic toward
+    // its pads, it starts at this value and fades linearly to 0 by the last step
+    pub pad_attraction: f32,
+    // seed for the placer's rng, runs with the same seed and initial solution
+    // make the same moves, None seeds from entropy
+    pub seed: Option<u64>,
+    // chance that a step also tries PlacementAction::Teleport, in [0, 1]
+    pub teleport_probability: f64,
+    // sites PlacementAction::MoveBiased samples, 0 leaves the action out
+    #[serde(default)]
+    pub biased_move_k: usize,
+    // chance a step whose best neighbor has exactly the current cost is
+    // accepted once the temperature is 0 (with a temperature they always
+    // are), letting the placement wander across flat stretches of the
+    // cost, 0.0 never accepts them and 1.0 always does
+    #[serde(default)]
+    pub equal_cost_acceptance: f64,
+    // how PlacerOutput::y_cost_normalized is normalized
+    pub normalization: CostNormalization,
+    // validate the solution after every accepted move and panic with the
+    // violations if it is illegal, catches move generators that break
+    // legality at the price of a full validation per accepted step
+    pub check_legality: bool,
+    // fill in PlacerOutput::move_distances, which diffs the placement on every
+    // accepted step
+    #[serde(default)]
+    pub record_move_distances: bool,
+}
+
+fn default_axis_weight() -> f32 {
+    1.0
+}
+
+impl Default for PlacerConfig {
+    fn default() -> Self {
+        Self {
+            n_steps: 1000,
+            n_neighbors: 16,
+            neighbor_schedule: NeighborSchedule::Constant,
+            initial_temperature: 0.0,
+            cooling_rate: 0.99,
+            cooling_schedule: CoolingSchedule::EveryStep,
+            reheat: None,
+            restart: None,
+            verbose: false,
+            render: false,
+            beta: 0.0,
+            lambda: 0.0,
+            auto_weight: WeightPolicy::Manual,
+            x_weight: 1.0,
+            y_weight: 1.0,
+            column_crossing_weight: 0.0,
+            hull_area_weight: 0.0,
+            pad_attraction: 0.0,
+            seed: None,
+            teleport_probability: 0.02,
+            biased_move_k: 0,
+            equal_cost_acceptance: 0.0,
+            normalization: CostNormalization::PerNet,
+            check_legality: false,
+            record_move_distances: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlacerConfigError {
+    // a chance the placer rolls every step is outside [0, 1]
+    ProbabilityOutOfRange { field: &'static str, value: f64 },
+}
+
+impl std::fmt::Display for PlacerConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlacerConfigError::ProbabilityOutOfRange { field, value } => {
+                write!(f, "{} must be between 0 and 1 but is {}", field, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlacerConfigError {}
+
+impl PlacerConfig {
+    // check the fields the placer can't run with, Placer::new panics with
+    // this error so check configs read from a file first
+    pub fn validate(&self) -> Result<(), PlacerConfigError> {
+        for (field, value) in [
+            ("teleport_probability", self.teleport_probability),
+            ("equal_cost_acceptance", self.equal_cost_acceptance),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(PlacerConfigError::ProbabilityOutOfRange { field, value });
+            }
+        }
+        Ok(())
+    }
+}
+
+// what happened during a single placer step
+#[derive(Debug, Clone, Copy)]
+pub struct StepResult {
+    pub step: u32,
+    // action that produced the best neighbor
+    pub action: PlacementAction,
+    // cost change of the best neighbor, whether or not it was accepted
+    pub delta: f32,
+    pub accepted: bool,
+    // neighbors explored during the step
+    pub n_neighbors: usize,
+    // temperature the step was run at
+    pub temperature: f32,
+    // cost of the current solution after the step
+    pub cost: f32,
+    // farthest any node moved, 0 when the step wasn't accepted, only
+    // measured with PlacerConfig::record_move_distances
+    pub move_distance: Option<u32>,
+}
+
+// the annealing loop broken into single steps so it can be driven from outside
+pub struct Placer<'a> {
+    pub config: PlacerConfig,
+    pub initial_solution: PlacementSolution<'a>,
+    pub current_solution: PlacementSolution<'a>,
+    pub step_count: u32,
+    pub n_accepted: u32,
+    pub temperature: f32,
+    pub reheats: Vec<ReheatEvent>,
+    // lowest cost solution seen so far and its cost, costs from different
+    // steps only compare exactly when no term depends on the step (see
+    // pad_attraction)
+    pub best_solution: PlacementSolution<'a>,
+    pub best_cost: f32,
+    // steps at which the current solution was reset to best_solution
+    pub restarts: Vec<u32>,
+    // per action, keyed by its Debug name, counted by every step
+    pub action_stats: FxHashMap<String, ActionStats>,
+    steps_since_best: u32,
+    // accepted moves and steps in the current reheat window
+    window_accepted: u32,
+    window_steps: u32,
+    // only built when lambda is set
+    timing_graph: Option<TimingGraph>,
+    // only looked up when column_crossing_weight is set
+    special_columns: Option<Vec<u32>>,
+    // replaces every term of the built in objective, see with_cost_function
+    cost_function: Option<&'a dyn CostFunction>,
+    moves_per_temperature: u32,
+    rng: StdRng,
+}
+
+impl<'a> Placer<'a> {
+    const ACTIONS: &'static [PlacementAction] = &[
+        PlacementAction::Move,
+        PlacementAction::Swap,
+        PlacementAction::MoveDirected,
+    ];
+
+    pub fn new(solution: PlacementSolution<'a>, config: PlacerConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let temperature = config.initial_temperature;
+        Self::with_rng(solution, config, temperature, rng)
+    }
+
+    // continue from an earlier run's state, its temperature and rng replace
+    // config.initial_temperature and config.seed
+    pub fn from_state(state: PlacerState<'a>, config: PlacerConfig) -> Self {
+        Self::with_rng(state.solution, config, state.temperature, state.rng)
+    }
+
+    fn with_rng(
+        mut solution: PlacementSolution<'a>,
+        config: PlacerConfig,
+        temperature: f32,
+        rng: StdRng,
+    ) -> Self {
+        if let Err(err) = config.validate() {
+            panic!("invalid placer config: {}", err);
+        }
+        if config.auto_weight != WeightPolicy::Manual {
+            solution.net_weights = Some(Arc::new(config.auto_weight.net_weights(solution.netlist)));
+        }
+        solution.enable_net_cache();
+
+        let timing_graph = if config.lambda != 0.0 {
+            Some(TimingGraph::new(solution.netlist))
+        } else {
+            None
+        };
+
+        let special_columns = if config.column_crossing_weight != 0.0 {
+            Some(solution.layout.special_columns())
+        } else {
+            None
+        };
+
+        let moves_per_temperature = config
+            .cooling_schedule
+            .moves_per_temperature(solution.netlist.graph.node_count());
+
+        let mut placer = Self {
+            rng,
+            timing_graph,
+            special_columns,
+            cost_function: None,
+            moves_per_temperature,
+            temperature,
+            config,
+            initial_solution: solution.clone(),
+            best_solution: solution.clone(),
+            best_cost: f32::INFINITY,
+            restarts: Vec::new(),
+            action_stats: FxHashMap::default(),
+            steps_since_best: 0,
+            current_solution: solution,
+            step_count: 0,
+            n_accepted: 0,
+            reheats: Vec::new(),
+            window_accepted: 0,
+            window_steps: 0,
+        };
+        placer.best_cost = placer.cost(&placer.current_solution);
+        placer
+    }
+
+    // minimize cost_function instead of the objective the config describes,
+    // the config's cost weights are then ignored and cost_terms gives the
+    // whole cost as a single "custom" term
+    pub fn with_cost_function(mut self, cost_function: &'a dyn CostFunction) -> Self {
+        self.cost_function = Some(cost_function);
+        self.best_cost = self.cost(&self.best_solution);
+        self
+    }
+
+    pub fn cost(&self, solution: &PlacementSolution) -> f32 {
+        let mut cost = 0.0;
+        self.cost_terms(solution, |_, term| cost += term);
+        cost
+    }
+
+    // change in cost from the current solution, whose cost is current_cost,
+    // to the neighbor moved
+    fn delta(&self, moved: &PlacementSolution, current_cost: f32) -> f32 {
+        match self.cost_function {
+            Some(cost_function) => cost_function.delta(&self.current_solution, current_cost, moved),
+            None => self.cost(moved) - current_cost,
+        }
+    }
+
+    // every weighted term of the objective in the order cost adds them up,
+    // named as in PlacerOutput::cost_components, terms whose weight is off in
+    // the config are skipped, the same ones for every step of a run
+    pub fn cost_terms<F: FnMut(&'static str, f32)>(&self, solution: &PlacementSolution, mut f: F) {
+        if let Some(cost_function) = self.cost_function {
+            f("custom", cost_function.cost(solution));
+            return;
+        }
+        let hpwl_cost = HpwlCost {
+            x_weight: self.config.x_weight,
+            y_weight: self.config.y_weight,
+        };
+        f("hpwl", hpwl_cost.cost(solution));
+        if self.config.beta != 0.0 {
+            f("max_net", self.config.beta * solution.cost_max_net());
+        }
+        if let Some(timing_graph) = &self.timing_graph {
+            f(
+                "timing",
+                self.config.lambda * timing_graph.critical_path_delay(solution),
+            );
+        }
+        if let Some(special_columns) = &self.special_columns {
+            f(
+                "column_crossing",
+                self.config.column_crossing_weight
+                    * solution.cost_column_crossing_with(special_columns),
+            );
+        }
+        if self.config.hull_area_weight != 0.0 {
+            f(
+                "hull_area",
+                self.config.hull_area_weight * solution.cost_hull_area(),
+            );
+        }
+        if !solution.soft_regions.is_empty() {
+            f("soft_regions", solution.cost_soft_regions());
+        }
+        if self.config.pad_attraction != 0.0 {
+            f(
+                "pad_attraction",
+                self.pad_attraction() * solution.io_net_hpwl(),
+            );
+        }
+    }
+
+    // put another placement in place of the current one, e.g. one traded in
+    // from another chain, and return the old one, best_solution picks it up
+    // when it beats the best seen so far
+    pub fn replace_current_solution(
+        &mut self,
+        solution: PlacementSolution<'a>,
+    ) -> PlacementSolution<'a> {
+        let old_solution = std::mem::replace(&mut self.current_solution, solution);
+        self.update_best(self.cost(&self.current_solution));
+        old_solution
+    }
+
+    // the pad attraction weight at the current step
+    pub fn pad_attraction(&self) -> f32 {
+        let remaining = 1.0 - self.step_count as f32 / self.config.n_steps.max(1) as f32;
+        self.config.pad_attraction * remaining.max(0.0)
+    }
+
+    // number of neighbors the schedule asks for at the current temperature / step
+    pub fn n_neighbors(&self) -> usize {
+        match self.config.neighbor_schedule {
+            NeighborSchedule::Constant => self.config.n_neighbors.max(1),
+            NeighborSchedule::Cooling { min } => {
+                let min = min.clamp(1, self.config.n_neighbors.max(1));
+                // how much of the run is still ahead, 1.0 at the start and 0.0 when cold
+                let remaining = if self.config.initial_temperature > 0.0 {
+                    self.temperature / self.config.initial_temperature
+                } else {
+                    1.0 - self.step_count as f32 / self.config.n_steps.max(1) as f32
+                };
+                let extra =
+                    self.config.n_neighbors.saturating_sub(min) as f32 * remaining.clamp(0.0, 1.0);
+                min + extra.round() as usize
+            }
+        }
+    }
+
+    pub fn step(&mut self) -> StepResult {
+        let current_cost = self.cost(&self.current_solution);
+        let n_neighbors = self.n_neighbors();
+
+        let mut action_kinds = Self::ACTIONS.to_vec();
+        if self.config.biased_move_k > 0 {
+            action_kinds.push(PlacementAction::MoveBiased(self.config.biased_move_k));
+        }
+
+        // randomly select actions, with replacement so n_neighbors can be
+        // larger than the number of action kinds
+        let mut actions: Vec<_> = (0..n_neighbors)
+            .map(|_| *action_kinds.choose(&mut self.rng).unwrap())
+            .collect();
+
+        // teleport always picks the same node for a given placement so it is
+        // only tried now and then rather than every step
+        if self.rng.gen_bool(self.config.teleport_probability) {
+            actions.push(PlacementAction::Teleport);
+        }
+        for action in &actions {
+            self.action_stats
+                .entry(format!("{:?}", action))
+                .or_default()
+                .proposed += 1;
+        }
+
+        let (best_action, best_solution, delta) = actions
+            .into_iter()
+            .map(|action| {
+                let mut new_solution = self.current_solution.clone();
+                new_solution.action_with_rng(action, &mut self.rng);
+                let delta = self.delta(&new_solution, current_cost);
+                (action, new_solution, delta)
+            })
+            .min_by(|(_, _, delta1), (_, _, delta2)| delta1.partial_cmp(delta2).unwrap())
+            .unwrap();
+
+        let best_cost = current_cost + delta;
+        let temperature = self.temperature;
+        // at temperature a move that leaves the cost unchanged is always
+        // accepted since exp(0) = 1, once cold only equal_cost_acceptance
+        // lets such moves through
+        let accepted = delta < 0.0
+            || (delta == 0.0
+                && self.config.equal_cost_acceptance > 0.0
+                && self.rng.gen_bool(self.config.equal_cost_acceptance))
+            || (temperature > 0.0 && self.rng.gen::<f32>() < (-delta / temperature).exp());
+        let move_distance = self.config.record_move_distances.then(|| {
+            if accepted {
+                self.current_solution.max_displacement(&best_solution)
+            } else {
+                0
+            }
+        });
+        if accepted {
+            self.current_solution = best_solution;
+            self.n_accepted += 1;
+
+            if self.config.check_legality {
+                let violations = self.current_solution.validate(false);
+                assert!(
+                    violations.is_empty(),
+                    "{:?} at step {} left an illegal placement: {}",
+                    best_action,
+                    self.step_count,
+                    violations
+                        .iter()
+                        .map(|violation| violation.to_string())
+                        .join(", ")
+                );
+            }
+        }
+
+        let stats = self
+            .action_stats
+            .entry(format!("{:?}", best_action))
+            .or_default();
+        stats.selected += 1;
+        if accepted {
+            stats.accepted += 1;
+            stats.total_improvement -= delta;
+        }
+
+        let result = StepResult {
+            step: self.step_count,
+            action: best_action,
+            delta,
+            accepted,
+            n_neighbors,
+            temperature,
+            cost: if accepted { best_cost } else { current_cost },
+            move_distance,
+        };
+
+        self.step_count += 1;
+        if self.step_count.is_multiple_of(self.moves_per_temperature) {
+            self.temperature *= self.config.cooling_rate;
+        }
+        // moves that leave the cost unchanged (often ones that found nowhere
+        // better to go) don't count so stagnation shows in the reheat window
+        self.check_reheat(accepted && delta != 0.0, result.cost);
+        self.check_restart(result.cost);
+
+        result
+    }
+
+    // the one place best_solution changes, called with the cost of the
+    // current solution whenever that changes, it copies the solution on every
+    // new best, restart or not, since the run always returns it
+    fn update_best(&mut self, cost: f32) -> bool {
+        if cost < self.best_cost {
+            self.best_cost = cost;
+            self.best_solution = self.current_solution.clone();
+            self.steps_since_best = 0;
+            return true;
+        }
+        false
+    }
+
+    fn check_restart(&mut self, cost: f32) {
+        if self.update_best(cost) {
+            return;
+        }
+
+        let restart = match self.config.restart {
+            Some(restart) => restart,
+            None => return,
+        };
+        self.steps_since_best += 1;
+        if self.steps_since_best >= restart.patience.max(1) && cost > self.best_cost {
+            self.current_solution = self.best_solution.clone();
+            self.restarts.push(self.step_count);
+            self.steps_since_best = 0;
+        }
+    }
+
+    fn check_reheat(&mut self, accepted: bool, cost: f32) {
+        let reheat = match self.config.reheat {
+            Some(reheat) => reheat,
+            None => return,
+        };
+
+        self.window_steps += 1;
+        if accepted {
+            self.window_accepted += 1;
+        }
+        if self.window_steps < reheat.window.max(1) {
+            return;
+        }
+
+        let acceptance_ratio = self.window_accepted as f32 / self.window_steps as f32;
+        self.window_steps = 0;
+        self.window_accepted = 0;
+
+        if acceptance_ratio < reheat.acceptance_threshold
+            && cost > reheat.target_cost
+            && self.temperature > 0.0
+            && (self.reheats.len() as u32) < reheat.max_reheats
+        {
+            let temperature_before = self.temperature;
+            self.temperature *= reheat.factor;
+            self.reheats.push(ReheatEvent {
+                step: self.step_count,
+                cost,
+                temperature_before,
+                temperature_after: self.temperature,
+            });
+        }
+    }
+
+    pub fn progress_info(&self, result: &StepResult) -> ProgressInfo {
+        ProgressInfo {
+            step: result.step,
+            n_steps: self.config.n_steps,
+            cost: result.cost,
+            delta: if result.accepted { result.delta } else { 0.0 },
+            temperature: result.temperature,
+            acceptance_ratio: self.n_accepted as f32 / self.step_count.max(1) as f32,
+        }
+    }
+
+    // run the remaining steps of the configured n_steps
+    pub fn run(self, progress: Option<&dyn Fn(ProgressInfo)>) -> PlacerOutput<'a> {
+        self.run_with_history::<std::io::Sink>(progress, None)
+            .expect("only writing the history can fail")
+    }
+
+    // run, but write a step, obj_fn_value, temperature row to history after
+    // every step instead of keeping the per-step vectors of PlacerOutput, which
+    // come back empty, so memory stays flat on very long runs, the rows match
+    // PlacerOutput::write_history_csv of a plain run
+    // the csv writer buffers rows and writes them out whenever its buffer
+    // fills, the history is flushed once the run finishes, rows still in the
+    // buffer are lost if the process dies before then
+    pub fn run_streaming<W: std::io::Write>(
+        self,
+        progress: Option<&dyn Fn(ProgressInfo)>,
+        history: &mut csv::Writer<W>,
+    ) -> Result<PlacerOutput<'a>, csv::Error> {
+        history.write_record(HISTORY_CSV_HEADER)?;
+        let output = self.run_with_history(progress, Some(&mut *history))?;
+        history.flush()?;
+        Ok(output)
+    }
+
+    fn run_with_history<W: std::io::Write>(
+        mut self,
+        progress: Option<&dyn Fn(ProgressInfo)>,
+        mut history: Option<&mut csv::Writer<W>>,
+    ) -> Result<PlacerOutput<'a>, csv::Error> {
+        let keep_history = history.is_none();
+        let mut renderer = Renderer::new();
+
+        let mut x_steps = Vec::new();
+        let mut y_cost = Vec::new();
+        let mut n_neighbors_per_step = Vec::new();
+        let mut temperature_per_step = Vec::new();
+        let mut move_distances = Vec::new();
+        let mut cost_components: FxHashMap<String, Vec<f32>> = FxHashMap::default();
+        let mut best_cost_per_step = Vec::new();
+
+        while self.step_count < self.config.n_steps {
+            let step = self.step_count;
+            // summed in the same order as cost so y_cost matches it exactly
+            let mut cost = 0.0;
+            self.cost_terms(&self.current_solution, |name, term| {
+                cost += term;
+                if !keep_history {
+                    return;
+                }
+                match cost_components.get_mut(name) {
+                    Some(values) => values.push(term),
+                    None => {
+                        cost_components.insert(name.to_string(), vec![term]);
+                    }
+                }
+            });
+            if self.config.render {
+                renderer.add_frame_with_cost(
+                    self.current_solution.render_svg(),
+                    self.cost(&self.current_solution),
+                );
+            }
+
+            let result = self.step();
+            match history.as_deref_mut() {
+                Some(history) => history.write_record(&[
+                    step.to_string(),
+                    cost.to_string(),
+                    result.temperature.to_string(),
+                ])?,
+                None => {
+                    x_steps.push(step);
+                    y_cost.push(cost);
+                    n_neighbors_per_step.push(result.n_neighbors);
+                    temperature_per_step.push(result.temperature);
+                    if let Some(move_distance) = result.move_distance {
+                        move_distances.push(move_distance);
+                    }
+                    best_cost_per_step.push(self.best_cost);
+                }
+            }
+
+            let info = self.progress_info(&result);
+            if self.config.verbose {
+                print_progress(info);
+            }
+            if let Some(progress) = progress {
+                progress(info);
+            }
+        }
+
+        if self.config.restart.is_some() && self.best_cost < self.cost(&self.current_solution) {
+            self.current_solution = self.best_solution.clone();
+            self.restarts.push(self.step_count);
+        }
+
+        if self.config.render {
+            renderer.add_frame_with_cost(
+                self.current_solution.render_svg(),
+                self.cost(&self.current_solution),
+            );
+        }
+
+        if self.config.verbose {
+            for (action, stats) in self
+                .action_stats
+                .iter()
+                .sorted_by_key(|(action, _)| *action)
+            {
+                println!(
+                    "{}: proposed {}, selected {}, accepted {}, improvement {}",
+                    action, stats.proposed, stats.selected, stats.accepted, stats.total_improvement
+                );
+            }
+        }
+
+        let divisor = self
+            .config
+            .normalization
+            .divisor(self.current_solution.netlist, self.current_solution.layout);
+        let y_cost_normalized = y_cost.iter().map(|cost| cost / divisor).collect();
+
+        Ok(PlacerOutput {
+            best_solution: self.best_solution,
+            best_cost: self.best_cost,
+            metrics: self
+                .current_solution
+                .metrics(self.config.beta, self.config.lambda),
+            initial_solution: self.initial_solution,
+            final_solution: self.current_solution,
+            x_steps,
+            y_cost,
+            y_cost_normalized,
+            normalization: self.config.normalization,
+            n_neighbors_per_step,
+            temperature_per_step,
+            move_distances,
+            cost_components,
+            best_cost_per_step,
+            restarts: self.restarts,
+            reheats: self.reheats,
+            moves_per_temperature: self.moves_per_temperature,
+            action_stats: self.action_stats,
+            renderer: if self.config.render {
+                Some(renderer)
+            } else {
+                None
+            },
+            final_temperature: self.temperature,
+            rng: self.rng,
+        })
+    }
 }
 
-pub fn gen_random_placement<'a>(
-    layout: &'a FPGALayout,
-    netlist: &'a NetlistGraph,
-) -> PlacementSolution<'a> {
-    let mut solution = PlacementSolution::new(layout, netlist);
+pub fn fast_sa_placer<'a>(
+    initial_solution: PlacementSolution<'a>,
+    n_steps: u32,
+    n_neighbors: usize, // number of neighbors to explore at each step
+    verbose: bool,
+    render: bool,
+    progress: Option<&dyn Fn(ProgressInfo)>,
+) -> PlacerOutput<'a> {
+    let config = PlacerConfig {
+        n_steps,
+        n_neighbors,
+        verbose,
+        render,
+        ..Default::default()
+    };
+
+    Placer::new(initial_solution, config).run(progress)
+}
+
+// fast_sa_placer warm started from PlacerOutput::into_state, use
+// PlacerState::with_netlist first when the netlist was edited in between
+pub fn fast_sa_placer_from<'a>(
+    state: PlacerState<'a>,
+    n_steps: u32,
+    n_neighbors: usize,
+    verbose: bool,
+    render: bool,
+    progress: Option<&dyn Fn(ProgressInfo)>,
+) -> PlacerOutput<'a> {
+    let config = PlacerConfig {
+        n_steps,
+        n_neighbors,
+        verbose,
+        render,
+        ..Default::default()
+    };
+
+    Placer::from_state(state, config).run(progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clb(id: u32) -> NetlistNode {
+        NetlistNode {
+            id,
+            macro_type: MacroType::CLB,
+        }
+    }
+
+    // n CLBs in a chain, node i and i + 1 sharing a two pin net
+    fn chain_netlist(n: u32) -> NetlistGraph {
+        let nodes = (0..n).map(clb).collect_vec();
+        let nets = nodes.windows(2).map(|pair| pair.to_vec()).collect_vec();
+        NetlistGraph::from_nets(&nodes, &nets)
+    }
+
+    #[test]
+    fn reoptimize_leaves_unaffected_nodes_in_place() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let netlist = chain_netlist(40);
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        solution
+            .lock_node(clb(30), solution.solution_map[&clb(30)])
+            .unwrap();
+        let before = solution.solution_map.clone();
+
+        let config = PlacerConfig {
+            n_steps: 200,
+            initial_temperature: 10.0,
+            seed: Some(2),
+            ..Default::default()
+        };
+        solution.reoptimize(&[clb(5)], config).unwrap();
+
+        assert!(solution.valid());
+        for (node, location) in &before {
+            if ![4, 5, 6].contains(&node.id) {
+                assert_eq!(solution.solution_map[node], *location, "node {}", node.id);
+            }
+        }
+        assert_eq!(solution.locked, FxHashSet::from_iter([clb(30)]));
+    }
+
+    #[test]
+    fn cold_reoptimize_never_raises_the_cost() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let netlist = chain_netlist(40);
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let before = solution.cost_hpwl();
+
+        let config = PlacerConfig {
+            n_steps: 300,
+            initial_temperature: 0.0,
+            seed: Some(4),
+            ..Default::default()
+        };
+        solution.reoptimize(&[clb(10), clb(20)], config).unwrap();
+        assert!(solution.valid());
+        assert!(
+            solution.cost_hpwl() < before,
+            "{} vs {}",
+            solution.cost_hpwl(),
+            before
+        );
+
+        // the running totals match a placement costed from scratch
+        let mut uncached = solution.clone();
+        uncached.net_cache = None;
+        assert_eq!(solution.cost_hpwl(), uncached.cost_hpwl());
+        assert_eq!(solution.weighted_hpwl(), uncached.weighted_hpwl());
+    }
+
+    #[test]
+    fn centroid_weights_neighbors_by_net() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let mut netlist = chain_netlist(3);
+        netlist.set_net_weight(1, 3.0);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(0), FPGALayoutCoordinate::new(1, 1));
+        solution.place_node(clb(1), FPGALayoutCoordinate::new(3, 1));
+        solution.place_node(clb(2), FPGALayoutCoordinate::new(5, 5));
+
+        assert_eq!(
+            solution.centroid(&clb(1), CentroidMode::Neighbors),
+            Some((4, 4))
+        );
+        assert_eq!(
+            netlist.neighbor_nets(&clb(1)),
+            vec![(clb(2), 1), (clb(0), 0)]
+        );
+    }
+
+    #[test]
+    fn directed_move_targets_the_neighbor_centroid() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..4).map(clb).collect_vec();
+        let netlist =
+            NetlistGraph::from_nets(&nodes, &[vec![clb(0), clb(1)], vec![clb(2), clb(3)]]);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        let site = FPGALayoutCoordinate::new;
+        for (node, (x, y)) in nodes.iter().zip([(5, 5), (2, 1), (8, 8), (8, 6)]) {
+            solution.place_node(*node, site(x, y));
+        }
+        // node 0 is the only one the move can pick
+        solution.locked.extend([clb(1), clb(2), clb(3)]);
+
+        // the global mean sits on node 0 itself, far from its only neighbor
+        assert_eq!(
+            solution.centroid(&clb(0), CentroidMode::Global),
+            Some((5, 5))
+        );
+        assert_eq!(
+            solution.centroid(&clb(0), CentroidMode::Neighbors),
+            Some((2, 1))
+        );
+
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut global = solution.clone();
+        global.action_move_directed_toward_with_rng(CentroidMode::Global, &mut rng);
+        assert_eq!(global.solution_map[&clb(0)], site(5, 5));
+
+        let mut local = solution.clone();
+        local.action_move_directed_toward_with_rng(CentroidMode::Neighbors, &mut rng);
+        assert_eq!(
+            local.solution_map[&clb(0)].manhattan_distance(&site(2, 1)),
+            1
+        );
+        assert!(local.cost_hpwl() < solution.cost_hpwl());
+    }
+
+    #[test]
+    fn cost_bb_simd_matches_the_scalar_loop() {
+        let layout = build_simple_fpga_layout(12, 12);
+        // 20 edges, two full vectors and a scalar tail of 4
+        let nodes = (0..21).map(clb).collect_vec();
+        let nets = nodes.windows(2).map(|pair| pair.to_vec()).collect_vec();
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(18);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        assert_eq!(netlist.graph.edge_count(), 20);
+        assert_eq!(solution.cost_bb_simd(), solution.cost_bb());
+        assert_eq!(solution.cost_bb(), solution.cost_hpwl());
+
+        let mut torus = layout.clone();
+        torus.config_topology(Topology::Torus);
+        let solution = PlacementSolution {
+            layout: &torus,
+            ..solution
+        };
+        assert_eq!(solution.cost_bb_simd(), solution.cost_bb());
+    }
+
+    #[test]
+    fn star_center_wirelength_is_the_sum_of_its_spokes() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..5).map(clb).collect_vec();
+        let spokes = nodes[1..]
+            .iter()
+            .map(|spoke| vec![clb(0), *spoke])
+            .collect_vec();
+        let netlist = NetlistGraph::from_nets(&nodes, &spokes);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        let site = FPGALayoutCoordinate::new;
+        for (node, (x, y)) in nodes.iter().zip([(4, 4), (1, 1), (8, 4), (4, 7), (5, 5)]) {
+            solution.place_node(*node, site(x, y));
+        }
+
+        // spokes of 6, 4, 3 and 2
+        assert_eq!(solution.node_wirelength(&clb(0)), 15.0);
+        assert_eq!(solution.node_wirelength(&clb(2)), 4.0);
+        solution.enable_net_cache();
+        assert_eq!(solution.node_wirelength(&clb(0)), 15.0);
+        assert_eq!(solution.node_wirelengths()[&clb(0)], 15.0);
+    }
+
+    #[test]
+    fn half_placed_design_costs_its_placed_edges() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(4);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        let site = FPGALayoutCoordinate::new;
+        solution.place_node(clb(0), site(1, 1));
+        solution.place_node(clb(1), site(4, 2));
+
+        // only the edge from 0 to 1 has both ends placed
+        assert_eq!(solution.cost_partial(), 4.0);
+        assert!(solution.validate(true).is_empty());
+        assert_eq!(
+            solution.validate(false),
+            vec![
+                PlacementViolation::Unplaced(clb(2)),
+                PlacementViolation::Unplaced(clb(3))
+            ]
+        );
+        let mut unplaced = solution.get_unplaced_nodes();
+        unplaced.sort_by_key(|node| node.id);
+        assert_eq!(unplaced, vec![clb(2), clb(3)]);
+
+        // a partial placement can still break the rules
+        solution.place_node(clb(2), site(4, 2));
+        assert!(!solution.validate(true).is_empty());
+    }
+
+    #[test]
+    fn free_site_count_matches_free_sites() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(3);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(0), FPGALayoutCoordinate::new(2, 2));
+        solution.place_node(clb(1), FPGALayoutCoordinate::new(5, 5));
+        solution.forbid_region(6, 1, 6, 8);
+        solution.region = Some(Region::new(1, 1, 7, 7));
+        solution.min_spacing.insert(MacroType::CLB, 2);
+
+        let free_sites = solution.free_sites(MacroType::CLB);
+        assert_eq!(solution.free_site_count(MacroType::CLB), free_sites.len());
+        // 7 x 7 region less 7 forbidden sites and 5 sites around each placed
+        // node, one of which is already forbidden
+        assert_eq!(free_sites.len(), 49 - 7 - 9);
+        assert!(!free_sites.contains(&FPGALayoutCoordinate::new(2, 3)));
+    }
+
+    #[test]
+    fn annealing_keeps_min_spacing() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(12);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for i in 0..12 {
+            let site = FPGALayoutCoordinate::new(1 + 2 * (i % 4), 1 + 2 * (i / 4));
+            solution.place_node(clb(i), site);
+        }
+        solution.min_spacing.insert(MacroType::CLB, 2);
+        assert!(solution.valid(), "{:?}", solution.validate(false));
+        let initial = solution.solution_map.clone();
+
+        let config = PlacerConfig {
+            n_steps: 500,
+            n_neighbors: 4,
+            initial_temperature: 5.0,
+            teleport_probability: 0.1,
+            seed: Some(2),
+            ..Default::default()
+        };
+        let output = Placer::new(solution, config).run(None);
+        assert_ne!(output.final_solution.solution_map, initial);
+        for solution in [&output.final_solution, &output.best_solution] {
+            assert!(solution.valid(), "{:?}", solution.validate(false));
+            let clbs = solution.solution_map.values().collect_vec();
+            for (a, b) in clbs.iter().tuple_combinations() {
+                assert!(layout.distance(a, b) >= 2, "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn action_stats_count_every_proposal() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let netlist = chain_netlist(40);
+        let mut rng = StdRng::seed_from_u64(3);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 100,
+            n_neighbors: 4,
+            initial_temperature: 5.0,
+            teleport_probability: 0.5,
+            seed: Some(4),
+            ..Default::default()
+        };
+        let mut placer = Placer::new(solution, config);
+        let n_neighbors = (0..100).map(|_| placer.step().n_neighbors).sum::<usize>();
+
+        let stats = placer.action_stats.values().collect_vec();
+        let teleports = placer.action_stats["Teleport"].proposed;
+        assert!(teleports > 0);
+        assert_eq!(
+            stats.iter().map(|stats| stats.proposed).sum::<u32>(),
+            n_neighbors as u32 + teleports
+        );
+        assert_eq!(stats.iter().map(|stats| stats.selected).sum::<u32>(), 100);
+        assert_eq!(
+            stats.iter().map(|stats| stats.accepted).sum::<u32>(),
+            placer.n_accepted
+        );
+    }
+
+    #[test]
+    fn default_config_sometimes_teleports() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let netlist = chain_netlist(40);
+        let mut rng = StdRng::seed_from_u64(3);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            seed: Some(4),
+            ..Default::default()
+        };
+        let mut placer = Placer::new(solution, config);
+        for _ in 0..1000 {
+            placer.step();
+        }
+        // about 2% of the steps
+        let teleports = placer.action_stats["Teleport"].proposed;
+        assert!((5..60).contains(&teleports), "{}", teleports);
+    }
+
+    #[test]
+    fn no_action_lands_on_a_forbidden_site() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(30);
+        let nodes = (0..3).map(clb).collect_vec();
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        solution.forbid_region(3, 3, 6, 6);
+        solution.legalize().unwrap();
+        solution
+            .add_relative_group(RelativeGroup::horizontal(&nodes))
+            .unwrap();
+        let on_forbidden = |solution: &PlacementSolution| {
+            solution
+                .solution_map
+                .values()
+                .any(|loc| solution.forbidden.contains(loc))
+        };
+        assert!(!on_forbidden(&solution));
+
+        // every action, applied directly
+        let actions = [
+            PlacementAction::Move,
+            PlacementAction::Swap,
+            PlacementAction::MoveDirected,
+            PlacementAction::MoveDirectedGlobal,
+            PlacementAction::Teleport,
+            PlacementAction::MoveBiased(4),
+        ];
+        let mut direct = solution.clone();
+        for _ in 0..200 {
+            for action in actions {
+                direct.action_with_rng(action, &mut rng);
+                assert!(!on_forbidden(&direct), "{:?}", action);
+            }
+            direct.action_move_group_with_rng(0, &mut rng);
+            assert!(!on_forbidden(&direct));
+        }
+
+        // and through a seeded anneal with every action enabled
+        let config = PlacerConfig {
+            initial_temperature: 10.0,
+            n_neighbors: 4,
+            biased_move_k: 4,
+            teleport_probability: 0.2,
+            seed: Some(6),
+            ..Default::default()
+        };
+        let mut placer = Placer::new(solution, config);
+        for _ in 0..500 {
+            placer.step();
+            assert!(!on_forbidden(&placer.current_solution));
+        }
+        for action in ["Move", "Swap", "MoveDirected", "MoveBiased(4)", "Teleport"] {
+            assert!(placer.action_stats[action].proposed > 0, "{}", action);
+        }
+    }
+
+    #[test]
+    fn vpr_schedule_cools_once_per_inner_loop() {
+        let vpr = |inner_num| CoolingSchedule::Vpr { inner_num };
+        assert_eq!(CoolingSchedule::EveryStep.moves_per_temperature(1000), 1);
+        // never fewer than one move per temperature
+        assert_eq!(vpr(1.0).moves_per_temperature(0), 1);
+        assert_eq!(vpr(0.0).moves_per_temperature(1000), 1);
+        assert_eq!(vpr(1.0).moves_per_temperature(1), 1);
+        // exact at perfect cubes, rounded up in between
+        assert_eq!(vpr(1.0).moves_per_temperature(8), 16);
+        assert_eq!(vpr(1.0).moves_per_temperature(27), 81);
+        assert_eq!(vpr(1.0).moves_per_temperature(1000), 10000);
+        assert_eq!(vpr(0.5).moves_per_temperature(8), 8);
+        assert_eq!(vpr(0.1).moves_per_temperature(8), 2);
+        assert_eq!(vpr(1.0).moves_per_temperature(9), 19);
+
+        // 8 blocks at inner_num 0.25 is 4 moves per temperature
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = chain_netlist(8);
+        let mut rng = StdRng::seed_from_u64(7);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 12,
+            initial_temperature: 8.0,
+            cooling_rate: 0.5,
+            cooling_schedule: vpr(0.25),
+            seed: Some(8),
+            ..Default::default()
+        };
+        let output = Placer::new(solution, config).run(None);
+        assert_eq!(output.moves_per_temperature, 4);
+        assert_eq!(
+            output.temperature_per_step,
+            [[8.0; 4], [4.0; 4], [2.0; 4]].concat()
+        );
+    }
+
+    #[test]
+    fn neighbor_schedule_stays_in_bounds() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = chain_netlist(4);
+        let solution = PlacementSolution::new(&layout, &netlist);
+        let placer = |n_neighbors, min| {
+            let config = PlacerConfig {
+                n_neighbors,
+                neighbor_schedule: NeighborSchedule::Cooling { min },
+                ..Default::default()
+            };
+            Placer::new(solution.clone(), config)
+        };
+
+        assert_eq!(placer(0, 4).n_neighbors(), 1);
+        assert_eq!(placer(2, 4).n_neighbors(), 2);
+        let mut cooling = placer(16, 4);
+        assert_eq!(cooling.n_neighbors(), 16);
+        cooling.step_count = cooling.config.n_steps;
+        assert_eq!(cooling.n_neighbors(), 4);
+    }
+
+    #[test]
+    fn teleport_pulls_in_the_worst_node() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let nodes = (0..5).map(clb).collect_vec();
+        let netlist = NetlistGraph::from_nets(
+            &nodes,
+            &[
+                vec![clb(0), clb(1)],
+                vec![clb(1), clb(2)],
+                vec![clb(2), clb(3)],
+                vec![clb(4), clb(0)],
+                vec![clb(4), clb(1)],
+            ],
+        );
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for id in 0..4 {
+            solution.place_node(clb(id), FPGALayoutCoordinate::new(1 + id, 1));
+        }
+        solution.place_node(clb(4), FPGALayoutCoordinate::new(10, 10));
+
+        assert_eq!(solution.worst_node(), Some(clb(4)));
+        let before = solution.node_wirelength(&clb(4));
+        let hpwl_before = solution.hpwl();
+        solution.action_teleport();
+        assert!(solution.node_wirelength(&clb(4)) < before);
+        assert!(solution.hpwl() < hpwl_before);
+        assert!(solution.valid());
+    }
+
+    #[test]
+    fn config_rejects_probabilities_outside_unit_range() {
+        assert_eq!(PlacerConfig::default().teleport_probability, 0.02);
+        assert!(PlacerConfig::default().validate().is_ok());
+        let config = PlacerConfig {
+            teleport_probability: 1.5,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(PlacerConfigError::ProbabilityOutOfRange {
+                field: "teleport_probability",
+                value: 1.5
+            })
+        );
+
+        for equal_cost_acceptance in [-0.1, 1.5, f64::NAN] {
+            let config = PlacerConfig {
+                equal_cost_acceptance,
+                ..Default::default()
+            };
+            assert!(matches!(
+                config.validate(),
+                Err(PlacerConfigError::ProbabilityOutOfRange {
+                    field: "equal_cost_acceptance",
+                    ..
+                })
+            ));
+        }
+        let config = PlacerConfig {
+            equal_cost_acceptance: 1.0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn reheat_fires_on_a_stagnating_run() {
+        // every CLB site is taken and the chain starts out at its lowest cost,
+        // so the best neighbor of every step is one that changes nothing
+        let layout = build_simple_fpga_layout(4, 4);
+        let netlist = chain_netlist(4);
+        let mut rng = StdRng::seed_from_u64(5);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 100,
+            initial_temperature: 1.0,
+            reheat: Some(ReheatConfig {
+                window: 10,
+                target_cost: 0.0,
+                ..Default::default()
+            }),
+            seed: Some(6),
+            ..Default::default()
+        };
+        let mut placer = Placer::new(solution, config);
+        let steps = (0..100).map(|_| placer.step()).collect_vec();
+
+        // at temperature the equal cost moves are still taken
+        assert!(steps.iter().all(|step| step.delta == 0.0 && step.accepted));
+        assert!(!placer.reheats.is_empty());
+        let reheat = placer.reheats[0];
+        assert!(reheat.temperature_after > reheat.temperature_before);
+    }
+
+    #[test]
+    fn run_returns_the_best_placement_without_restart() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(8);
+        let mut rng = StdRng::seed_from_u64(7);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 200,
+            initial_temperature: 5.0,
+            seed: Some(8),
+            ..Default::default()
+        };
+        let placer = Placer::new(solution, config);
+        let initial_cost = placer.cost(&placer.current_solution);
+        assert_eq!(placer.best_cost, initial_cost);
+
+        let output = placer.run(None);
+        let lowest = output.y_cost.iter().copied().fold(initial_cost, f32::min);
+        assert_eq!(output.best_cost, lowest);
+        assert_eq!(output.best_cost_per_step.last(), Some(&lowest));
+        assert_eq!(output.best_solution.weighted_hpwl(), output.best_cost);
+    }
+
+    #[test]
+    fn restart_mode_returns_a_final_solution_no_worse_than_the_best() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(8);
+        let mut rng = StdRng::seed_from_u64(7);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        // kept hot with one neighbor per step so the last step isn't the best
+        // one, and patient enough that only the end of the run moves back
+        // onto the best
+        let config = PlacerConfig {
+            n_steps: 200,
+            initial_temperature: 50.0,
+            cooling_rate: 1.0,
+            n_neighbors: 1,
+            restart: Some(RestartConfig { patience: 1000 }),
+            seed: Some(8),
+            ..Default::default()
+        };
+        let output = Placer::new(solution, config).run(None);
+
+        let final_cost = output.final_solution.weighted_hpwl();
+        assert!(final_cost <= output.best_cost);
+        assert!(output.y_cost.iter().all(|cost| final_cost <= *cost));
+        assert!(*output.y_cost.last().unwrap() > output.best_cost);
+        assert_eq!(output.restarts, vec![200]);
+    }
+
+    #[test]
+    fn selected_frames_end_with_the_last_frame() {
+        let mut renderer = Renderer::new();
+        for frame in 0..10 {
+            renderer.add_frame_with_cost(String::new(), frame as f32);
+        }
+        assert_eq!(
+            renderer.selected_frames(FrameSelection::EveryN(4)),
+            vec![0, 4, 8, 9]
+        );
+        assert_eq!(
+            renderer.selected_frames(FrameSelection::EveryN(3)),
+            vec![0, 3, 6, 9]
+        );
+    }
+
+    #[test]
+    fn incident_edges_match_a_direct_graph_query() {
+        use rustworkx_core::petgraph::Direction;
+
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..7).map(clb).collect_vec();
+        let mut netlist = NetlistGraph::from_nets(
+            &nodes,
+            &[
+                vec![clb(0), clb(1), clb(2), clb(3)],
+                vec![clb(3), clb(0)],
+                vec![clb(4), clb(2), clb(5)],
+                vec![clb(5), clb(6)],
+            ],
+        );
+        // a self loop shows up once, node 6 only has an incoming edge
+        let node_idx = |id: usize| netlist.graph.node_indices().nth(id).unwrap();
+        netlist.graph.add_edge(node_idx(4), node_idx(4), ());
+        let solution = PlacementSolution::new(&layout, &netlist);
+
+        for node_idx in netlist.graph.node_indices() {
+            let mut expected = [Direction::Outgoing, Direction::Incoming]
+                .into_iter()
+                .flat_map(|direction| netlist.graph.edges_directed(node_idx, direction))
+                .map(|edge| edge.id())
+                .collect_vec();
+            expected.sort();
+            expected.dedup();
+            let mut incident = solution.incident_edges(&netlist.graph[node_idx]).to_vec();
+            incident.sort();
+            assert_eq!(incident, expected);
+        }
+        assert!(solution.incident_edges(&clb(7)).is_empty());
+    }
+
+    #[test]
+    fn gradient_of_a_node_between_two_nets() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(3);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(0), FPGALayoutCoordinate::new(2, 5));
+        solution.place_node(clb(2), FPGALayoutCoordinate::new(4, 5));
+
+        // each net adds 1 per axis where node 1 alone sets an edge of its box,
+        // level with the net's other pin (a box corner) adds nothing
+        for ((x, y), gradient) in [
+            ((6, 5), (2.0, 0.0)),
+            ((3, 5), (0.0, 0.0)),
+            ((3, 8), (0.0, 2.0)),
+            ((4, 2), (1.0, -2.0)),
+        ] {
+            solution.place_node(clb(1), FPGALayoutCoordinate::new(x, y));
+            assert_eq!(solution.wirelength_gradient(&clb(1)), gradient);
+        }
+
+        solution.net_weights = Some(Arc::new(vec![3.0, 1.0]));
+        solution.place_node(clb(1), FPGALayoutCoordinate::new(1, 5));
+        assert_eq!(solution.wirelength_gradient(&clb(1)), (-4.0, 0.0));
+        assert_eq!(solution.wirelength_gradient(&clb(0)), (3.0, 0.0));
+    }
+
+    #[test]
+    fn optimal_site_is_the_median_of_a_star() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..6).map(clb).collect_vec();
+        // node 0 in the middle of 5 two pin nets
+        let nets = (1..6).map(|id| vec![clb(0), clb(id)]).collect_vec();
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(0), FPGALayoutCoordinate::new(2, 2));
+        for (id, (x, y)) in (1..6).zip([(1, 2), (3, 7), (6, 1), (7, 4), (8, 8)]) {
+            solution.place_node(clb(id), FPGALayoutCoordinate::new(x, y));
+        }
+
+        // median of x 1, 3, 6, 7, 8 and of y 2, 7, 1, 4, 8
+        let median = FPGALayoutCoordinate::new(6, 4);
+        assert_eq!(solution.optimal_site_for(&clb(0)), Some(median));
+        let mut moved = solution.clone();
+        moved.place_node(clb(0), median);
+        for site in solution.free_sites(MacroType::CLB) {
+            let mut other = solution.clone();
+            other.place_node(clb(0), site);
+            assert!(other.node_wirelength(&clb(0)) >= moved.node_wirelength(&clb(0)));
+        }
+
+        // snapped to the closest free site, the lowest x winning the tie
+        solution.forbid_region(6, 4, 6, 4);
+        assert_eq!(
+            solution.optimal_site_for(&clb(0)),
+            Some(FPGALayoutCoordinate::new(5, 4))
+        );
+    }
+
+    #[test]
+    fn gradient_and_optimal_site_see_every_pin_of_a_net() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..4).map(clb).collect_vec();
+        // node 2 shares the first net with node 0 without an edge to it
+        let netlist = NetlistGraph::from_nets(
+            &nodes,
+            &[vec![clb(1), clb(0), clb(2)], vec![clb(0), clb(3)]],
+        );
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for (node, (x, y)) in nodes.iter().zip([(3, 4), (5, 3), (3, 6), (7, 1)]) {
+            solution.set_location(*node, FPGALayoutCoordinate::new(x, y));
+        }
+
+        for enable_cache in [false, true] {
+            if enable_cache {
+                solution.enable_net_cache();
+            }
+            assert_eq!(solution.wirelength_gradient(&clb(0)), (-1.0, 1.0));
+            assert_eq!(solution.wirelength_gradient(&clb(1)), (1.0, -1.0));
+            assert_eq!(
+                solution.optimal_site_for(&clb(0)),
+                Some(FPGALayoutCoordinate::new(5, 1))
+            );
+        }
+    }
+
+    #[test]
+    fn nets_in_region_takes_corners_in_either_order() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(3);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for (id, (x, y)) in [(2, 2), (3, 3), (7, 7)].into_iter().enumerate() {
+            solution.set_location(clb(id as u32), FPGALayoutCoordinate::new(x, y));
+        }
+
+        let expected = (vec![0], vec![1]);
+        assert_eq!(solution.nets_in_region(1, 1, 4, 4), expected);
+        assert_eq!(solution.nets_in_region(4, 4, 1, 1), expected);
+        assert_eq!(solution.nets_in_region(4, 1, 1, 4), expected);
+    }
+
+    #[test]
+    fn higher_y_weight_compresses_vertically() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let netlist = chain_netlist(16);
+        let mut rng = StdRng::seed_from_u64(11);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+
+        let spans = |y_weight: f32| {
+            let config = PlacerConfig {
+                n_steps: 1000,
+                y_weight,
+                seed: Some(12),
+                ..Default::default()
+            };
+            let output = Placer::new(solution.clone(), config).run(None);
+            let placed = output.final_solution;
+            (
+                placed.weighted_hpwl_xy(0.0, 1.0),
+                placed.weighted_hpwl_xy(1.0, 0.0),
+            )
+        };
+        let (y_even, x_even) = spans(1.0);
+        let (y_weighted, x_weighted) = spans(8.0);
+        assert!(y_weighted < y_even, "{} vs {}", y_weighted, y_even);
+        assert!(y_weighted / x_weighted < y_even / x_even);
+    }
+
+    #[test]
+    fn streamed_history_matches_the_buffered_csv() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(8);
+        let mut rng = StdRng::seed_from_u64(13);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 100,
+            initial_temperature: 2.0,
+            seed: Some(14),
+            ..Default::default()
+        };
+
+        let mut buffered = csv::Writer::from_writer(Vec::new());
+        Placer::new(solution.clone(), config.clone())
+            .run(None)
+            .write_history_csv(&mut buffered)
+            .unwrap();
+
+        let mut streamed = csv::Writer::from_writer(Vec::new());
+        let output = Placer::new(solution, config)
+            .run_streaming(None, &mut streamed)
+            .unwrap();
+        assert!(output.y_cost.is_empty());
+
+        let buffered = String::from_utf8(buffered.into_inner().unwrap()).unwrap();
+        let streamed = String::from_utf8(streamed.into_inner().unwrap()).unwrap();
+        assert_eq!(buffered.lines().count(), 101);
+        assert_eq!(streamed, buffered);
+    }
+
+    #[test]
+    fn rmst_of_a_four_pin_net_exceeds_its_hpwl() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..4).map(clb).collect_vec();
+        let netlist = NetlistGraph::from_nets(&nodes, std::slice::from_ref(&nodes));
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        // the corners of a 4x4 box, the bounding box is half the perimeter but
+        // a spanning tree needs three of its sides
+        for (node, (x, y)) in nodes.iter().zip([(1, 1), (5, 1), (1, 5), (5, 5)]) {
+            solution.place_node(*node, FPGALayoutCoordinate::new(x, y));
+        }
+
+        assert_eq!(solution.cost_hpwl(), 8.0);
+        assert_eq!(solution.cost_rmst(), 12.0);
+        assert_eq!(solution.net_rmst_edges(&nodes).len(), 3);
+    }
+
+    #[test]
+    fn hilbert_start_beats_greedy() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let netlist = chain_netlist(40);
+        let initial_hpwl = |method| {
+            let mut rng = StdRng::seed_from_u64(15);
+            gen_initial_placement_with_rng(&layout, &netlist, method, &mut rng)
+                .unwrap()
+                .cost_hpwl()
+        };
+
+        let hilbert = initial_hpwl(InitialPlacerMethod::HilbertCurve);
+        let greedy = initial_hpwl(InitialPlacerMethod::Greedy);
+        assert!(hilbert < greedy, "{} vs {}", hilbert, greedy);
+    }
+
+    #[test]
+    fn touching_footprints_do_not_overlap() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(4);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution
+            .footprints
+            .insert(MacroType::CLB, Footprint::new(2, 2));
+        // 1 touches 0 on its right side and 2 touches 0 on its top side
+        for (id, (x, y)) in [(1, 1), (3, 1), (1, 3)].into_iter().enumerate() {
+            solution.set_location(clb(id as u32), FPGALayoutCoordinate::new(x, y));
+        }
+        assert!(solution.footprint_overlaps().is_empty());
+
+        // 3 covers the corner the other three meet at
+        solution.set_location(clb(3), FPGALayoutCoordinate::new(2, 2));
+        let mut overlaps = solution
+            .footprint_overlaps()
+            .into_iter()
+            .map(|(a, b)| (a.id.min(b.id), a.id.max(b.id)))
+            .collect_vec();
+        overlaps.sort();
+        assert_eq!(overlaps, vec![(0, 3), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn legalize_min_displacement_leaves_legal_nodes_alone() {
+        let layout = build_simple_fpga_layout(20, 20);
+        let netlist = chain_netlist(60);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let before = solution.solution_map.clone();
+
+        // node 1 joins node 0 on its site, node 0 keeps it as the lower id
+        let shared = before[&clb(0)];
+        solution.place_node(clb(1), shared);
+        let displacement = solution.legalize_min_displacement().unwrap();
+
+        assert!(solution.valid());
+        for (node, location) in &before {
+            if *node != clb(1) {
+                assert_eq!(solution.solution_map[node], *location);
+            }
+        }
+        let moved_to = solution.solution_map[&clb(1)];
+        assert_eq!(moved_to.manhattan_distance(&shared) as u32, displacement);
+        let nearest = solution
+            .free_sites(MacroType::CLB)
+            .iter()
+            .map(|site| site.manhattan_distance(&shared))
+            .min()
+            .unwrap();
+        assert!(displacement as u64 <= nearest);
+    }
+
+    #[test]
+    fn shortening_the_worst_net_can_lengthen_the_rest() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..4).map(clb).collect_vec();
+        let netlist = NetlistGraph::from_nets(
+            &nodes,
+            &[
+                vec![clb(0), clb(1)],
+                vec![clb(1), clb(2)],
+                vec![clb(1), clb(3)],
+            ],
+        );
+        let site = FPGALayoutCoordinate::new;
+        let place = |hub: FPGALayoutCoordinate| {
+            let mut solution = PlacementSolution::new(&layout, &netlist);
+            solution.place_node(clb(0), site(1, 1));
+            solution.place_node(clb(2), site(7, 2));
+            solution.place_node(clb(3), site(1, 3));
+            solution.place_node(clb(1), hub);
+            solution
+        };
+        // node 1 next to nodes 0 and 3, or halfway to node 2
+        let near = place(site(1, 2));
+        let halfway = place(site(4, 2));
+
+        assert_eq!((near.cost_hpwl(), near.cost_max_net()), (8.0, 6.0));
+        assert_eq!((halfway.cost_hpwl(), halfway.cost_max_net()), (11.0, 4.0));
+        assert!(near.metrics(0.0, 0.0).objective < halfway.metrics(0.0, 0.0).objective);
+        assert!(near.metrics(2.0, 0.0).objective > halfway.metrics(2.0, 0.0).objective);
+        assert_eq!(halfway.metrics(2.0, 0.0).max_net, 4.0);
+    }
+
+    #[test]
+    fn bottom_left_origin_mirrors_svg_rows() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(2);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(0), FPGALayoutCoordinate::new(1, 2));
+        solution.place_node(clb(1), FPGALayoutCoordinate::new(5, 8));
+
+        // y of every node rectangle, the site rectangles are drawn translucent
+        let node_rows = |coord_system| {
+            let options = SvgRenderOptions {
+                coord_system,
+                ..Default::default()
+            };
+            solution
+                .render_svg_with_options(&options)
+                .lines()
+                .filter(|line| line.contains("<rect") && line.contains("fill=\"red\"/>"))
+                .map(|line| {
+                    let y = line.split("y=\"").nth(1).unwrap();
+                    y[..y.find('"').unwrap()].parse::<u32>().unwrap()
+                })
+                .collect_vec()
+        };
+        let cell = SvgRenderOptions::default().cell_size;
+        let mut top_left = node_rows(CoordSystem::TopLeft);
+        let mut bottom_left = node_rows(CoordSystem::BottomLeft);
+        top_left.sort();
+        bottom_left.sort();
+        assert_eq!(top_left, vec![2 * cell, 8 * cell]);
+        assert_eq!(bottom_left, vec![cell, 7 * cell]);
+    }
+
+    #[test]
+    fn try_swap_rejects_incompatible_and_unplaced_nodes() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let site = FPGALayoutCoordinate::new;
+        let pad = NetlistNode {
+            id: 3,
+            macro_type: MacroType::IO,
+        };
+        let netlist = NetlistGraph::from_nets(
+            &[clb(0), clb(1), clb(2), pad],
+            &[vec![clb(0), clb(1), clb(2), pad]],
+        );
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(0), site(1, 1));
+        solution.place_node(clb(1), site(2, 2));
+        solution.place_node(pad, site(0, 3));
+
+        assert_eq!(
+            solution.try_swap(clb(0), pad),
+            Err(SwapError::IncompatibleSite(clb(0), site(0, 3)))
+        );
+        assert_eq!(
+            solution.try_swap(clb(0), clb(2)),
+            Err(SwapError::NodeNotPlaced(clb(2)))
+        );
+        assert_eq!(solution.solution_map[&clb(0)], site(1, 1));
+        assert_eq!(solution.solution_map[&pad], site(0, 3));
+
+        assert_eq!(solution.try_swap(clb(0), clb(1)), Ok(()));
+        assert_eq!(solution.solution_map[&clb(0)], site(2, 2));
+        assert_eq!(solution.solution_map[&clb(1)], site(1, 1));
+    }
+
+    #[test]
+    fn input_pad_stays_off_output_sites() {
+        let mut layout = build_simple_fpga_layout(5, 5);
+        let site = FPGALayoutCoordinate::new;
+        for y in 1..4 {
+            layout.config_io_direction(site(0, y), IoDirection::Output);
+            layout.config_io_direction(site(4, y), IoDirection::Output);
+        }
+        for x in 1..4 {
+            layout.config_io_direction(site(x, 0), IoDirection::Output);
+            layout.config_io_direction(site(x, 4), IoDirection::Output);
+        }
+        layout.config_io_direction(site(0, 2), IoDirection::Input);
+
+        let pad = NetlistNode {
+            id: 1,
+            macro_type: MacroType::IO,
+        };
+        let mut netlist = NetlistGraph::from_nets(&[clb(0), pad], &[vec![pad, clb(0)]]);
+        netlist.set_io_direction(pad, IoDirection::Input);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(0), site(2, 2));
+
+        assert_eq!(solution.get_possible_sites_for(&pad), vec![site(0, 2)]);
+        assert!(!solution.site_accepts(&pad, &site(0, 1)));
+        assert_eq!(
+            solution.try_place_at(pad, site(0, 1)),
+            Err(PlaceError::WrongSiteType(site(0, 1)))
+        );
+        solution.place_node(pad, site(0, 1));
+        assert!(!solution.valid());
+        solution.place_node(pad, site(0, 2));
+        assert!(solution.valid());
+    }
 
-    let mut rng = rand::thread_rng();
+    #[test]
+    fn net_cache_stays_consistent_over_random_moves() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let nodes = (0..30).map(clb).collect_vec();
+        let mut nets = nodes.windows(2).map(|pair| pair.to_vec()).collect_vec();
+        nets.push(nodes.iter().step_by(5).copied().collect_vec());
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(16);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        solution.enable_net_cache();
 
-    let count_summary_layout = solution.layout.count_summary();
-    let count_summary_netlist = solution.netlist.count_summary();
+        for _ in 0..50 {
+            solution.perturb(10, &mut rng);
+            assert_eq!(solution.hpwl(), solution.cost_hpwl());
+        }
+        for net in 0..netlist.net_count() {
+            let cached = solution.net_cache.as_ref().unwrap().net_bbox(net);
+            assert_eq!(solution.recompute_net_bbox(net), cached);
+        }
+    }
 
-    for &macro_type in &[
-        MacroType::CLB,
-        MacroType::DSP,
-        MacroType::BRAM,
-        MacroType::IO,
-    ] {
-        assert!(
-            count_summary_layout
-                .get(&FPGALayoutType::MacroType(macro_type))
-                .unwrap()
-                >= count_summary_netlist.get(&macro_type).unwrap()
+    #[test]
+    fn legalize_separates_two_overlapping_clbs() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = chain_netlist(3);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        let site = FPGALayoutCoordinate::new;
+        solution.place_node(clb(0), site(3, 3));
+        solution.place_node(clb(1), site(3, 3));
+        solution.place_node(clb(2), site(5, 5));
+        assert!(!solution.valid());
+
+        assert_eq!(solution.legalize(), Ok(1));
+        assert!(solution.valid());
+        // the lower id keeps the site and the other lands right next to it
+        assert_eq!(solution.solution_map[&clb(0)], site(3, 3));
+        assert_eq!(
+            solution.solution_map[&clb(1)].manhattan_distance(&site(3, 3)),
+            1
         );
+        assert_eq!(solution.solution_map[&clb(2)], site(5, 5));
     }
 
-    for node in solution.netlist.all_nodes() {
-        let possible_sites = solution.get_possible_sites(node.macro_type);
-        let location: FPGALayoutCoordinate = possible_sites[rng.gen_range(0..possible_sites.len())];
-        solution.place_node(*node, location);
+    #[test]
+    fn label_threshold_drops_every_text_element() {
+        let layout = build_simple_fpga_layout(6, 6);
+        let netlist = chain_netlist(3);
+        let mut rng = StdRng::seed_from_u64(19);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let render = |max_labeled_nodes| {
+            solution.render_svg_with_options(&SvgRenderOptions {
+                cell_size: 20,
+                max_labeled_nodes,
+                ..Default::default()
+            })
+        };
+
+        assert_eq!(render(None).matches("<text").count(), 3);
+        assert_eq!(render(Some(3)).matches("<text").count(), 3);
+        let unlabeled = render(Some(2));
+        assert_eq!(unlabeled.matches("<text").count(), 0);
+        assert!(unlabeled.contains("viewBox=\"0 0 120 120\""));
     }
 
-    assert!(solution.valid());
+    #[test]
+    fn fanout_weights_change_the_placement() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..12).map(clb).collect_vec();
+        // node 0 drives nodes 1 to 6 over nets of their own, the rest are a chain
+        let mut nets = (1..7).map(|sink| vec![clb(0), clb(sink)]).collect_vec();
+        nets.extend(nodes[6..].windows(2).map(|pair| pair.to_vec()));
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
 
-    solution
-}
+        let weights = WeightPolicy::Fanout.net_weights(&netlist);
+        assert_eq!(weights[..6], [1.0 / 6.0; 6]);
+        assert_eq!(weights[6..], [1.0; 5]);
+        assert_eq!(WeightPolicy::Manual.net_weights(&netlist), vec![1.0; 11]);
 
-pub fn gen_greedy_placement<'a>(
-    layout: &'a FPGALayout,
-    netlist: &'a NetlistGraph,
-) -> PlacementSolution<'a> {
-    // place nodes in the first spot in the layout closest to the origin (0,0) which is the top left corner
+        let mut rng = StdRng::seed_from_u64(20);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let place = |auto_weight| {
+            let config = PlacerConfig {
+                n_steps: 300,
+                auto_weight,
+                seed: Some(21),
+                ..Default::default()
+            };
+            Placer::new(solution.clone(), config)
+                .run(None)
+                .final_solution
+                .solution_map
+        };
+        assert_ne!(place(WeightPolicy::Manual), place(WeightPolicy::Fanout));
+    }
 
-    let mut solution = PlacementSolution::new(layout, netlist);
+    #[test]
+    fn pad_attraction_pulls_pad_logic_to_the_border() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let pad = NetlistNode {
+            id: 0,
+            macro_type: MacroType::IO,
+        };
+        let mut nodes = vec![pad];
+        nodes.extend((1..9).map(clb));
+        let mut nets = vec![vec![pad, clb(1)]];
+        nets.extend(nodes[1..].windows(2).map(|pair| pair.to_vec()));
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(22);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        solution
+            .lock_node(pad, FPGALayoutCoordinate::new(0, 6))
+            .unwrap();
 
-    let count_summary_layout = solution.layout.count_summary();
-    let count_summary_netlist = solution.netlist.count_summary();
+        let pad_net_hpwl = |pad_attraction| {
+            let config = PlacerConfig {
+                n_steps: 100,
+                pad_attraction,
+                seed: Some(29),
+                ..Default::default()
+            };
+            let placer = Placer::new(solution.clone(), config);
+            assert_eq!(placer.pad_attraction(), pad_attraction);
+            placer.run(None).final_solution.io_net_hpwl()
+        };
+        let plain = pad_net_hpwl(0.0);
+        let attracted = pad_net_hpwl(20.0);
+        assert!(attracted < plain, "{} vs {}", attracted, plain);
+    }
 
-    for &macro_type in &[
-        MacroType::CLB,
-        MacroType::DSP,
-        MacroType::BRAM,
-        MacroType::IO,
-    ] {
-        assert!(
-            count_summary_layout
-                .get(&FPGALayoutType::MacroType(macro_type))
-                .unwrap()
-                >= count_summary_netlist.get(&macro_type).unwrap()
+    #[test]
+    fn scarcity_order_fills_a_tightly_packed_fabric() {
+        // nine interior sites, the center one the only site a BRAM fits on
+        let mut layout = build_simple_fpga_layout(5, 5);
+        layout.config_multi_type(
+            FPGALayoutCoordinate::new(2, 2),
+            &[MacroType::CLB, MacroType::BRAM],
         );
-    }
+        let bram = NetlistNode {
+            id: 8,
+            macro_type: MacroType::BRAM,
+        };
+        let mut nodes = (0..8).map(clb).collect_vec();
+        nodes.push(bram);
+        let netlist = NetlistGraph::from_nets(&nodes, &[nodes.clone()]);
 
-    let nodes = solution.netlist.all_nodes();
-    for node in nodes {
-        let possible_sites = solution.get_possible_sites(node.macro_type);
-        // get the site with the min manhattan distance to the origin (0,0)
-        let location = possible_sites
-            .iter()
-            .min_by(|a, b| {
-                let a_distance: u32 = a.x + a.y;
-                let b_distance = b.x + b.y;
-                a_distance.cmp(&b_distance)
-            })
+        let mut random_failures = 0;
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let solution = gen_initial_placement_with_rng(
+                &layout,
+                &netlist,
+                InitialPlacerMethod::ScarcityOrdered,
+                &mut rng,
+            )
             .unwrap();
+            assert!(solution.valid());
+            assert_eq!(
+                solution.solution_map[&bram],
+                FPGALayoutCoordinate::new(2, 2)
+            );
 
-        solution.place_node(*node, *location);
+            let random = gen_initial_placement_with_rng(
+                &layout,
+                &netlist,
+                InitialPlacerMethod::Random,
+                &mut rng,
+            );
+            if random.is_err() {
+                random_failures += 1;
+            }
+        }
+        assert!(random_failures > 0);
     }
 
-    assert!(solution.valid());
+    #[test]
+    fn only_nets_straddling_a_bram_column_cross_it() {
+        let layout = build_simple_fpga_layout(14, 6);
+        assert_eq!(layout.special_columns(), vec![10]);
+        let nodes = (0..6).map(clb).collect_vec();
+        let mut netlist = NetlistGraph::from_nets(
+            &nodes,
+            &[
+                vec![clb(0), clb(1)],
+                vec![clb(2), clb(3)],
+                vec![clb(4), clb(5)],
+            ],
+        );
+        netlist.set_net_weight(0, 2.0);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        let site = FPGALayoutCoordinate::new;
+        // net 0 straddles the column, net 1 stays left of it and net 2 ends on
+        // a site next to it
+        for (node, (x, y)) in nodes
+            .iter()
+            .zip([(8, 1), (12, 2), (2, 1), (6, 3), (11, 1), (12, 4)])
+        {
+            solution.place_node(*node, site(x, y));
+        }
 
-    solution
-}
+        assert_eq!(solution.cost_column_crossing(), 2.0);
+        assert_eq!(solution.cost_column_crossing_with(&[3, 10]), 3.0);
 
-pub fn gen_initial_placement<'a>(
-    layout: &'a FPGALayout,
-    netlist: &'a NetlistGraph,
-    method: InitialPlacerMethod,
-) -> PlacementSolution<'a> {
-    match method {
-        InitialPlacerMethod::Random => gen_random_placement(layout, netlist),
-        InitialPlacerMethod::Greedy => gen_greedy_placement(layout, netlist),
+        // net 2 now runs along column 11, only net 0 crosses it
+        solution.place_node(clb(5), site(11, 4));
+        assert_eq!(solution.cost_column_crossing_with(&[11]), 2.0);
     }
-}
 
-#[derive(Clone)]
-pub struct Renderer {
-    pub svg_renders: Vec<String>,
-}
+    #[test]
+    fn annealing_recovers_from_a_perturbation() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(12);
+        let mut rng = StdRng::seed_from_u64(24);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 500,
+            seed: Some(26),
+            ..Default::default()
+        };
+        let annealed = Placer::new(solution, config.clone())
+            .run(None)
+            .final_solution;
+        let annealed_cost = annealed.cost_hpwl();
 
-impl Default for Renderer {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+        let mut perturbed = annealed.clone();
+        let delta = perturbed.perturb(20, &mut rng);
+        assert!(perturbed.valid());
+        assert_eq!(perturbed.cost_hpwl(), annealed_cost + delta);
+        assert!(delta > 0.0);
 
-impl Renderer {
-    pub fn new() -> Renderer {
-        Renderer {
-            svg_renders: Vec::new(),
-        }
+        let recovered = Placer::new(perturbed, config).run(None).final_solution;
+        assert!(recovered.valid());
+        assert!(
+            recovered.cost_hpwl() <= annealed_cost * 1.25,
+            "{} vs {}",
+            recovered.cost_hpwl(),
+            annealed_cost
+        );
     }
 
-    pub fn add_frame(&mut self, svg: String) {
-        self.svg_renders.push(svg);
+    #[test]
+    fn warm_restart_starts_at_the_prior_cost() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(10);
+        let mut rng = StdRng::seed_from_u64(26);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 200,
+            initial_temperature: 3.0,
+            seed: Some(27),
+            ..Default::default()
+        };
+        let output = Placer::new(solution, config.clone()).run(None);
+        let prior_cost = output.final_solution.cost_hpwl();
+        let prior_temperature = output.final_temperature;
+
+        let state = output.into_state();
+        assert_eq!(state.temperature, prior_temperature);
+        let placer = Placer::from_state(state, config);
+        assert_eq!(placer.temperature, prior_temperature);
+        let restarted = placer.run(None);
+        assert_eq!(restarted.y_cost[0], prior_cost);
+        assert!(restarted.final_solution.cost_hpwl() <= prior_cost * 1.25);
     }
 
-    pub fn render_to_video(
-        self,
-        output_name: &str,
-        output_dir: &str,
-        framerate: f64,
-        every_n_frames: usize,
-        make_gif: bool,
-    ) {
-        let dir = tempdir().unwrap();
-        let frame_dir = dir.path().join("frames");
-        std::fs::create_dir(&frame_dir).unwrap();
+    #[test]
+    fn check_placeable_counts_an_oversubscribed_bram_column() {
+        // one BRAM column with a site on each of the 4 interior rows
+        let layout = build_simple_fpga_layout(14, 6);
+        let bram = |id| NetlistNode {
+            id,
+            macro_type: MacroType::BRAM,
+        };
+        let nodes = (0..3).map(clb).chain((3..9).map(bram)).collect_vec();
+        let netlist = NetlistGraph::from_nets(&nodes, std::slice::from_ref(&nodes));
+        assert_eq!(
+            check_placeable(&layout, &netlist),
+            Err(vec![CompatIssue::NotEnoughSites {
+                macro_type: MacroType::BRAM,
+                needed: 6,
+                available: 4,
+            }])
+        );
 
-        let mut input_frames_svg_paths = Vec::new();
+        let fitting = NetlistGraph::from_nets(&nodes[..7], &[nodes[..7].to_vec()]);
+        assert_eq!(check_placeable(&layout, &fitting), Ok(()));
+    }
 
-        for (frame_number, svg) in self.svg_renders.iter().enumerate() {
-            if frame_number % every_n_frames != 0 {
-                continue;
+    #[test]
+    fn normalized_cost_ignores_design_size() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let shape = [(1, 1), (2, 1), (3, 1), (3, 2)];
+        // copies of a 4 node chain, each placed in the same shape a few rows up
+        fn place_copies<'a>(
+            layout: &'a FPGALayout,
+            netlist: &'a NetlistGraph,
+            shape: &[(u32, u32)],
+            copies: u32,
+        ) -> PlacementSolution<'a> {
+            let mut solution = PlacementSolution::new(layout, netlist);
+            for copy in 0..copies {
+                for (i, (x, y)) in shape.iter().enumerate() {
+                    let node = clb(copy * 4 + i as u32);
+                    solution.place_node(node, FPGALayoutCoordinate::new(*x, y + copy * 3));
+                }
             }
-            let frame_fp = frame_dir.join(format!("frame_{}.svg", frame_number));
-            input_frames_svg_paths.push(frame_fp.clone());
-            std::fs::write(&frame_fp, svg).expect("Unable to write file");
+            solution
         }
+        let copies_netlist = |copies: u32| {
+            let nodes = (0..copies * 4).map(clb).collect_vec();
+            let nets = nodes
+                .chunks(4)
+                .flat_map(|chain| chain.windows(2).map(|pair| pair.to_vec()))
+                .collect_vec();
+            NetlistGraph::from_nets(&nodes, &nets)
+        };
+        let (small, large) = (copies_netlist(1), copies_netlist(2));
+        let (small, large) = (
+            place_copies(&layout, &small, &shape, 1),
+            place_copies(&layout, &large, &shape, 2),
+        );
 
-        // rename the every_n_frames frames to be sequential to not confuse ffmpeg
-        let mut input_frames_svg_paths_renumbered = Vec::new();
-        for (frame_number, svg_fp) in input_frames_svg_paths.iter().enumerate() {
-            let new_fp = frame_dir.join(format!("frame_{}.svg", frame_number));
-            std::fs::rename(svg_fp, &new_fp).expect("Unable to rename file");
-            input_frames_svg_paths_renumbered.push(new_fp);
+        assert_eq!(large.cost_hpwl(), 2.0 * small.cost_hpwl());
+        for normalization in [CostNormalization::PerNet, CostNormalization::LowerBound] {
+            assert_eq!(
+                small.normalized_cost_with(normalization),
+                large.normalized_cost_with(normalization)
+            );
         }
+        assert_eq!(small.normalized_cost(), 1.0);
 
-        // convert the frames to pngs
-        input_frames_svg_paths_renumbered
-            .par_iter()
-            .for_each(|svg_fp| {
-                let png_fp = svg_fp.with_extension("png");
-                println!("Converting {:?} to {:?} ... ", svg_fp, png_fp);
-                let _output: std::process::Output = std::process::Command::new("magick")
-                    .arg("convert")
-                    .arg("-size")
-                    .arg("800x800")
-                    .arg(svg_fp)
-                    .arg(png_fp)
-                    .output()
-                    .expect("failed to execute magick");
-            });
+        let config = PlacerConfig {
+            n_steps: 5,
+            seed: Some(28),
+            ..Default::default()
+        };
+        let output = Placer::new(large, config).run(None);
+        for (cost, normalized) in output.y_cost.iter().zip(&output.y_cost_normalized) {
+            assert_eq!(*normalized, cost / 6.0);
+        }
+    }
 
-        // use ffmpeg to convert the frames to a video
-        let mut ffmpeg_cmd = Command::new("ffmpeg");
-        ffmpeg_cmd.arg("-y");
-        ffmpeg_cmd.arg("-framerate");
-        ffmpeg_cmd.arg(format!("{}", framerate));
-        ffmpeg_cmd.arg("-i");
-        ffmpeg_cmd.arg(frame_dir.join("frame_%d.png").to_str().unwrap());
-        ffmpeg_cmd.arg("-c:v");
-        ffmpeg_cmd.arg("libx264");
-        ffmpeg_cmd.arg("-pix_fmt");
-        ffmpeg_cmd.arg("yuv420p");
-        ffmpeg_cmd.arg(format!("{}/{}.mp4", output_dir, output_name));
-
-        let child = ffmpeg_cmd.spawn().expect("failed to execute ffmpeg");
-        child.wait_with_output().expect("failed to wait on ffmpeg");
-
-        if make_gif {
-            // use ffmpeg to convert the frames to a gif
-            let mut ffmpeg_cmd = Command::new("ffmpeg");
-            ffmpeg_cmd.arg("-y");
-            ffmpeg_cmd.arg("-framerate");
-            ffmpeg_cmd.arg(format!("{}", framerate));
-            ffmpeg_cmd.arg("-i");
-            ffmpeg_cmd.arg(frame_dir.join("frame_%d.png").to_str().unwrap());
+    #[test]
+    fn a_capacity_3_site_refuses_a_fourth_node() {
+        let mut layout = build_simple_fpga_layout(10, 10);
+        let site = FPGALayoutCoordinate::new(2, 2);
+        layout.config_capacity(site, 3);
+        let netlist = chain_netlist(5);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for id in 0..3 {
+            solution.place_node(clb(id), site);
+        }
+        solution.place_node(clb(4), FPGALayoutCoordinate::new(5, 5));
+        assert!(solution.validate(true).is_empty());
+        assert!(!solution.free_sites(MacroType::CLB).contains(&site));
 
-            // Optimize gif size using rescaling and color pallet reduction
-            ffmpeg_cmd.arg("-filter_complex");
-            ffmpeg_cmd.arg("scale=iw/2:-1,split [a][b];[a] palettegen=stats_mode=diff:max_colors=32[p]; [b][p] paletteuse=dither=bayer");
+        // joining a full site swaps out its lowest id rather than sharing it
+        solution.place_node(clb(3), FPGALayoutCoordinate::new(6, 6));
+        solution.try_place_at(clb(3), site).unwrap();
+        assert_eq!(
+            solution.solution_map[&clb(0)],
+            FPGALayoutCoordinate::new(6, 6)
+        );
+        assert!(solution.valid());
 
-            ffmpeg_cmd.arg(format!("{}/{}.gif", output_dir, output_name));
+        solution.place_node(clb(0), site);
+        assert_eq!(
+            solution.validate(false),
+            vec![PlacementViolation::SharedSite(site, clb(0), clb(3))]
+        );
+    }
 
-            let child = ffmpeg_cmd.spawn().expect("failed to execute ffmpeg");
-            child.wait_with_output().expect("failed to wait on ffmpeg");
-        }
+    #[test]
+    fn check_legality_passes_a_legal_run() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(6);
+        let mut rng = StdRng::seed_from_u64(29);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let config = PlacerConfig {
+            n_steps: 200,
+            initial_temperature: 5.0,
+            check_legality: true,
+            seed: Some(29),
+            ..Default::default()
+        };
+        assert!(Placer::new(solution, config)
+            .run(None)
+            .final_solution
+            .valid());
     }
-}
 
-pub struct PlacerOutput<'a> {
-    pub initial_solution: PlacementSolution<'a>,
-    pub final_solution: PlacementSolution<'a>,
-    pub x_steps: Vec<u32>,
-    pub y_cost: Vec<f32>,
-    pub renderer: Option<Renderer>,
-}
+    #[test]
+    #[should_panic(expected = "left an illegal placement")]
+    fn check_legality_catches_an_illegal_move() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(6);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        // nodes 0 and 1 stacked on one site and locked there, what a buggy
+        // move generator could leave behind, so every accepted move keeps it
+        for id in 0..2 {
+            solution.set_location(clb(id), FPGALayoutCoordinate::new(1, 1));
+            solution.locked.insert(clb(id));
+        }
+        for (id, (x, y)) in (2..6).zip([(8, 8), (1, 8), (8, 1), (5, 5)]) {
+            solution.place_node(clb(id), FPGALayoutCoordinate::new(x, y));
+        }
+        let config = PlacerConfig {
+            n_steps: 50,
+            initial_temperature: 10.0,
+            check_legality: true,
+            seed: Some(29),
+            ..Default::default()
+        };
+        Placer::new(solution, config).run(None);
+    }
 
-pub fn fast_sa_placer(
-    initial_solution: PlacementSolution,
-    n_steps: u32,
-    n_neighbors: usize, // number of neighbors to explore at each step
-    verbose: bool,
-    render: bool,
-) -> PlacerOutput {
-    let mut renderer = Renderer::new();
+    #[test]
+    fn manual_edits_after_annealing_keep_the_cached_cost() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(8);
+        let mut rng = StdRng::seed_from_u64(30);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        solution.enable_net_cache();
+        let config = PlacerConfig {
+            n_steps: 100,
+            seed: Some(30),
+            ..Default::default()
+        };
+        let mut annealed = Placer::new(solution, config).run(None).final_solution;
+        assert!(annealed.net_cache.is_some());
 
-    let mut current_solution = initial_solution.clone();
+        let free = annealed.free_sites(MacroType::CLB);
+        annealed.place_node(clb(0), free[0]);
+        assert_eq!(annealed.hpwl(), annealed.cost_hpwl());
+        annealed.try_place_at(clb(7), free[free.len() - 1]).unwrap();
+        assert_eq!(annealed.hpwl(), annealed.cost_hpwl());
+        // swapping onto an occupied site moves both nodes
+        let occupied = annealed.solution_map[&clb(3)];
+        annealed.try_place_at(clb(0), occupied).unwrap();
+        assert_eq!(annealed.hpwl(), annealed.cost_hpwl());
 
-    let mut rng = rand::thread_rng();
-    let actions: &[PlacementAction] = &[
-        PlacementAction::Move,
-        PlacementAction::Swap,
-        PlacementAction::MoveDirected,
-    ];
+        // writing to solution_map directly bypasses the cache until a recompute
+        annealed.solution_map.insert(clb(4), free[1]);
+        annealed.recompute_cost();
+        assert_eq!(annealed.hpwl(), annealed.cost_hpwl());
+    }
 
-    let mut x_steps = Vec::new();
-    let mut y_cost = Vec::new();
+    #[test]
+    fn opposite_edges_are_adjacent_on_a_torus() {
+        let planar = build_simple_fpga_layout(10, 10);
+        let mut torus = planar.clone();
+        torus.config_topology(Topology::Torus);
+        let pad = |id| NetlistNode {
+            id,
+            macro_type: MacroType::IO,
+        };
+        let nodes = [pad(0), pad(1)];
+        let netlist = NetlistGraph::from_nets(&nodes, &[nodes.to_vec()]);
+        let (left, right) = (
+            FPGALayoutCoordinate::new(0, 5),
+            FPGALayoutCoordinate::new(9, 5),
+        );
 
-    for _i in 0..n_steps {
-        x_steps.push(_i);
-        y_cost.push(current_solution.cost_bb());
-        if render {
-            renderer.add_frame(current_solution.render_svg());
+        for (layout, distance) in [(&planar, 9), (&torus, 1)] {
+            assert_eq!(layout.distance(&left, &right), distance);
+            let mut solution = PlacementSolution::new(layout, &netlist);
+            solution.place_node(pad(0), left);
+            solution.place_node(pad(1), right);
+            assert!(solution.valid());
+            assert_eq!(solution.cost_hpwl(), distance as f32);
         }
+    }
+
+    #[test]
+    fn bbox_render_draws_one_rectangle_per_net() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..8).map(clb).collect_vec();
+        let mut nets = nodes.windows(2).map(|pair| pair.to_vec()).collect_vec();
+        nets.push(vec![clb(0), clb(3), clb(5), clb(7)]);
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(32);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
 
-        // randomly select actions
-        let actions: Vec<_> = actions.choose_multiple(&mut rng, n_neighbors).collect();
+        let bboxes = solution.render_svg_bboxes();
+        assert_eq!(
+            bboxes.matches("class=\"net-bbox\"").count(),
+            netlist.net_count()
+        );
+        assert_eq!(
+            solution.render_svg().matches("class=\"net-bbox\"").count(),
+            0
+        );
+    }
 
-        let new_solutions: Vec<_> = actions
-            // .into_par_iter()
+    #[test]
+    fn triangle_hull_area() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let nodes = (0..7).map(clb).collect_vec();
+        let nets = vec![
+            vec![clb(0), clb(1), clb(2)],
+            // node 6 sits inside the triangle so the hull is unchanged
+            vec![clb(0), clb(1), clb(2), clb(6)],
+            vec![clb(3), clb(4), clb(5)],
+            vec![clb(0), clb(1)],
+        ];
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for (id, (x, y)) in [(1, 1), (5, 1), (1, 4), (7, 2), (7, 4), (7, 8), (2, 2)]
             .into_iter()
-            .map(|action| {
-                let mut new_solution = current_solution.clone();
-                new_solution.action(*action);
-                new_solution
-            })
-            .collect();
+            .enumerate()
+        {
+            solution.place_node(clb(id as u32), FPGALayoutCoordinate::new(x, y));
+        }
 
-        let best_solution = new_solutions
+        let areas = nets
             .iter()
-            .min_by(|sol1, sol2| {
-                (sol1.cost_bb() - current_solution.cost_bb())
-                    .partial_cmp(&(sol2.cost_bb() - current_solution.cost_bb()))
-                    .unwrap()
+            .map(|pins| solution.net_hull_area(pins))
+            .collect_vec();
+        // half of the 4 x 3 legs, collinear and 2 pin nets have no area
+        assert_eq!(areas, vec![6.0, 6.0, 0.0, 0.0]);
+        assert_eq!(solution.cost_hull_area(), 12.0);
+    }
+
+    #[test]
+    fn biased_move_never_picks_an_illegal_site() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let nodes = (0..14)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: match id {
+                    0..=9 => MacroType::CLB,
+                    10 | 11 => MacroType::BRAM,
+                    _ => MacroType::IO,
+                },
             })
-            .unwrap();
+            .collect_vec();
+        let nets = nodes.windows(2).map(|pair| pair.to_vec()).collect_vec();
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(33);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.forbid_region(3, 3, 5, 5);
+        solution.min_spacing.insert(MacroType::BRAM, 3);
+        for node in &nodes {
+            let site = *solution
+                .get_possible_sites_for(node)
+                .choose(&mut rng)
+                .unwrap();
+            solution.place_node(*node, site);
+        }
+        assert!(solution.valid());
+
+        for step in 0..300 {
+            solution.action_move_biased_with_rng(step % 6 + 1, &mut rng);
+            let violations = solution.validate(false);
+            assert!(violations.is_empty(), "step {}: {:?}", step, violations);
+            // validate doesn't know about forbidden sites
+            assert!(solution
+                .solution_map
+                .values()
+                .all(|location| !solution.forbidden.contains(location)));
+        }
+    }
+
+    #[test]
+    fn soft_region_penalty_decides_whether_a_node_leaks_out() {
+        let layout = build_simple_fpga_layout(10, 10);
+        // node 0 prefers the left two columns but both its nets pull it right
+        let nodes = (0..3).map(clb).collect_vec();
+        let netlist =
+            NetlistGraph::from_nets(&nodes, &[vec![clb(0), clb(1)], vec![clb(0), clb(2)]]);
+        let soft_region = |penalty_per_cell| SoftRegion {
+            nodes: vec![clb(0)],
+            bounds: Region::new(1, 1, 2, 8),
+            penalty_per_cell,
+        };
+
+        let mut x_after = Vec::new();
+        for penalty_per_cell in [10.0, 0.5] {
+            let mut solution = PlacementSolution::new(&layout, &netlist);
+            solution.place_node(clb(0), FPGALayoutCoordinate::new(1, 5));
+            solution
+                .lock_node(clb(1), FPGALayoutCoordinate::new(8, 4))
+                .unwrap();
+            solution
+                .lock_node(clb(2), FPGALayoutCoordinate::new(8, 6))
+                .unwrap();
+            solution.add_soft_region(soft_region(penalty_per_cell));
+            let config = PlacerConfig {
+                n_steps: 300,
+                seed: Some(34),
+                ..Default::default()
+            };
+            let output = Placer::new(solution, config).run(None);
+            x_after.push(output.final_solution.solution_map[&clb(0)].x);
+        }
+        // every cell out saves 2 of wirelength, worth it only under the low penalty
+        assert_eq!(x_after[0], 2);
+        assert!(x_after[1] > 2, "{:?}", x_after);
+    }
+
+    #[test]
+    fn cost_components_sum_to_the_recorded_cost() {
+        let layout = build_simple_fpga_layout(14, 14);
+        let nodes = (0..16)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: match id {
+                    0..=11 => MacroType::CLB,
+                    12 | 13 => MacroType::BRAM,
+                    _ => MacroType::IO,
+                },
+            })
+            .collect_vec();
+        let mut nets = nodes.windows(2).map(|pair| pair.to_vec()).collect_vec();
+        nets.push(vec![clb(0), clb(4), clb(8), nodes[14]]);
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(35);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        solution.add_soft_region(SoftRegion {
+            nodes: vec![clb(0), clb(1)],
+            bounds: Region::new(1, 1, 4, 4),
+            penalty_per_cell: 0.5,
+        });
+        let config = PlacerConfig {
+            n_steps: 100,
+            initial_temperature: 3.0,
+            beta: 0.5,
+            lambda: 0.3,
+            column_crossing_weight: 0.2,
+            hull_area_weight: 0.1,
+            pad_attraction: 0.4,
+            seed: Some(35),
+            ..Default::default()
+        };
+        let output = Placer::new(solution, config).run(None);
+
+        let mut names = output.cost_components.keys().cloned().collect_vec();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "column_crossing",
+                "hpwl",
+                "hull_area",
+                "max_net",
+                "pad_attraction",
+                "soft_regions",
+                "timing"
+            ]
+        );
+        for (step, cost) in output.y_cost.iter().enumerate() {
+            let sum = output
+                .cost_components
+                .values()
+                .map(|values| values[step])
+                .sum::<f32>();
+            assert!(
+                (sum - cost).abs() <= 1e-3 * cost.max(1.0),
+                "step {}: {} vs {}",
+                step,
+                sum,
+                cost
+            );
+        }
+    }
+
+    #[test]
+    fn equal_cost_acceptance_lets_a_node_wander_a_plateau() {
+        let layout = build_simple_fpga_layout(10, 10);
+        // no nets, so every placement costs 0
+        let netlist = NetlistGraph::from_nets(&[clb(0)], &[]);
+        let start = FPGALayoutCoordinate::new(4, 4);
+
+        let mut wandered = Vec::new();
+        for equal_cost_acceptance in [0.0, 1.0] {
+            let mut solution = PlacementSolution::new(&layout, &netlist);
+            solution.place_node(clb(0), start);
+            let config = PlacerConfig {
+                n_steps: 50,
+                equal_cost_acceptance,
+                seed: Some(36),
+                ..Default::default()
+            };
+            let output = Placer::new(solution, config).run(None);
+            let accepted = output
+                .action_stats
+                .values()
+                .map(|stats| stats.accepted)
+                .sum::<u32>();
+            wandered.push((
+                accepted,
+                output.final_solution.solution_map[&clb(0)] != start,
+            ));
+        }
+        assert_eq!(wandered[0], (0, false));
+        assert_eq!(wandered[1].0, 50);
+        assert!(wandered[1].1);
+    }
+
+    #[test]
+    fn vpr_place_lists_one_row_per_placed_node() {
+        let mut layout = build_simple_fpga_layout(6, 6);
+        layout.config_capacity(FPGALayoutCoordinate::new(0, 2), 2);
+        let io = |id| NetlistNode {
+            id,
+            macro_type: MacroType::IO,
+        };
+        let nodes = [clb(1), clb(2), io(4), io(5)];
+        let netlist = NetlistGraph::from_nets(&nodes, &[nodes.to_vec()]);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(1), FPGALayoutCoordinate::new(2, 3));
+        solution.place_node(io(5), FPGALayoutCoordinate::new(0, 2));
+        solution.place_node(io(4), FPGALayoutCoordinate::new(0, 2));
+
+        let expected = format!(
+            "Netlist_File: top.net Netlist_ID: SHA256:{}\n\
+             Architecture_File: arch.xml\n\
+             Array size: 6 x 6 logic blocks\n\
+             \n\
+             #block name\tx\ty\tsubblk\tblock number\n\
+             #----------\t--\t--\t------\t------------\n\
+             n1\t2\t2\t0\t#0\n\
+             n4\t0\t3\t0\t#1\n\
+             n5\t0\t3\t1\t#2\n",
+            "0".repeat(64)
+        );
+        assert_eq!(solution.to_vpr_place("top.net", "arch.xml"), expected);
+    }
+
+    #[test]
+    fn utilization_counts_used_and_total_sites_per_type() {
+        // 32 IO sites on the border, a column of 6 BRAM sites at x = 10 and
+        // 54 CLB sites, the 4 empty corners don't count
+        let layout = build_simple_fpga_layout(12, 8);
+        let node = |id, macro_type| NetlistNode { id, macro_type };
+        let nodes = [
+            clb(0),
+            clb(1),
+            clb(2),
+            node(3, MacroType::BRAM),
+            node(4, MacroType::IO),
+            node(5, MacroType::IO),
+        ];
+        let netlist = NetlistGraph::from_nets(&nodes, &[nodes.to_vec()]);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        assert_eq!(solution.utilization(), 0.0);
+
+        let sites = [(1, 1), (2, 1), (3, 1), (10, 3), (0, 2), (5, 7)];
+        for (node, (x, y)) in nodes.iter().zip(sites) {
+            solution.place_node(*node, FPGALayoutCoordinate::new(x, y));
+        }
+        assert!(solution.valid(), "{:?}", solution.validate(false));
+        assert_eq!(solution.utilization(), 6.0 / 92.0);
+
+        let by_type = solution.utilization_by_type();
+        assert_eq!(by_type.len(), 3);
+        assert_eq!(by_type[&MacroType::CLB], 3.0 / 54.0);
+        assert_eq!(by_type[&MacroType::BRAM], 1.0 / 6.0);
+        assert_eq!(by_type[&MacroType::IO], 2.0 / 32.0);
+        assert!(!by_type.contains_key(&MacroType::DSP));
+    }
+
+    #[test]
+    fn net_length_histogram_buckets_a_hand_placement() {
+        let layout = build_simple_fpga_layout(10, 10);
+        let netlist = chain_netlist(6);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        let sites = [(1, 1), (2, 1), (4, 1), (7, 1), (8, 1), (8, 6)];
+        for (id, (x, y)) in sites.into_iter().enumerate() {
+            solution.place_node(clb(id as u32), FPGALayoutCoordinate::new(x, y));
+        }
+        // nets of length 1, 2, 3, 1 and 5
+        let histogram = |n_bins| solution.net_length_histogram(n_bins);
+        assert_eq!(
+            histogram(5),
+            NetLengthHistogram {
+                bins: vec![2, 1, 1, 0, 1],
+                min: 1,
+                max: 5
+            }
+        );
+        assert_eq!(histogram(2).bins, vec![4, 1]);
+        assert_eq!(histogram(1).bins, vec![5]);
+        // more bins than lengths leaves some empty
+        assert_eq!(histogram(10).bins, vec![2, 0, 1, 0, 1, 0, 0, 0, 1, 0]);
 
-        let best_delta = best_solution.cost_bb() - current_solution.cost_bb();
-        let mut delta = 0.0;
-        if best_delta < 0.0 {
-            current_solution = best_solution.clone();
-            delta = best_delta;
+        let empty = NetlistGraph::from_nets(&[clb(0)], &[]);
+        let solution = PlacementSolution::new(&layout, &empty);
+        assert_eq!(
+            solution.net_length_histogram(3),
+            NetLengthHistogram {
+                bins: vec![0, 0, 0],
+                min: 0,
+                max: 0
+            }
+        );
+    }
+
+    #[test]
+    fn ascii_render_draws_ids_over_the_fabric() {
+        let mut layout = build_simple_fpga_layout(4, 3);
+        layout.config_capacity(FPGALayoutCoordinate::new(1, 0), 2);
+        let io = |id| NetlistNode {
+            id,
+            macro_type: MacroType::IO,
+        };
+        let nodes = [clb(7), clb(1234), io(2), io(3)];
+        let netlist = NetlistGraph::from_nets(&nodes, &[nodes.to_vec()]);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(7), FPGALayoutCoordinate::new(1, 1));
+        solution.place_node(clb(1234), FPGALayoutCoordinate::new(2, 1));
+        solution.place_node(io(2), FPGALayoutCoordinate::new(1, 0));
+        solution.place_node(io(3), FPGALayoutCoordinate::new(1, 0));
+
+        // a 4 digit id doesn't fit and two nodes on one site show as *2
+        let expected = "\
+┌───┬───┬───┬───┐
+│   │*2 │ I │   │
+├───┼───┼───┼───┤
+│ I │ 7 │[C]│ I │
+├───┼───┼───┼───┤
+│   │ I │ I │   │
+└───┴───┴───┴───┘
+";
+        assert_eq!(solution.render_ascii(), expected);
+    }
+
+    #[test]
+    fn demand_svg_has_one_overlay_rect_per_loaded_site() {
+        let layout = build_simple_fpga_layout(6, 5);
+        let netlist = chain_netlist(3);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for (id, (x, y)) in [(1, 1), (3, 1), (3, 3)].into_iter().enumerate() {
+            solution.place_node(clb(id as u32), FPGALayoutCoordinate::new(x, y));
         }
+        let svg = solution.render_demand_svg(1);
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<svg").count(), 1);
+        assert_eq!(svg.matches("</svg>").count(), 1);
+        let rects = svg
+            .lines()
+            .filter(|line| line.trim_start().starts_with("<rect"))
+            .collect_vec();
+        assert!(rects.iter().all(|rect| rect.ends_with("/>")));
+
+        // the fabric draws one rect per site and the overlay one per site
+        // under a net's bounding box, (3, 1) is under both
+        let fabric = rects
+            .iter()
+            .filter(|rect| rect.contains("fill-opacity=\"0.25\""))
+            .count();
+        assert_eq!(fabric, 6 * 5);
+        let overlay = rects
+            .iter()
+            .filter(|rect| rect.contains("fill=\"red\" fill-opacity") && !rect.contains("stroke"))
+            .copied()
+            .collect_vec();
+        assert_eq!(overlay.len(), 3 + 3 - 1);
+        let loaded = solution.demand_grid(1).concat();
+        assert_eq!(
+            overlay.len(),
+            loaded.iter().filter(|demand| **demand > 0).count()
+        );
+        assert_eq!(
+            overlay
+                .iter()
+                .filter(|rect| rect.contains("fill-opacity=\"0.600\""))
+                .collect_vec(),
+            vec![&"\t<rect x=\"300\" y=\"100\" width=\"100\" height=\"100\" fill=\"red\" fill-opacity=\"0.600\"/>"]
+        );
+    }
+
+    #[test]
+    fn progress_is_reported_once_per_step() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = chain_netlist(10);
+        let mut rng = StdRng::seed_from_u64(9);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+
+        let infos = std::cell::RefCell::new(Vec::new());
+        let progress = |info: ProgressInfo| infos.borrow_mut().push(info);
+        let output = fast_sa_placer(solution, 50, 4, false, false, Some(&progress));
+        let infos = infos.into_inner();
+
+        assert_eq!(infos.len(), 50);
+        assert_eq!(
+            infos.iter().map(|info| info.step).collect_vec(),
+            (0..50).collect_vec()
+        );
+        assert!(infos.iter().all(|info| info.n_steps == 50));
+        assert!(infos
+            .iter()
+            .all(|info| (0.0..=1.0).contains(&info.acceptance_ratio)));
+        assert_eq!(
+            infos.iter().map(|info| info.temperature).collect_vec(),
+            output.temperature_per_step
+        );
+        assert_eq!(
+            infos.last().unwrap().cost,
+            output.final_solution.cost_hpwl()
+        );
+    }
+
+    #[test]
+    fn lock_nodes_rolls_back_on_a_failed_pin() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = chain_netlist(4);
+        let mut rng = StdRng::seed_from_u64(10);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        let before = solution.solution_map.clone();
+        let site = FPGALayoutCoordinate::new;
+
+        // the second pin lands on an IO site
+        let pins = [(clb(0), site(3, 3)), (clb(1), site(0, 3))];
+        assert_eq!(
+            solution.lock_nodes(&pins),
+            Err((clb(1), PlaceError::WrongSiteType(site(0, 3))))
+        );
+        assert_eq!(solution.solution_map, before);
+        assert!(solution.locked.is_empty());
+
+        let pins = [(clb(0), site(3, 3)), (clb(1), site(4, 3))];
+        solution.lock_nodes(&pins).unwrap();
+        assert_eq!(solution.solution_map[&clb(1)], site(4, 3));
+        assert_eq!(solution.locked, FxHashSet::from_iter([clb(0), clb(1)]));
+        assert!(solution.valid());
+    }
+
+    #[cfg(feature = "resvg")]
+    #[test]
+    fn svg_to_png_scales_the_longer_side_to_800_even_pixels() {
+        let dir = tempdir().unwrap();
+        for ((width, height), expected) in [((300, 200), (800, 534)), ((100, 250), (320, 800))] {
+            let svg_fp = dir.path().join("frame.svg");
+            let png_fp = dir.path().join("frame.png");
+            std::fs::write(
+                &svg_fp,
+                format!(
+                    "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\">\n\
+                     \t<rect x=\"0\" y=\"0\" width=\"10\" height=\"10\" fill=\"red\"/>\n</svg>\n",
+                    width, height, width, height
+                ),
+            )
+            .unwrap();
+            svg_to_png(&svg_fp, &png_fp);
 
-        if verbose && _i % 10 == 0 {
-            println!("Current Itteration: {:?}", _i);
-            println!("Delta Cost: {:?}", delta);
-            println!("Current Cost: {:?}", current_solution.cost_bb());
+            let png = resvg::tiny_skia::Pixmap::load_png(&png_fp).unwrap();
+            assert_eq!((png.width(), png.height()), expected);
+            assert_eq!(png.width() % 2, 0);
+            assert_eq!(png.height() % 2, 0);
+            // the background is filled white and the rect drawn scaled
+            let pixel = |x, y| png.pixel(x, y).unwrap();
+            assert_eq!(
+                (
+                    pixel(png.width() - 1, png.height() - 1).red(),
+                    pixel(png.width() - 1, png.height() - 1).green()
+                ),
+                (255, 255)
+            );
+            assert_eq!((pixel(1, 1).red(), pixel(1, 1).green()), (255, 0));
         }
     }
 
-    if render {
-        renderer.add_frame(current_solution.render_svg());
+    #[test]
+    fn try_place_at_free_swapped_and_blocked_sites() {
+        let mut layout = build_simple_fpga_layout(8, 8);
+        let site = FPGALayoutCoordinate::new;
+        layout.config_multi_type(site(4, 4), &[MacroType::CLB, MacroType::DSP]);
+        let dsp = NetlistNode {
+            id: 2,
+            macro_type: MacroType::DSP,
+        };
+        let netlist = NetlistGraph::from_nets(
+            &[clb(0), clb(1), dsp],
+            &[vec![clb(0), clb(1)], vec![clb(1), dsp]],
+        );
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(0), site(1, 1));
+        solution.place_node(clb(1), site(2, 2));
+        solution.place_node(dsp, site(4, 4));
+
+        // free site
+        assert_eq!(solution.try_place_at(clb(0), site(3, 3)), Ok(()));
+        assert_eq!(solution.solution_map[&clb(0)], site(3, 3));
+
+        // occupied by a node of the same type, the two swap
+        assert_eq!(solution.try_place_at(clb(0), site(2, 2)), Ok(()));
+        assert_eq!(solution.solution_map[&clb(0)], site(2, 2));
+        assert_eq!(solution.solution_map[&clb(1)], site(3, 3));
+
+        // occupied by a node of another type
+        assert_eq!(
+            solution.try_place_at(clb(0), site(4, 4)),
+            Err(PlaceError::Occupied(dsp))
+        );
+        // a site that can't host the type at all
+        assert_eq!(
+            solution.try_place_at(clb(0), site(0, 3)),
+            Err(PlaceError::WrongSiteType(site(0, 3)))
+        );
+        assert_eq!(solution.solution_map[&clb(0)], site(2, 2));
+        assert!(solution.valid());
     }
 
-    PlacerOutput {
-        initial_solution: initial_solution.clone(),
-        final_solution: current_solution.clone(),
-        x_steps,
-        y_cost,
-        renderer: if render { Some(renderer) } else { None },
+    #[test]
+    fn try_place_at_keeps_site_rules() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = chain_netlist(3);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        let site = FPGALayoutCoordinate::new;
+        solution.place_node(clb(0), site(1, 1));
+        solution.place_node(clb(1), site(3, 3));
+        solution.place_node(clb(2), site(5, 5));
+
+        solution.forbid_region(2, 2, 2, 2);
+        assert_eq!(
+            solution.try_place_at(clb(0), site(2, 2)),
+            Err(PlaceError::NotAllowed(site(2, 2)))
+        );
+
+        // swapping 0 onto 1's site would put 1 on the forbidden site
+        solution.place_node(clb(0), site(2, 2));
+        assert_eq!(
+            solution.try_place_at(clb(0), site(3, 3)),
+            Err(PlaceError::Occupied(clb(1)))
+        );
+        solution.place_node(clb(0), site(1, 1));
+        solution.clear_forbidden();
+
+        solution.region = Some(Region::new(1, 1, 5, 5));
+        assert_eq!(
+            solution.try_place_at(clb(0), site(6, 6)),
+            Err(PlaceError::NotAllowed(site(6, 6)))
+        );
+
+        solution.min_spacing.insert(MacroType::CLB, 2);
+        assert_eq!(
+            solution.try_place_at(clb(0), site(3, 4)),
+            Err(PlaceError::TooClose(site(3, 4)))
+        );
+        // a swap doesn't count the two nodes against each other
+        assert_eq!(solution.try_place_at(clb(0), site(3, 3)), Ok(()));
+        assert_eq!(solution.solution_map[&clb(1)], site(1, 1));
+        assert!(solution.valid());
     }
 }
@@ -0,0 +1,349 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use super::fpga_layout::*;
+use super::netlist::*;
+use super::placer::*;
+
+// nodes that move as one rigid block, like a relatively placed macro
+// every member sits at a fixed (dx, dy) offset from the anchor, which is the
+// first member at (0, 0)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelativeGroup {
+    pub members: Vec<(NetlistNode, i32, i32)>,
+}
+
+impl RelativeGroup {
+    pub fn new(anchor: NetlistNode, offsets: &[(NetlistNode, i32, i32)]) -> RelativeGroup {
+        let mut members = vec![(anchor, 0, 0)];
+        members.extend_from_slice(offsets);
+
+        let nodes = members
+            .iter()
+            .map(|(node, _, _)| *node)
+            .collect::<FxHashSet<_>>();
+        let cells = members
+            .iter()
+            .map(|(_, dx, dy)| (*dx, *dy))
+            .collect::<FxHashSet<_>>();
+        assert!(
+            nodes.len() == members.len(),
+            "group members must be distinct"
+        );
+        assert!(
+            cells.len() == members.len(),
+            "group members must not overlap"
+        );
+
+        RelativeGroup { members }
+    }
+
+    // the nodes side by side in a row, left to right
+    pub fn horizontal(nodes: &[NetlistNode]) -> RelativeGroup {
+        assert!(!nodes.is_empty(), "a group needs at least one node");
+        let offsets = nodes
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, node)| (*node, i as i32, 0))
+            .collect::<Vec<_>>();
+        RelativeGroup::new(nodes[0], &offsets)
+    }
+
+    pub fn anchor(&self) -> NetlistNode {
+        self.members[0].0
+    }
+
+    pub fn contains(&self, node: &NetlistNode) -> bool {
+        self.members.iter().any(|(member, _, _)| member == node)
+    }
+
+    // where each member lands with the anchor at the given site, None if part
+    // of the group would hang off the low edge of the layout
+    pub fn member_locations(
+        &self,
+        anchor: FPGALayoutCoordinate,
+    ) -> Option<Vec<(NetlistNode, FPGALayoutCoordinate)>> {
+        self.members
+            .iter()
+            .map(|(node, dx, dy)| {
+                let x = anchor.x.checked_add_signed(*dx)?;
+                let y = anchor.y.checked_add_signed(*dy)?;
+                Some((*node, FPGALayoutCoordinate::new(x, y)))
+            })
+            .collect()
+    }
+}
+
+impl<'a> PlacementSolution<'a> {
+    // index into groups of the group holding the node
+    pub fn group_of(&self, node: &NetlistNode) -> Option<usize> {
+        self.groups.iter().position(|group| group.contains(node))
+    }
+
+    // register a group and move it to the legal anchor closest to where its
+    // anchor currently sits, the first legal anchor if the anchor is unplaced
+    pub fn add_relative_group(&mut self, group: RelativeGroup) -> Result<usize, LegalizeError> {
+        if let Some((node, _, _)) = group
+            .members
+            .iter()
+            .find(|(node, _, _)| self.group_of(node).is_some())
+        {
+            return Err(LegalizeError::AlreadyGrouped(*node));
+        }
+
+        let anchor = group.anchor();
+        let current_location = self.solution_map.get(&anchor).copied();
+        let location = self
+            .nearest_group_anchor(&group, current_location)
+            .ok_or(LegalizeError::NoFreeSite(anchor))?;
+
+        self.groups.push(group);
+        let group_idx = self.groups.len() - 1;
+        self.move_group(group_idx, location);
+
+        Ok(group_idx)
+    }
+
+    // anchor sites where every member of the group lands on a site that can
+    // host it, isn't taken by a node outside the group and keeps min spacing
+    // to the nodes outside the group
+    pub fn group_anchor_sites(&self, group: &RelativeGroup) -> Vec<FPGALayoutCoordinate> {
+        let occupied = self
+            .solution_map
+            .iter()
+            .filter(|(node, _)| !group.contains(node))
+            .map(|(_, location)| *location)
+            .collect::<FxHashSet<_>>();
+        let group_nodes = group
+            .members
+            .iter()
+            .map(|(node, _, _)| *node)
+            .collect::<Vec<_>>();
+        let mut blocked = FxHashMap::default();
+        for node in &group_nodes {
+            blocked
+                .entry(node.macro_type)
+                .or_insert_with(|| self.spacing_blocked_sites(node.macro_type, &group_nodes));
+        }
+
+        let mut anchor_sites = Vec::new();
+        for x in 0..self.layout.width {
+            for y in 0..self.layout.height {
+                let anchor = FPGALayoutCoordinate::new(x, y);
+                let member_locations = match group.member_locations(anchor) {
+                    Some(member_locations) => member_locations,
+                    None => continue,
+                };
+
+                let fits = member_locations.iter().all(|(node, location)| {
+                    !occupied.contains(location)
+                        && !blocked[&node.macro_type].contains(location)
+                        && self.site_accepts(node, location)
+                        && self.region.is_none_or(|region| region.contains(location))
+                        && !self.forbidden.contains(location)
+                });
+                if fits {
+                    anchor_sites.push(anchor);
+                }
+            }
+        }
+
+        anchor_sites
+    }
+
+    // the anchor site from group_anchor_sites closest to the location, the
+    // first one without a location
+    pub fn nearest_group_anchor(
+        &self,
+        group: &RelativeGroup,
+        location: Option<FPGALayoutCoordinate>,
+    ) -> Option<FPGALayoutCoordinate> {
+        let anchor_sites = self.group_anchor_sites(group);
+        match location {
+            Some(location) => anchor_sites
+                .into_iter()
+                .min_by_key(|site| self.layout.distance(site, &location)),
+            None => anchor_sites.first().copied(),
+        }
+    }
+
+    // whether any member of the group is locked, such a group never moves
+    pub fn group_is_locked(&self, group_idx: usize) -> bool {
+        self.groups[group_idx]
+            .members
+            .iter()
+            .any(|(node, _, _)| self.locked.contains(node))
+    }
+
+    // place every member of the group relative to the anchor site, the caller
+    // makes sure the site came from group_anchor_sites, a group with a locked
+    // member stays where it is
+    pub fn move_group(&mut self, group_idx: usize, anchor: FPGALayoutCoordinate) {
+        if self.group_is_locked(group_idx) {
+            return;
+        }
+        let member_locations = self.groups[group_idx]
+            .member_locations(anchor)
+            .expect("group does not fit at this anchor");
+        for (node, location) in member_locations {
+            self.place_node(node, location);
+        }
+    }
+
+    pub fn action_move_group_with_rng<R: Rng + ?Sized>(&mut self, group_idx: usize, rng: &mut R) {
+        if self.group_is_locked(group_idx) {
+            return;
+        }
+        let anchor_sites = self.group_anchor_sites(&self.groups[group_idx]);
+        if let Some(anchor) = anchor_sites.choose(rng) {
+            self.move_group(group_idx, *anchor);
+        }
+    }
+
+    // anchors of the groups whose members no longer sit at their offsets
+    pub fn broken_groups(&self) -> Vec<NetlistNode> {
+        self.groups
+            .iter()
+            .filter(|group| {
+                let anchor = match self.solution_map.get(&group.anchor()) {
+                    Some(anchor) => *anchor,
+                    None => return false,
+                };
+                match group.member_locations(anchor) {
+                    Some(member_locations) => !member_locations
+                        .iter()
+                        .all(|(node, location)| self.solution_map.get(node) == Some(location)),
+                    None => true,
+                }
+            })
+            .map(|group| group.anchor())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn clb(id: u32) -> NetlistNode {
+        NetlistNode {
+            id,
+            macro_type: MacroType::CLB,
+        }
+    }
+
+    #[test]
+    fn legalize_moves_a_group_as_a_unit() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let nodes = (0..5).map(clb).collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, std::slice::from_ref(&nodes));
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for (i, node) in nodes.iter().enumerate() {
+            solution.place_node(*node, FPGALayoutCoordinate::new(1 + i as u32, 1));
+        }
+        let group_idx = solution
+            .add_relative_group(RelativeGroup::horizontal(&nodes[..3]))
+            .unwrap();
+        assert_eq!(
+            solution.add_relative_group(RelativeGroup::horizontal(&nodes[2..4])),
+            Err(LegalizeError::AlreadyGrouped(nodes[2]))
+        );
+
+        // node 3 lands on the middle member and the anchor's site is forbidden
+        solution.place_node(nodes[3], FPGALayoutCoordinate::new(2, 1));
+        solution.forbid_region(1, 1, 1, 1);
+        solution.legalize().unwrap();
+        assert!(solution.valid(), "{:?}", solution.validate(false));
+        let anchor = solution.solution_map[&nodes[0]];
+        assert_ne!(anchor, FPGALayoutCoordinate::new(1, 1));
+        for (node, location) in solution.groups[group_idx].member_locations(anchor).unwrap() {
+            assert_eq!(solution.solution_map[&node], location);
+        }
+
+        // the group is whole again, so legalize_min_displacement only moves the stray node
+        solution.place_node(nodes[4], anchor);
+        solution.legalize_min_displacement().unwrap();
+        assert!(solution.valid(), "{:?}", solution.validate(false));
+        assert_eq!(solution.solution_map[&nodes[0]], anchor);
+    }
+    #[test]
+    fn a_moved_group_keeps_its_offsets() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let nodes = (0..4).map(clb).collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, std::slice::from_ref(&nodes));
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for (i, node) in nodes.iter().enumerate() {
+            solution.place_node(*node, FPGALayoutCoordinate::new(1 + i as u32, 1));
+        }
+        let group_idx = solution
+            .add_relative_group(RelativeGroup::horizontal(&nodes[..3]))
+            .unwrap();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut anchors = FxHashSet::default();
+        for _ in 0..20 {
+            solution.action_move_group_with_rng(group_idx, &mut rng);
+            // the annealer's move picks a member and moves its whole group
+            solution.action_move_with_rng(&mut rng);
+            assert!(solution.valid(), "{:?}", solution.validate(false));
+            anchors.insert(solution.solution_map[&nodes[0]]);
+        }
+        assert!(anchors.len() > 1);
+
+        // a locked member pins the whole group
+        solution.locked.insert(nodes[1]);
+        let pinned = solution.solution_map.clone();
+        for _ in 0..10 {
+            solution.action_move_group_with_rng(group_idx, &mut rng);
+        }
+        let free_anchor = solution.group_anchor_sites(&solution.groups[group_idx])[0];
+        solution.move_group(group_idx, free_anchor);
+        assert_eq!(solution.solution_map, pinned);
+    }
+
+    #[test]
+    fn group_anchors_keep_min_spacing() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let nodes = (0..3).map(clb).collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, std::slice::from_ref(&nodes));
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(nodes[2], FPGALayoutCoordinate::new(4, 4));
+        let group = RelativeGroup::horizontal(&nodes[..2]);
+        let site = |(x, y)| FPGALayoutCoordinate::new(x, y);
+        assert!(solution.group_anchor_sites(&group).contains(&site((3, 3))));
+
+        solution.min_spacing.insert(MacroType::CLB, 2);
+        let anchors = solution.group_anchor_sites(&group);
+        // neither member may sit next to node 2
+        for anchor in [(2, 4), (3, 4), (4, 3), (4, 5), (5, 4), (3, 3), (3, 5)] {
+            assert!(!anchors.contains(&site(anchor)));
+        }
+        assert!(anchors.contains(&site((1, 4))));
+        assert!(anchors.contains(&site((5, 5))));
+    }
+
+    #[test]
+    fn nearest_group_anchor_wraps_on_a_torus() {
+        let planar = build_simple_fpga_layout(8, 8);
+        let mut torus = planar.clone();
+        torus.config_topology(Topology::Torus);
+        let netlist = NetlistGraph::from_nets(&[clb(0)], &[]);
+        let group = RelativeGroup::horizontal(&[clb(0)]);
+        let location = Some(FPGALayoutCoordinate::new(7, 2));
+
+        // only columns 1 to 4 are left, (1, 2) is 2 steps from (7, 2) across the wrap
+        for (layout, nearest) in [(&planar, (4, 2)), (&torus, (1, 2))] {
+            let mut solution = PlacementSolution::new(layout, &netlist);
+            solution.forbid_region(5, 0, 6, 7);
+            assert_eq!(
+                solution.nearest_group_anchor(&group, location),
+                Some(FPGALayoutCoordinate::new(nearest.0, nearest.1))
+            );
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use super::fpga_layout::*;
+use super::netlist::*;
+use super::placer::*;
+
+use rustc_hash::FxHashMap;
+
+// placed nodes bucketed into bucket_size x bucket_size blocks of sites, a
+// range query only visits the blocks its range touches
+// small buckets hold fewer nodes to filter but a query visits more of them,
+// large buckets are the other way around, a bucket around the usual query
+// radius keeps both low
+#[derive(Debug, Clone)]
+pub struct SpatialIndex {
+    bucket_size: u32,
+    buckets: FxHashMap<(u32, u32), Vec<(NetlistNode, FPGALayoutCoordinate)>>,
+}
+
+impl SpatialIndex {
+    pub fn new(
+        bucket_size: u32,
+        solution_map: &FxHashMap<NetlistNode, FPGALayoutCoordinate>,
+    ) -> Self {
+        assert!(bucket_size > 0, "bucket_size must be positive");
+        let mut index = SpatialIndex {
+            bucket_size,
+            buckets: FxHashMap::default(),
+        };
+        for (node, location) in solution_map {
+            index.insert(*node, *location);
+        }
+        index
+    }
+
+    pub fn bucket_size(&self) -> u32 {
+        self.bucket_size
+    }
+
+    fn bucket(&self, location: &FPGALayoutCoordinate) -> (u32, u32) {
+        (location.x / self.bucket_size, location.y / self.bucket_size)
+    }
+
+    pub fn insert(&mut self, node: NetlistNode, location: FPGALayoutCoordinate) {
+        let bucket = self.bucket(&location);
+        self.buckets
+            .entry(bucket)
+            .or_default()
+            .push((node, location));
+    }
+
+    pub fn remove(&mut self, node: NetlistNode, location: FPGALayoutCoordinate) {
+        let bucket = self.bucket(&location);
+        if let Some(entries) = self.buckets.get_mut(&bucket) {
+            if let Some(idx) = entries.iter().position(|entry| *entry == (node, location)) {
+                entries.swap_remove(idx);
+            }
+        }
+    }
+
+    // buckets along an axis of the given length holding positions within
+    // radius of pos, on a torus the range wraps around the ends
+    fn axis_buckets(&self, topology: Topology, pos: u32, radius: u32, length: u32) -> Vec<u32> {
+        let (low, high) = (pos as i64 - radius as i64, pos as i64 + radius as i64);
+        let ranges = match topology {
+            Topology::Planar => vec![(low.max(0), high.min(length as i64 - 1))],
+            Topology::Torus if 2 * radius as i64 + 1 >= length as i64 => {
+                vec![(0, length as i64 - 1)]
+            }
+            Topology::Torus if low < 0 => vec![(0, high), (low + length as i64, length as i64 - 1)],
+            Topology::Torus if high >= length as i64 => {
+                vec![(low, length as i64 - 1), (0, high - length as i64)]
+            }
+            Topology::Torus => vec![(low, high)],
+        };
+
+        let bucket_size = self.bucket_size as i64;
+        let mut buckets = ranges
+            .into_iter()
+            .flat_map(|(low, high)| low / bucket_size..=high / bucket_size)
+            .map(|bucket| bucket.min(u32::MAX as i64) as u32)
+            .collect::<Vec<_>>();
+        buckets.sort_unstable();
+        buckets.dedup();
+        buckets
+    }
+
+    // nodes within a manhattan distance of radius from the coordinate as the
+    // layout measures it, sorted by id
+    pub fn nodes_near(
+        &self,
+        layout: &FPGALayout,
+        coord: &FPGALayoutCoordinate,
+        radius: u32,
+    ) -> Vec<NetlistNode> {
+        let bxs = self.axis_buckets(layout.topology, coord.x, radius, layout.width);
+        let bys = self.axis_buckets(layout.topology, coord.y, radius, layout.height);
+
+        let mut nodes = Vec::new();
+        for bx in &bxs {
+            for by in &bys {
+                let Some(entries) = self.buckets.get(&(*bx, *by)) else {
+                    continue;
+                };
+                nodes.extend(
+                    entries
+                        .iter()
+                        .filter(|(_, location)| within(layout, location, coord, radius))
+                        .map(|(node, _)| *node),
+                );
+            }
+        }
+        nodes.sort_by_key(|node| node.id);
+        nodes
+    }
+}
+
+// whether the two sites are within radius of each other, summed as u64 so a
+// large radius can't overflow
+fn within(
+    layout: &FPGALayout,
+    a: &FPGALayoutCoordinate,
+    b: &FPGALayoutCoordinate,
+    radius: u32,
+) -> bool {
+    let dx = layout.topology.axis_distance(a.x, b.x, layout.width) as u64;
+    let dy = layout.topology.axis_distance(a.y, b.y, layout.height) as u64;
+    dx + dy <= radius as u64
+}
+
+impl<'a> PlacementSolution<'a> {
+    // index the current placement, from then on every move made through the
+    // solution's methods keeps it up to date like the net cache
+    pub fn enable_spatial_index(&mut self, bucket_size: u32) {
+        self.spatial_index = Some(SpatialIndex::new(bucket_size, &self.solution_map));
+    }
+
+    // placed nodes within a manhattan distance of radius from the coordinate
+    // as the layout measures it, sorted by id, scans every node when the spatial index isn't enabled
+    pub fn nodes_near(&self, coord: &FPGALayoutCoordinate, radius: u32) -> Vec<NetlistNode> {
+        if let Some(spatial_index) = &self.spatial_index {
+            return spatial_index.nodes_near(self.layout, coord, radius);
+        }
+
+        let mut nodes = self
+            .solution_map
+            .iter()
+            .filter(|(_, location)| within(self.layout, location, coord, radius))
+            .map(|(node, _)| *node)
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|node| node.id);
+        nodes
+    }
+
+    // placed nodes within a manhattan distance of r_limit that try_swap would
+    // accept for the node: same macro type, each able to sit on the other's
+    // site, and neither grouped nor locked, nodes sharing the node's own site
+    // are left out since swapping with them changes nothing, sorted by id
+    pub fn candidate_swaps(&self, node: &NetlistNode, r_limit: u32) -> Vec<NetlistNode> {
+        let location = match self.solution_map.get(node) {
+            Some(location) => *location,
+            None => return Vec::new(),
+        };
+        if self.group_of(node).is_some() || self.locked.contains(node) {
+            return Vec::new();
+        }
+
+        self.nodes_near(&location, r_limit)
+            .into_iter()
+            .filter(|other| other.macro_type == node.macro_type)
+            .filter(|other| {
+                let other_location = self.solution_map[other];
+                other_location != location
+                    && self.group_of(other).is_none()
+                    && !self.locked.contains(other)
+                    && self.site_accepts(node, &other_location)
+                    && self.site_accepts(other, &location)
+                    && !self.forbidden.contains(&other_location)
+                    && !self.forbidden.contains(&location)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn nodes_near_returns_exactly_the_nodes_in_range() {
+        let layout = build_simple_fpga_layout(16, 16);
+        let nodes = (0..60)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: MacroType::CLB,
+            })
+            .collect::<Vec<_>>();
+        let nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+        solution.enable_spatial_index(3);
+        // moves made after the index is built have to show up in it
+        solution.perturb(100, &mut rng);
+
+        for (x, y) in [(0, 0), (4, 7), (8, 8), (15, 2)] {
+            let coord = FPGALayoutCoordinate::new(x, y);
+            for radius in [0, 1, 2, 3, 5, 30] {
+                let mut expected = solution
+                    .solution_map
+                    .iter()
+                    .filter(|(_, location)| location.manhattan_distance(&coord) <= radius as u64)
+                    .map(|(node, _)| *node)
+                    .collect::<Vec<_>>();
+                expected.sort_by_key(|node| node.id);
+                assert_eq!(solution.nodes_near(&coord, radius), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn nodes_near_wraps_on_a_torus() {
+        let mut layout = build_simple_fpga_layout(16, 16);
+        layout.config_topology(Topology::Torus);
+        let nodes = (0..60)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: MacroType::CLB,
+            })
+            .collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, &[]);
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+
+        for indexed in [false, true] {
+            if indexed {
+                solution.enable_spatial_index(3);
+            }
+            for (x, y) in [(0, 0), (1, 14), (8, 8), (15, 2)] {
+                let coord = FPGALayoutCoordinate::new(x, y);
+                for radius in [0, 1, 2, 3, 5, 8, 30, u32::MAX] {
+                    let mut expected = solution
+                        .solution_map
+                        .iter()
+                        .filter(|(_, location)| layout.distance(location, &coord) <= radius)
+                        .map(|(node, _)| *node)
+                        .collect::<Vec<_>>();
+                    expected.sort_by_key(|node| node.id);
+                    assert_eq!(solution.nodes_near(&coord, radius), expected);
+                }
+            }
+        }
+
+        // (1, 1) and (14, 14) are 6 apart across both edges
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(nodes[0], FPGALayoutCoordinate::new(14, 14));
+        solution.enable_spatial_index(4);
+        let coord = FPGALayoutCoordinate::new(1, 1);
+        assert!(solution.nodes_near(&coord, 5).is_empty());
+        assert_eq!(solution.nodes_near(&coord, 6), vec![nodes[0]]);
+    }
+
+    #[test]
+    fn candidate_swaps_within_zero_and_unlimited_radius() {
+        let layout = build_simple_fpga_layout(16, 16);
+        let nodes = (0..10)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: if id < 8 {
+                    MacroType::CLB
+                } else {
+                    MacroType::BRAM
+                },
+            })
+            .collect::<Vec<_>>();
+        let nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+
+        for indexed in [false, true] {
+            if indexed {
+                solution.enable_spatial_index(3);
+            }
+            for node in &nodes {
+                assert!(solution.candidate_swaps(node, 0).is_empty());
+                let same_type = nodes
+                    .iter()
+                    .filter(|other| other.macro_type == node.macro_type && *other != node)
+                    .copied()
+                    .collect::<Vec<_>>();
+                assert_eq!(solution.candidate_swaps(node, 100), same_type);
+            }
+        }
+    }
+}
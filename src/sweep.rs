@@ -0,0 +1,354 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use super::fpga_layout::*;
+use super::netlist::*;
+use super::placer::*;
+
+// anneal once per lambda, all from the same initial placement, and return the
+// final metrics of each run in the order of lambdas
+// config.lambda is overridden, config.seed (if set) seeds the initial placement
+// and every run so the sweep is reproducible
+pub fn pareto_sweep(
+    layout: &FPGALayout,
+    netlist: &NetlistGraph,
+    lambdas: &[f32],
+    config: &PlacerConfig,
+) -> Result<Vec<(f32, PlacementMetrics)>, InitialPlacementError> {
+    let initial_solution = match config.seed {
+        Some(seed) => gen_initial_placement_with_rng(
+            layout,
+            netlist,
+            InitialPlacerMethod::Random,
+            &mut StdRng::seed_from_u64(seed),
+        ),
+        None => gen_initial_placement(layout, netlist, InitialPlacerMethod::Random),
+    }?;
+
+    Ok(lambdas
+        .par_iter()
+        .map(|&lambda| {
+            let config = PlacerConfig {
+                lambda,
+                ..config.clone()
+            };
+            let placer_output = Placer::new(initial_solution.clone(), config).run(None);
+            (lambda, placer_output.metrics)
+        })
+        .collect())
+}
+
+// the sweep results no other result beats on both hpwl and timing, sorted by hpwl
+pub fn pareto_front(results: &[(f32, PlacementMetrics)]) -> Vec<(f32, PlacementMetrics)> {
+    let mut front = results
+        .iter()
+        .filter(|(_, metrics)| {
+            !results.iter().any(|(_, other)| {
+                other.hpwl <= metrics.hpwl
+                    && other.timing <= metrics.timing
+                    && (other.hpwl < metrics.hpwl || other.timing < metrics.timing)
+            })
+        })
+        .copied()
+        .collect::<Vec<_>>();
+    front.sort_by(|(_, a), (_, b)| a.hpwl.total_cmp(&b.hpwl));
+    front
+}
+
+// one row per run: lambda, hpwl, timing, max_net, objective
+pub fn write_sweep_csv(
+    path: impl AsRef<std::path::Path>,
+    results: &[(f32, PlacementMetrics)],
+) -> Result<(), csv::Error> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["lambda", "hpwl", "timing", "max_net", "objective"])?;
+    for (lambda, metrics) in results {
+        wtr.write_record(&[
+            lambda.to_string(),
+            metrics.hpwl.to_string(),
+            metrics.timing.to_string(),
+            metrics.max_net.to_string(),
+            metrics.objective.to_string(),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// how consistently reruns with different seeds place each node
+#[derive(Debug, Clone, PartialEq)]
+pub struct StabilityReport {
+    // the seeds of the runs were drawn from a StdRng seeded with this
+    pub base_seed: u64,
+    // seed of every run, used for both its initial placement and its placer
+    pub seeds: Vec<u64>,
+    // (node, variance of x, variance of y) over the runs, sorted by node id
+    pub node_variance: Vec<(NetlistNode, f32, f32)>,
+    // mean over nodes of sqrt(var x + var y), the rms distance of a node from
+    // its mean location, 0 when every run puts every node on the same site
+    pub mean_deviation: f32,
+    // 1 / (1 + mean_deviation), 1.0 for fully repeatable placements and
+    // towards 0 the more the runs disagree
+    pub stability: f32,
+}
+
+// anneal k times from a fresh random initial placement each, with seeds drawn
+// from config.seed (or a random base seed when it's None, reported so the runs
+// can be repeated), and measure the spread of every node's final location
+// the variance is over raw coordinates, so on a torus a node flipping between
+// two sides of the wrap counts as unstable
+pub fn placement_stability(
+    layout: &FPGALayout,
+    netlist: &NetlistGraph,
+    k: usize,
+    config: &PlacerConfig,
+) -> Result<StabilityReport, InitialPlacementError> {
+    let (base_seed, seeds) = run_seeds(k, config);
+    let final_solutions = seeds
+        .par_iter()
+        .map(|&seed| {
+            let initial_solution = gen_initial_placement_with_rng(
+                layout,
+                netlist,
+                InitialPlacerMethod::Random,
+                &mut StdRng::seed_from_u64(seed),
+            )?;
+            Ok(anneal_with_seed(initial_solution, config, seed))
+        })
+        .collect::<Result<Vec<_>, InitialPlacementError>>()?;
+
+    Ok(stability_report(
+        netlist,
+        base_seed,
+        seeds,
+        &final_solutions,
+    ))
+}
+
+// placement_stability with every run annealing from a copy of initial_solution
+// rather than a fresh random placement, so its locks, region and forbidden
+// sites hold in every run and only the placer seed differs
+pub fn placement_stability_from(
+    initial_solution: &PlacementSolution,
+    k: usize,
+    config: &PlacerConfig,
+) -> StabilityReport {
+    let (base_seed, seeds) = run_seeds(k, config);
+    let final_solutions = seeds
+        .par_iter()
+        .map(|&seed| anneal_with_seed(initial_solution.clone(), config, seed))
+        .collect::<Vec<_>>();
+
+    stability_report(initial_solution.netlist, base_seed, seeds, &final_solutions)
+}
+
+// the base seed and the k run seeds drawn from it
+fn run_seeds(k: usize, config: &PlacerConfig) -> (u64, Vec<u64>) {
+    assert!(k > 0, "need at least one run");
+
+    let base_seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut seed_rng = StdRng::seed_from_u64(base_seed);
+    let seeds = (0..k).map(|_| seed_rng.gen()).collect();
+    (base_seed, seeds)
+}
+
+fn anneal_with_seed<'a>(
+    initial_solution: PlacementSolution<'a>,
+    config: &PlacerConfig,
+    seed: u64,
+) -> PlacementSolution<'a> {
+    let config = PlacerConfig {
+        seed: Some(seed),
+        verbose: false,
+        render: false,
+        ..config.clone()
+    };
+    Placer::new(initial_solution, config)
+        .run(None)
+        .final_solution
+}
+
+fn stability_report(
+    netlist: &NetlistGraph,
+    base_seed: u64,
+    seeds: Vec<u64>,
+    final_solutions: &[PlacementSolution],
+) -> StabilityReport {
+    let node_variance = netlist
+        .all_nodes_sorted()
+        .into_iter()
+        .map(|node| {
+            let locations = final_solutions
+                .iter()
+                .filter_map(|solution| solution.solution_map.get(node))
+                .collect::<Vec<_>>();
+            let n = locations.len().max(1) as f32;
+            let mean_x = locations
+                .iter()
+                .map(|location| location.x as f32)
+                .sum::<f32>()
+                / n;
+            let mean_y = locations
+                .iter()
+                .map(|location| location.y as f32)
+                .sum::<f32>()
+                / n;
+            let var_x = locations
+                .iter()
+                .map(|location| (location.x as f32 - mean_x).powi(2))
+                .sum::<f32>()
+                / n;
+            let var_y = locations
+                .iter()
+                .map(|location| (location.y as f32 - mean_y).powi(2))
+                .sum::<f32>()
+                / n;
+            (*node, var_x, var_y)
+        })
+        .collect::<Vec<_>>();
+
+    let mean_deviation = node_variance
+        .iter()
+        .map(|(_, var_x, var_y)| (var_x + var_y).sqrt())
+        .sum::<f32>()
+        / node_variance.len().max(1) as f32;
+
+    StabilityReport {
+        base_seed,
+        seeds,
+        node_variance,
+        mean_deviation,
+        stability: 1.0 / (1.0 + mean_deviation),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(hpwl: f32, timing: f32) -> PlacementMetrics {
+        PlacementMetrics {
+            hpwl,
+            max_net: 0.0,
+            beta: 0.0,
+            timing,
+            lambda: 0.0,
+            objective: hpwl,
+            lower_bound_ratio: 1.0,
+            utilization: 0.5,
+        }
+    }
+
+    fn chain_netlist(n: u32) -> NetlistGraph {
+        let nodes = (0..n)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: MacroType::CLB,
+            })
+            .collect::<Vec<_>>();
+        let nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        NetlistGraph::from_nets(&nodes, &nets)
+    }
+
+    #[test]
+    fn pareto_front_drops_dominated_points() {
+        let results = [
+            (0.0, metrics(10.0, 9.0)),
+            // beaten on both by the lambda 0.5 run
+            (0.25, metrics(14.0, 8.0)),
+            (0.5, metrics(12.0, 6.0)),
+            (1.0, metrics(20.0, 4.0)),
+            // ties the lambda 1 run on timing with a longer hpwl
+            (2.0, metrics(22.0, 4.0)),
+        ];
+        let lambdas = pareto_front(&results)
+            .iter()
+            .map(|(lambda, _)| *lambda)
+            .collect::<Vec<_>>();
+        assert_eq!(lambdas, vec![0.0, 0.5, 1.0]);
+
+        // a NaN hpwl is dominated by nothing and sorts last instead of panicking
+        let with_nan = [(0.0, metrics(f32::NAN, 1.0)), (1.0, metrics(5.0, 2.0))];
+        assert_eq!(pareto_front(&with_nan).len(), 2);
+    }
+
+    #[test]
+    fn pareto_sweep_returns_one_result_per_lambda_in_order() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = chain_netlist(10);
+        let config = PlacerConfig {
+            n_steps: 50,
+            seed: Some(5),
+            ..Default::default()
+        };
+        let lambdas = [2.0, 0.0, 0.5, 1.0];
+        let results = pareto_sweep(&layout, &netlist, &lambdas, &config).unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|(lambda, _)| *lambda)
+                .collect::<Vec<_>>(),
+            lambdas
+        );
+        for (lambda, metrics) in &results {
+            assert_eq!(metrics.lambda, *lambda);
+        }
+    }
+
+    #[test]
+    fn stability_runs_repeat_from_the_base_seed() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = chain_netlist(8);
+        let config = PlacerConfig {
+            n_steps: 50,
+            seed: Some(6),
+            ..Default::default()
+        };
+        let report = placement_stability(&layout, &netlist, 3, &config).unwrap();
+        assert_eq!(report.base_seed, 6);
+        assert_eq!(report.seeds.len(), 3);
+        assert_eq!(report.node_variance.len(), 8);
+        assert_eq!(
+            placement_stability(&layout, &netlist, 3, &config).unwrap(),
+            report
+        );
+    }
+
+    #[test]
+    fn a_fully_locked_netlist_has_zero_variance() {
+        let layout = build_simple_fpga_layout(8, 8);
+        let netlist = chain_netlist(8);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        for (node, (x, y)) in netlist.all_nodes_sorted().into_iter().zip([
+            (1, 1),
+            (6, 6),
+            (2, 5),
+            (5, 2),
+            (1, 6),
+            (6, 1),
+            (3, 3),
+            (4, 4),
+        ]) {
+            solution
+                .lock_node(*node, FPGALayoutCoordinate::new(x, y))
+                .unwrap();
+        }
+        let config = PlacerConfig {
+            n_steps: 50,
+            initial_temperature: 5.0,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let report = placement_stability_from(&solution, 4, &config);
+        assert!(report
+            .node_variance
+            .iter()
+            .all(|(_, var_x, var_y)| *var_x == 0.0 && *var_y == 0.0));
+        assert_eq!(report.mean_deviation, 0.0);
+        assert_eq!(report.stability, 1.0);
+    }
+}
@@ -0,0 +1,182 @@
+// parallel tempering: one annealing chain per temperature, each held at its
+// temperature instead of cooling, run side by side on rayon and every
+// swap_interval steps neighboring chains get the chance to trade placements
+//
+// a swap between chain i at temperature T_i with cost E_i and chain j at T_j
+// with cost E_j is accepted with the metropolis probability
+//
+//   min(1, exp((1 / T_i - 1 / T_j) * (E_i - E_j)))
+//
+// so a better placement found by a hot chain always moves down to the colder
+// one, and a worse one moves down only now and then, which keeps every chain
+// sampling its own temperature while letting the cold chains escape minima
+// through the hot ones
+
+use rand::Rng;
+use rayon::prelude::*;
+
+use super::placer::*;
+
+pub struct TemperingOutput<'a> {
+    pub initial_solution: PlacementSolution<'a>,
+    // the current placement of the best chain
+    pub final_solution: PlacementSolution<'a>,
+    // lowest cost placement any chain reached and its cost, which can be
+    // better than final_solution when a chain later moved away from it
+    pub best_solution: PlacementSolution<'a>,
+    pub best_cost: f32,
+    pub temperatures: Vec<f32>,
+    // index into temperatures of the chain that ended with the lowest cost
+    pub best_chain: usize,
+    // cost of the best chain at every step
+    pub x_steps: Vec<u32>,
+    pub y_cost: Vec<f32>,
+    // per pair of neighboring chains, indexed by the colder chain of the pair
+    pub swap_attempts: Vec<u32>,
+    pub swaps_accepted: Vec<u32>,
+}
+
+pub fn parallel_tempering_placer<'a>(
+    initial_solution: PlacementSolution<'a>,
+    temps: &[f32],
+    n_steps: u32,
+    swap_interval: u32,
+) -> TemperingOutput<'a> {
+    parallel_tempering_placer_with_rng(
+        initial_solution,
+        temps,
+        n_steps,
+        swap_interval,
+        &mut rand::thread_rng(),
+    )
+}
+
+// temps should be sorted from cold to hot and all above 0, the rng seeds every
+// chain and decides the swaps so a seeded rng makes the run reproducible
+pub fn parallel_tempering_placer_with_rng<'a, R: Rng + ?Sized>(
+    initial_solution: PlacementSolution<'a>,
+    temps: &[f32],
+    n_steps: u32,
+    swap_interval: u32,
+    rng: &mut R,
+) -> TemperingOutput<'a> {
+    assert!(!temps.is_empty(), "need at least one temperature");
+    assert!(
+        temps.iter().all(|temperature| *temperature > 0.0),
+        "temperatures must be above 0"
+    );
+    assert!(swap_interval > 0, "swap_interval must be at least 1");
+
+    let mut chains = temps
+        .iter()
+        .map(|temperature| {
+            let config = PlacerConfig {
+                n_steps,
+                initial_temperature: *temperature,
+                cooling_rate: 1.0,
+                seed: Some(rng.gen()),
+                ..Default::default()
+            };
+            Placer::new(initial_solution.clone(), config)
+        })
+        .collect::<Vec<_>>();
+
+    let mut y_costs = vec![Vec::new(); chains.len()];
+    let n_pairs = chains.len() - 1;
+    let mut swap_attempts = vec![0; n_pairs];
+    let mut swaps_accepted = vec![0; n_pairs];
+
+    let mut step = 0;
+    while step < n_steps {
+        let n_round_steps = swap_interval.min(n_steps - step);
+        chains
+            .par_iter_mut()
+            .zip(y_costs.par_iter_mut())
+            .for_each(|(chain, y_cost)| {
+                for _ in 0..n_round_steps {
+                    y_cost.push(chain.cost(&chain.current_solution));
+                    chain.step();
+                }
+            });
+        step += n_round_steps;
+
+        for pair in 0..n_pairs {
+            let cost_cold = chains[pair].cost(&chains[pair].current_solution);
+            let cost_hot = chains[pair + 1].cost(&chains[pair + 1].current_solution);
+            let exponent = (1.0 / temps[pair] - 1.0 / temps[pair + 1]) * (cost_cold - cost_hot);
+
+            swap_attempts[pair] += 1;
+            if exponent >= 0.0 || rng.gen::<f32>() < exponent.exp() {
+                // through replace_current_solution so each chain's best stays in step
+                let (cold, hot) = chains.split_at_mut(pair + 1);
+                let hot_solution = hot[0].current_solution.clone();
+                let cold_solution = cold[pair].replace_current_solution(hot_solution);
+                hot[0].replace_current_solution(cold_solution);
+                swaps_accepted[pair] += 1;
+            }
+        }
+    }
+
+    let best_chain = (0..chains.len())
+        .min_by(|a, b| {
+            let cost_a = chains[*a].cost(&chains[*a].current_solution);
+            let cost_b = chains[*b].cost(&chains[*b].current_solution);
+            cost_a.total_cmp(&cost_b)
+        })
+        .unwrap();
+    let best = chains
+        .iter()
+        .min_by(|a, b| a.best_cost.total_cmp(&b.best_cost))
+        .unwrap();
+    let (best_solution, best_cost) = (best.best_solution.clone(), best.best_cost);
+
+    TemperingOutput {
+        initial_solution,
+        best_solution,
+        best_cost,
+        final_solution: chains.swap_remove(best_chain).current_solution,
+        temperatures: temps.to_vec(),
+        best_chain,
+        x_steps: (0..n_steps).collect(),
+        y_cost: y_costs.swap_remove(best_chain),
+        swap_attempts,
+        swaps_accepted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::fpga_layout::*;
+    use crate::netlist::*;
+
+    #[test]
+    fn chains_trade_placements_and_keep_the_best() {
+        let layout = build_simple_fpga_layout(12, 12);
+        let nodes = (0..30)
+            .map(|id| NetlistNode {
+                id,
+                macro_type: MacroType::CLB,
+            })
+            .collect::<Vec<_>>();
+        let nets = nodes
+            .windows(2)
+            .map(|pair| pair.to_vec())
+            .collect::<Vec<_>>();
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut rng = StdRng::seed_from_u64(0);
+        let solution = gen_random_placement_with_rng(&layout, &netlist, &mut rng).unwrap();
+
+        let output =
+            parallel_tempering_placer_with_rng(solution, &[0.5, 2.0, 8.0], 200, 10, &mut rng);
+
+        assert!(output.swaps_accepted.iter().sum::<u32>() > 0);
+        assert_eq!(output.best_cost, output.best_solution.weighted_hpwl());
+        assert!(output.best_cost <= output.final_solution.weighted_hpwl());
+        assert!(output.y_cost.iter().all(|cost| output.best_cost <= *cost));
+        assert!(output.best_solution.valid());
+    }
+}
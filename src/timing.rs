@@ -0,0 +1,287 @@
+use rustc_hash::FxHashSet;
+use rustworkx_core::petgraph::graph::NodeIndex;
+use rustworkx_core::petgraph::visit::{depth_first_search, Control, DfsEvent, EdgeRef};
+
+use super::netlist::*;
+use super::placer::*;
+
+// the netlist viewed as a DAG for timing, signals flow from edge source to
+// edge target and every edge costs its manhattan length in delay, scaled by
+// the solution's delay_multipliers for the macro types at its ends
+// combinational loops have no well defined arrival time so the edges that
+// close a loop (the back edges of a depth first search from the lowest node
+// index) are left out, which times every loop as if it were cut at that edge
+#[derive(Debug, Clone)]
+pub struct TimingGraph {
+    // every node, each one after all of its fanin
+    pub order: Vec<NodeIndex>,
+    // fanin of each node indexed by NodeIndex::index(), loop closing edges removed
+    pub fanin: Vec<Vec<NodeIndex>>,
+    // (source, target) pairs whose edges were left out to break loops
+    pub broken_edges: Vec<(NodeIndex, NodeIndex)>,
+}
+
+impl TimingGraph {
+    pub fn new(netlist: &NetlistGraph) -> TimingGraph {
+        let graph = &netlist.graph;
+
+        let mut broken_edges = Vec::new();
+        depth_first_search(graph, graph.node_indices(), |event| {
+            if let DfsEvent::BackEdge(source, target) = event {
+                broken_edges.push((source, target));
+            }
+            Control::<()>::Continue
+        });
+        let broken = broken_edges.iter().copied().collect::<FxHashSet<_>>();
+
+        let mut fanin = vec![Vec::new(); graph.node_count()];
+        let mut fanout = vec![Vec::new(); graph.node_count()];
+        for edge in graph.edge_references() {
+            if broken.contains(&(edge.source(), edge.target())) {
+                continue;
+            }
+            fanin[edge.target().index()].push(edge.source());
+            fanout[edge.source().index()].push(edge.target());
+        }
+
+        // kahn's algorithm, what is left after breaking the loops is acyclic
+        let mut pending = fanin
+            .iter()
+            .map(|sources| sources.len())
+            .collect::<Vec<_>>();
+        let mut order = graph
+            .node_indices()
+            .filter(|node_idx| pending[node_idx.index()] == 0)
+            .collect::<Vec<_>>();
+        let mut next = 0;
+        while next < order.len() {
+            let node_idx = order[next];
+            next += 1;
+            for target in &fanout[node_idx.index()] {
+                pending[target.index()] -= 1;
+                if pending[target.index()] == 0 {
+                    order.push(*target);
+                }
+            }
+        }
+
+        TimingGraph {
+            order,
+            fanin,
+            broken_edges,
+        }
+    }
+
+    // latest arrival time at every node indexed by NodeIndex::index(), nodes
+    // with no fanin start at 0 and an edge with an unplaced end adds no delay
+    pub fn arrival_times(&self, solution: &PlacementSolution) -> Vec<f32> {
+        let mut arrival = vec![0.0; solution.netlist.graph.node_count()];
+
+        for node_idx in &self.order {
+            arrival[node_idx.index()] = self.fanin[node_idx.index()]
+                .iter()
+                .map(|source_idx| {
+                    arrival[source_idx.index()] + edge_delay(solution, *source_idx, *node_idx)
+                })
+                .fold(0.0, f32::max);
+        }
+
+        arrival
+    }
+
+    // latest time a signal can reach every node without delaying the critical
+    // path, indexed by NodeIndex::index(), nodes with no fanout are required
+    // at the critical path delay
+    pub fn required_times(&self, solution: &PlacementSolution, arrival: &[f32]) -> Vec<f32> {
+        let critical_path_delay = arrival.iter().copied().fold(0.0, f32::max);
+        let mut required = vec![critical_path_delay; solution.netlist.graph.node_count()];
+
+        for node_idx in self.order.iter().rev() {
+            for source_idx in &self.fanin[node_idx.index()] {
+                let through_node =
+                    required[node_idx.index()] - edge_delay(solution, *source_idx, *node_idx);
+                if through_node < required[source_idx.index()] {
+                    required[source_idx.index()] = through_node;
+                }
+            }
+        }
+
+        required
+    }
+
+    // delay of the longest path through the netlist
+    pub fn critical_path_delay(&self, solution: &PlacementSolution) -> f32 {
+        self.arrival_times(solution).into_iter().fold(0.0, f32::max)
+    }
+}
+
+// manhattan length of the edge under the layout's topology times the mean
+// delay multiplier of its two ends, 0 when either end is unplaced
+fn edge_delay(solution: &PlacementSolution, source_idx: NodeIndex, target_idx: NodeIndex) -> f32 {
+    let graph = &solution.netlist.graph;
+    let source = &graph[source_idx];
+    let target = &graph[target_idx];
+    match (
+        solution.solution_map.get(source),
+        solution.solution_map.get(target),
+    ) {
+        (Some(source_location), Some(target_location)) => {
+            let multiplier = |node: &NetlistNode| {
+                solution
+                    .delay_multipliers
+                    .get(&node.macro_type)
+                    .copied()
+                    .unwrap_or(1.0)
+            };
+            let distance = solution.layout.distance(source_location, target_location) as f32;
+            distance * (multiplier(source) + multiplier(target)) / 2.0
+        }
+        _ => 0.0,
+    }
+}
+
+// result of a forward and backward timing pass, see PlacementSolution::timing_report
+#[derive(Debug, Clone)]
+pub struct TimingReport {
+    pub critical_path_delay: f32,
+    // indexed by NodeIndex::index()
+    pub arrival: Vec<f32>,
+    pub required: Vec<f32>,
+    // least slack over the edges of each net indexed by NetId, an edge's slack
+    // is how much longer it could get before it lengthens the critical path
+    // nets made up only of edges broken to cut a combinational loop aren't
+    // timed and get f32::INFINITY
+    pub net_slack: Vec<f32>,
+}
+
+impl TimingReport {
+    // 1 - slack / critical path delay per net indexed by NetId, the format
+    // SvgRenderOptions::criticality takes, critical nets are 1.0
+    pub fn net_criticality(&self) -> Vec<f32> {
+        self.net_slack
+            .iter()
+            .map(|slack| {
+                if self.critical_path_delay > 0.0 {
+                    (1.0 - slack / self.critical_path_delay).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+}
+
+impl<'a> PlacementSolution<'a> {
+    // arrival / required times per node and slack per net, loops are cut the
+    // same way as for cost_timing so edges closing a loop are ignored
+    pub fn timing_report(&self) -> TimingReport {
+        let timing_graph = TimingGraph::new(self.netlist);
+        let arrival = timing_graph.arrival_times(self);
+        let required = timing_graph.required_times(self, &arrival);
+        let broken = timing_graph
+            .broken_edges
+            .iter()
+            .copied()
+            .collect::<FxHashSet<_>>();
+
+        let mut net_slack = vec![f32::INFINITY; self.netlist.net_count()];
+        let edge_nets = self.netlist.edge_nets();
+        for edge in self.netlist.graph.edge_references() {
+            if broken.contains(&(edge.source(), edge.target())) {
+                continue;
+            }
+            let slack = required[edge.target().index()]
+                - arrival[edge.source().index()]
+                - edge_delay(self, edge.source(), edge.target());
+            let net = edge_nets[edge.id().index()];
+            net_slack[net] = net_slack[net].min(slack);
+        }
+
+        TimingReport {
+            critical_path_delay: arrival.iter().copied().fold(0.0, f32::max),
+            arrival,
+            required,
+            net_slack,
+        }
+    }
+
+    // render_svg with every net colored by its criticality from timing_report
+    pub fn render_timing_svg(&self) -> String {
+        self.render_svg_with_options(&SvgRenderOptions {
+            criticality: Some(self.timing_report().net_criticality()),
+            ..Default::default()
+        })
+    }
+
+    // critical path delay of the placement, see TimingGraph
+    // builds the timing graph on every call, keep a TimingGraph around when
+    // timing the same netlist repeatedly
+    pub fn cost_timing(&self) -> f32 {
+        TimingGraph::new(self.netlist).critical_path_delay(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fpga_layout::*;
+
+    fn clb(id: u32) -> NetlistNode {
+        NetlistNode {
+            id,
+            macro_type: MacroType::CLB,
+        }
+    }
+
+    #[test]
+    fn chain_slack_matches_hand_computation() {
+        let layout = build_simple_fpga_layout(10, 10);
+        // 0 -> 1 -> 2 is the critical path, 0 -> 3 is a short side branch
+        let nodes = (0..4).map(clb).collect::<Vec<_>>();
+        let nets = vec![
+            vec![clb(0), clb(1)],
+            vec![clb(1), clb(2)],
+            vec![clb(0), clb(3)],
+        ];
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        solution.place_node(clb(0), FPGALayoutCoordinate::new(1, 1));
+        solution.place_node(clb(1), FPGALayoutCoordinate::new(4, 1));
+        solution.place_node(clb(2), FPGALayoutCoordinate::new(4, 3));
+        solution.place_node(clb(3), FPGALayoutCoordinate::new(2, 1));
+
+        let report = solution.timing_report();
+        assert_eq!(report.critical_path_delay, 5.0);
+        assert_eq!(report.arrival, vec![0.0, 3.0, 5.0, 1.0]);
+        assert_eq!(report.required, vec![0.0, 3.0, 5.0, 5.0]);
+        assert_eq!(report.net_slack, vec![0.0, 0.0, 4.0]);
+        assert_eq!(report.net_criticality(), vec![1.0, 1.0, 1.0 - 4.0 / 5.0]);
+    }
+
+    #[test]
+    fn a_path_through_a_slow_type_is_slower() {
+        let layout = build_simple_fpga_layout(16, 16);
+        let bram = NetlistNode {
+            id: 1,
+            macro_type: MacroType::BRAM,
+        };
+        let nodes = vec![clb(0), bram, clb(2)];
+        let nets = vec![vec![clb(0), bram], vec![bram, clb(2)]];
+        let netlist = NetlistGraph::from_nets(&nodes, &nets);
+        let timing_graph = TimingGraph::new(&netlist);
+        let mut solution = PlacementSolution::new(&layout, &netlist);
+        // two hops of 2 sites across the BRAM column at x = 10
+        solution.place_node(clb(0), FPGALayoutCoordinate::new(8, 5));
+        solution.place_node(bram, FPGALayoutCoordinate::new(10, 5));
+        solution.place_node(clb(2), FPGALayoutCoordinate::new(12, 5));
+        assert_eq!(timing_graph.critical_path_delay(&solution), 4.0);
+
+        solution.delay_multipliers.insert(MacroType::CLB, 1.0);
+        solution.delay_multipliers.insert(MacroType::BRAM, 1.0);
+        assert_eq!(timing_graph.critical_path_delay(&solution), 4.0);
+
+        // each hop averages the CLB and BRAM multipliers, (1 + 3) / 2 = 2
+        solution.delay_multipliers.insert(MacroType::BRAM, 3.0);
+        assert_eq!(timing_graph.critical_path_delay(&solution), 8.0);
+    }
+}